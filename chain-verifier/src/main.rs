@@ -0,0 +1,116 @@
+// the errors of error_chain are large and the apis take `&String` throughout
+#![allow(clippy::result_large_err, clippy::ptr_arg)]
+
+use biscuit::{Empty, jwk::JWKSet};
+use core_lib::api::{BlockchainMessage, SignedDocumentReceipt};
+use core_lib::errors::*;
+use core_lib::model::audit::{audit_documents, AuditReport};
+use core_lib::model::document::EncryptedDocument;
+use serde::de::DeserializeOwned;
+use std::env;
+use std::fs;
+use std::process;
+
+const USAGE: &str = "Usage: chain-verifier --documents <file> [--receipts <file> --jwks <file>] [--anchors <file>] [--tsa-certificate <file>]
+
+  --documents <file>        exported process log: JSON array of encrypted documents
+  --receipts <file>         JSON array of signed document receipts
+  --jwks <file>             JWKS of the clearing house, required to verify receipts
+  --anchors <file>          JSON array of the hashes anchored in the ledger
+  --tsa-certificate <file>  DER encoded certificate of the TSA, to verify time-stamp tokens";
+
+/// Input files of the verification
+#[derive(Default)]
+struct Arguments {
+    documents: Option<String>,
+    receipts: Option<String>,
+    jwks: Option<String>,
+    anchors: Option<String>,
+    tsa_certificate: Option<String>,
+}
+
+fn parse_arguments(args: &[String]) -> Result<Arguments>{
+    let mut arguments = Arguments::default();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next(){
+        let target = match arg.as_str(){
+            "--documents" => &mut arguments.documents,
+            "--receipts" => &mut arguments.receipts,
+            "--jwks" => &mut arguments.jwks,
+            "--anchors" => &mut arguments.anchors,
+            "--tsa-certificate" => &mut arguments.tsa_certificate,
+            _ => return Err(Error::from(format!("Unknown argument '{}'", arg)))
+        };
+        match iter.next(){
+            Some(value) => *target = Some(value.clone()),
+            None => return Err(Error::from(format!("Missing file for argument '{}'", arg)))
+        }
+    }
+    if arguments.documents.is_none(){
+        return Err(Error::from("The exported process log is required"))
+    }
+    if arguments.receipts.is_some() && arguments.jwks.is_none(){
+        return Err(Error::from("The JWKS of the clearing house is required to verify receipts"))
+    }
+    Ok(arguments)
+}
+
+fn read_json<T: DeserializeOwned>(file: &Option<String>) -> Result<Option<T>>{
+    match file{
+        Some(f) => {
+            let content = fs::read_to_string(f).chain_err(|| format!("Could not read '{}'", f))?;
+            let value = serde_json::from_str(&content).chain_err(|| format!("Could not parse '{}'", f))?;
+            Ok(Some(value))
+        },
+        None => Ok(None)
+    }
+}
+
+fn verify(arguments: &Arguments) -> Result<AuditReport>{
+    let docs: Vec<EncryptedDocument> = read_json(&arguments.documents)?.unwrap_or_default();
+    let receipts: Vec<SignedDocumentReceipt> = read_json(&arguments.receipts)?.unwrap_or_default();
+    let jwks: Option<JWKSet<Empty>> = read_json(&arguments.jwks)?;
+    let anchors: Vec<BlockchainMessage> = read_json(&arguments.anchors)?.unwrap_or_default();
+    let tsa_certificate = match &arguments.tsa_certificate{
+        Some(f) => Some(fs::read(f).chain_err(|| format!("Could not read '{}'", f))?),
+        None => None
+    };
+    Ok(audit_documents(&docs, &receipts, jwks.as_ref(), &anchors, tsa_certificate.as_deref()))
+}
+
+fn print_report(report: &AuditReport){
+    for check in report.checks.iter(){
+        println!("[{}] {}", if check.passed { "PASS" } else { "FAIL" }, &check.subject);
+        for failure in check.failures.iter(){
+            println!("       {}", failure);
+        }
+    }
+    let failed = report.checks.iter().filter(|c| !c.passed).count();
+    println!();
+    println!("{}: {} checks, {} failed", if report.valid { "PASS" } else { "FAIL" }, report.checks.len(), failed);
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let arguments = match parse_arguments(&args){
+        Ok(arguments) => arguments,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            process::exit(2)
+        }
+    };
+
+    match verify(&arguments){
+        Ok(report) => {
+            print_report(&report);
+            if !report.valid{
+                process::exit(1)
+            }
+        },
+        Err(e) => {
+            let causes: Vec<String> = e.iter().map(|c| c.to_string()).collect();
+            eprintln!("Error: {}", causes.join(": "));
+            process::exit(2)
+        }
+    }
+}
@@ -83,7 +83,7 @@ impl<'r, T: DeserializeOwned + CompactJson + Debug + Clone, H: Serialize + Deser
                     Ok(jwks) => {
                         let auth_header_value: Vec<_> = request
                             .headers()
-                            .get(&DAPS_AUTHHEADER)
+                            .get(DAPS_AUTHHEADER)
                             .collect();
                         debug!("Auth Header: {:?}", &auth_header_value);
                         if auth_header_value.len() != 1 {
@@ -1,9 +1,11 @@
 use reqwest::Client;
+use reqwest::StatusCode;
 use reqwest::header::{HeaderValue, CONTENT_TYPE};
+use rocket::tokio::task;
 use serde_json;
-use crate::api::ApiClient;
+use crate::api::{ApiClient, BlockchainMessage};
+use crate::constants::BLOCKCHAIN_API_URL;
 use crate::errors::*;
-use crate::api::BlockchainMessage;
 
 #[derive(Clone)]
 pub struct BlockchainApiClient {
@@ -17,38 +19,53 @@ impl ApiClient for BlockchainApiClient {
             uri,
         }
     }
+
+    fn get_conf_param() -> String {
+        String::from(BLOCKCHAIN_API_URL)
+    }
 }
 
+// the http client is blocking, so the requests are moved to the blocking thread pool of the runtime
 impl BlockchainApiClient {
-    pub fn get_hash_list(&self, id: &String) -> Result<Vec<BlockchainMessage>>{
+    /// gets all hashes anchored for id
+    pub async fn get_hash_list(&self, id: &String) -> Result<Vec<BlockchainMessage>>{
         let uri = format!("{}/hash/{}", self.uri, id);
-        let client = Client::new();
+        task::spawn_blocking(move || {
+            let client = Client::new();
 
-        debug!("calling {}", &uri);
-        let mut response = client.get(uri.as_str())
-            .header(CONTENT_TYPE, HeaderValue::from_static("application/json"))
-            .send()?;
-        let hash_list: Vec<BlockchainMessage> = response.json()?;
+            debug!("calling {}", &uri);
+            let mut response = client.get(uri.as_str())
+                .header(CONTENT_TYPE, HeaderValue::from_static("application/json"))
+                .send()?;
 
-        debug!("Status Code: {}", &response.status());
-        Ok(hash_list)
+            debug!("Status Code: {}", &response.status());
+            match response.status(){
+                StatusCode::OK => {
+                    let hash_list: Vec<BlockchainMessage> = response.json()?;
+                    Ok(hash_list)
+                }
+                _ => bail!("Error while calling get_hash_list(): status {} content {:?}", response.status(), response.text())
+            }
+        }).await.chain_err(|| "Error while calling get_hash_list()")?
     }
 
-    pub fn store_hash(&self, id: &String, c_id: &String, hash: &String) -> Result<bool>{
+    /// anchors the hash for id and c_id
+    pub async fn store_hash(&self, id: &String, c_id: &String, hash: &String) -> Result<bool>{
         let uri = format!("{}/hash", self.uri);
-        let client = Client::new();
-
         let m = BlockchainMessage::new(id.clone(), c_id.clone(), hash.clone());
         let payload = serde_json::to_string(&m)?;
+        task::spawn_blocking(move || {
+            let client = Client::new();
 
-        debug!("calling {}", &uri);
-        let mut result = client.post(uri.as_str())
-            .header(CONTENT_TYPE, HeaderValue::from_static("application/json"))
-            .body(payload)
-            .send()?;
+            debug!("calling {}", &uri);
+            let mut result = client.post(uri.as_str())
+                .header(CONTENT_TYPE, HeaderValue::from_static("application/json"))
+                .body(payload)
+                .send()?;
 
-        debug!("Status Code: {}", &result.status());
-        debug!("result: {}", result.text()?);
-        Ok(result.status().is_success())
+            debug!("Status Code: {}", &result.status());
+            debug!("result: {}", result.text()?);
+            Ok(result.status().is_success())
+        }).await.chain_err(|| "Error while calling store_hash()")?
     }
-}
\ No newline at end of file
+}
@@ -26,33 +26,33 @@ impl Default for DapsApiClient {
         match cert_path.read_dir(){
             Ok(fs) => {
                 let certs: Vec<Certificate> = fs.filter_map(|entry|
-                    if entry.is_ok(){
-                        if let Ok(mut cert_file) = File::open(entry.as_ref().unwrap().path()){
+                    if let Ok(entry) = entry{
+                        if let Ok(mut cert_file) = File::open(entry.path()){
                             let mut buf = Vec::new();
                             match cert_file.read_to_end(& mut buf){
                                 Ok(_) => {
-                                    debug!("Parsing certificate {} ...", entry.as_ref().unwrap().path().to_str().unwrap_or(""));
+                                    debug!("Parsing certificate {} ...", entry.path().to_str().unwrap_or(""));
                                     if let Ok(cert) = reqwest::Certificate::from_der(&buf){
                                         debug!("... adding certificate as root certificate");
-                                        return Some(cert)
+                                        Some(cert)
                                     }
                                     else{
                                         warn!("... couldn't parse certificate. Not added.");
-                                        return None
+                                        None
                                     }
                                 }
                                 Err(e) => {
-                                    error!("Error while reading certificate file {}: {}", entry.as_ref().unwrap().file_name().to_str().unwrap_or(""), e);
-                                    return None
+                                    error!("Error while reading certificate file {}: {}", entry.file_name().to_str().unwrap_or(""), e);
+                                    None
                                 }
-                            };
+                            }
                         }
                         else{
-                            return None
+                            None
                         }
                     }
                     else{
-                        return None
+                        None
                     })
                     .collect();
                 for c in certs{
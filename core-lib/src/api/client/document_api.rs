@@ -1,11 +1,17 @@
-use reqwest::Client;
+use reqwest::{Body, Client, Response};
 use reqwest::StatusCode;
-use reqwest::header::{HeaderValue, CONTENT_TYPE};
+use reqwest::header::{HeaderValue, ACCEPT, CONTENT_TYPE};
 use serde_json;
-use crate::api::{ApiClient, DocumentReceipt};
-use crate::constants::{ROCKET_DOC_API, DOCUMENT_API_URL};
+use std::io::{BufRead, BufReader, Lines, Read};
+use biscuit::{Empty, jwk::JWKSet};
+use crate::api::{ApiClient, DocumentBatchItem, DocumentFailure, DocumentPage, DocumentQuery, SignedDocumentReceipt};
+use crate::constants::{IDEMPOTENCY_KEY_HEADER, ROCKET_ADMIN_API, ROCKET_DOC_API, ROCKET_JWKS, DOCUMENT_API_URL};
 use crate::errors::*;
-use crate::model::document::Document;
+use crate::model::alert::Alert;
+use crate::model::anchor::AnchorReport;
+use crate::model::chain::ChainReport;
+use crate::model::document::{Document, DocumentPart};
+use crate::model::merkle::{ConsistencyProof, InclusionProof};
 use crate::util::url_encode;
 
 #[derive(Clone)]
@@ -17,7 +23,7 @@ impl ApiClient for DocumentApiClient {
     fn new(uri: &str) -> DocumentApiClient {
         let uri = String::from(uri);
         DocumentApiClient {
-            uri: uri,
+            uri,
         }
     }
 
@@ -48,6 +54,29 @@ impl DocumentApiClient{
         }
     }
 
+    /// Gets the document with only the given parts decrypted
+    pub fn get_document_parts(&self, token: &String, pid: &String, id: &String, parts: &[String]) -> Result<Option<Document>>{
+        let document_url = format!("{}{}/{}/{}", self.uri, ROCKET_DOC_API, url_encode(pid), url_encode(id));
+        let client = Client::new();
+
+        debug!("calling {}", &document_url);
+        let mut response = client
+            .get(document_url.as_str())
+            .header(CONTENT_TYPE, HeaderValue::from_static("application/json"))
+            .query(&[("parts", parts.join(","))])
+            .bearer_auth(token)
+            .send()?;
+
+        debug!("Status Code: {}", &response.status());
+        match response.status(){
+            StatusCode::OK => {
+                let doc: Document = response.json()?;
+                Ok(Some(doc))
+            }
+            _ => Ok(None)
+        }
+    }
+
     pub fn get_document_with_integrity_check(&self, token: &String, pid: &String, id: &String, hash: &String) -> Result<Document>{
         let document_url = format!("{}{}/{}/{}", self.uri, ROCKET_DOC_API, url_encode(pid), url_encode(id));
         let client = Client::new();
@@ -61,14 +90,88 @@ impl DocumentApiClient{
             .send()?;
 
         debug!("Status Code: {}", &response.status());
-        let doc: Document = response.json()?;
-        Ok(doc)
+        match response.status(){
+            StatusCode::OK => {
+                let doc: Document = response.json()?;
+                Ok(doc)
+            }
+            StatusCode::CONFLICT => bail!(ErrorKind::IntegrityViolation(id.clone())),
+            _ => bail!("Error while calling get_document_with_integrity_check(): status {} content {:?}", response.status(), response.text())
+        }
     }
 
-    pub fn get_documents_for_pid(&self, token: &String, pid: &String) -> Result<Vec<Document>>{
+    /// Gets the documents of the process that match the query. With a `limit` only the first page is returned.
+    pub fn get_documents_for_pid(&self, token: &String, pid: &String, query: &DocumentQuery) -> Result<DocumentPage>{
         let document_url = format!("{}{}/{}", self.uri, ROCKET_DOC_API, url_encode(pid));
         let client = Client::new();
 
+        debug!("calling {}", &document_url);
+        let mut response = client
+            .get(document_url.as_str())
+            .header(CONTENT_TYPE, HeaderValue::from_static("application/json"))
+            .query(query)
+            .bearer_auth(token)
+            .send()?;
+
+        debug!("Status Code: {}", &response.status());
+        match response.status(){
+            StatusCode::OK => {
+                let page: DocumentPage = response.json()?;
+                Ok(page)
+            }
+            _ => bail!("Error while calling get_documents_for_pid(): status {} content {:?}", response.status(), response.text())
+        }
+    }
+
+    /// Streams the documents of the process that match the query. The documents are read from the response
+    /// one at a time, so the process is never held in memory as a whole.
+    pub fn stream_documents_for_pid(&self, token: &String, pid: &String, query: &DocumentQuery) -> Result<DocumentStream>{
+        let document_url = format!("{}{}/{}", self.uri, ROCKET_DOC_API, url_encode(pid));
+        let client = Client::new();
+
+        debug!("calling {}", &document_url);
+        let mut response = client
+            .get(document_url.as_str())
+            .header(ACCEPT, HeaderValue::from_static("application/x-ndjson"))
+            .query(query)
+            .bearer_auth(token)
+            .send()?;
+
+        debug!("Status Code: {}", &response.status());
+        match response.status(){
+            StatusCode::OK => Ok(DocumentStream{ lines: BufReader::new(response).lines() }),
+            _ => bail!("Error while calling stream_documents_for_pid(): status {} content {:?}", response.status(), response.text())
+        }
+    }
+
+    /// Iterates through the pages of the documents of the process that match the query, `page_size` documents at a time
+    pub fn pages_for_pid<'a>(&'a self, token: &'a String, pid: &'a String, query: &DocumentQuery, page_size: u32) -> DocumentPages<'a>{
+        let mut query = query.clone();
+        query.limit = Some(page_size);
+        DocumentPages{
+            client: self,
+            token,
+            pid,
+            query,
+            finished: false,
+        }
+    }
+
+    /// Gets all documents of the process that match the query, requesting them page by page
+    pub fn get_all_documents_for_pid(&self, token: &String, pid: &String, query: &DocumentQuery, page_size: u32) -> Result<DocumentPage>{
+        let mut all = DocumentPage::new(vec!(), vec!(), None);
+        for page in self.pages_for_pid(token, pid, query, page_size){
+            let page = page?;
+            all.documents.extend(page.documents);
+            all.failures.extend(page.failures);
+        }
+        Ok(all)
+    }
+
+    pub fn get_inclusion_proof(&self, token: &String, pid: &String, id: &String) -> Result<InclusionProof>{
+        let document_url = format!("{}{}/{}/{}/proof", self.uri, ROCKET_DOC_API, url_encode(pid), url_encode(id));
+        let client = Client::new();
+
         debug!("calling {}", &document_url);
         let mut response = client
             .get(document_url.as_str())
@@ -77,32 +180,263 @@ impl DocumentApiClient{
             .send()?;
 
         debug!("Status Code: {}", &response.status());
-        let docs: Vec<Document> = response.json()?;
-        Ok(docs)
+        match response.status(){
+            StatusCode::OK => {
+                let proof: InclusionProof = response.json()?;
+                Ok(proof)
+            }
+            _ => bail!("Error while calling get_inclusion_proof(): status {} content {:?}", response.status(), response.text())
+        }
     }
 
-    pub fn create_document(&self, token: &String, doc: &Document) -> Result<DocumentReceipt> {
+    /// public keys of the clearing house that are used to sign receipts and tree heads
+    pub fn get_jwks(&self) -> Result<JWKSet<Empty>>{
+        let jwks_url = format!("{}{}", self.uri, ROCKET_JWKS);
+        let client = Client::new();
+
+        debug!("calling {}", &jwks_url);
+        let mut response = client
+            .get(jwks_url.as_str())
+            .header(CONTENT_TYPE, HeaderValue::from_static("application/json"))
+            .send()?;
+
+        debug!("Status Code: {}", &response.status());
+        match response.status(){
+            StatusCode::OK => {
+                let jwks: JWKSet<Empty> = response.json()?;
+                Ok(jwks)
+            }
+            _ => bail!("Error while calling get_jwks(): status {} content {:?}", response.status(), response.text())
+        }
+    }
+
+    pub fn get_tree_head(&self, token: &String, pid: &String) -> Result<String>{
+        let document_url = format!("{}{}/{}/tree_head", self.uri, ROCKET_DOC_API, url_encode(pid));
+        let client = Client::new();
+
+        debug!("calling {}", &document_url);
+        let mut response = client
+            .get(document_url.as_str())
+            .header(CONTENT_TYPE, HeaderValue::from_static("application/json"))
+            .bearer_auth(token)
+            .send()?;
+
+        debug!("Status Code: {}", &response.status());
+        match response.status(){
+            StatusCode::OK => {
+                let sth: String = response.json()?;
+                Ok(sth)
+            }
+            _ => bail!("Error while calling get_tree_head(): status {} content {:?}", response.status(), response.text())
+        }
+    }
+
+    pub fn get_consistency_proof(&self, token: &String, pid: &String, first: usize, second: Option<usize>) -> Result<ConsistencyProof>{
+        let mut document_url = format!("{}{}/{}/consistency?first={}", self.uri, ROCKET_DOC_API, url_encode(pid), first);
+        if let Some(s) = second{
+            document_url = format!("{}&second={}", document_url, s);
+        }
+        let client = Client::new();
+
+        debug!("calling {}", &document_url);
+        let mut response = client
+            .get(document_url.as_str())
+            .header(CONTENT_TYPE, HeaderValue::from_static("application/json"))
+            .bearer_auth(token)
+            .send()?;
+
+        debug!("Status Code: {}", &response.status());
+        match response.status(){
+            StatusCode::OK => {
+                let proof: ConsistencyProof = response.json()?;
+                Ok(proof)
+            }
+            _ => bail!("Error while calling get_consistency_proof(): status {} content {:?}", response.status(), response.text())
+        }
+    }
+
+    /// alerts recorded by the tamper detection of the document api, optionally only for a single process
+    pub fn get_alerts(&self, token: &String, pid: Option<&String>) -> Result<Vec<Alert>>{
+        let mut alerts_url = format!("{}{}/alerts", self.uri, ROCKET_ADMIN_API);
+        if let Some(p) = pid{
+            alerts_url = format!("{}?pid={}", alerts_url, url_encode(p));
+        }
+        let client = Client::new();
+
+        debug!("calling {}", &alerts_url);
+        let mut response = client
+            .get(alerts_url.as_str())
+            .header(CONTENT_TYPE, HeaderValue::from_static("application/json"))
+            .bearer_auth(token)
+            .send()?;
+
+        debug!("Status Code: {}", &response.status());
+        match response.status(){
+            StatusCode::OK => {
+                let alerts: Vec<Alert> = response.json()?;
+                Ok(alerts)
+            }
+            _ => bail!("Error while calling get_alerts(): status {} content {:?}", response.status(), response.text())
+        }
+    }
+
+    pub fn compare_anchors(&self, token: &String, pid: &String) -> Result<AnchorReport>{
+        let document_url = format!("{}{}/{}/anchors", self.uri, ROCKET_DOC_API, url_encode(pid));
+        let client = Client::new();
+
+        debug!("calling {}", &document_url);
+        let mut response = client
+            .get(document_url.as_str())
+            .header(CONTENT_TYPE, HeaderValue::from_static("application/json"))
+            .bearer_auth(token)
+            .send()?;
+
+        debug!("Status Code: {}", &response.status());
+        match response.status(){
+            StatusCode::OK => {
+                let report: AnchorReport = response.json()?;
+                Ok(report)
+            }
+            _ => bail!("Error while calling compare_anchors(): status {} content {:?}", response.status(), response.text())
+        }
+    }
+
+    pub fn verify_chain(&self, token: &String, pid: &String) -> Result<ChainReport>{
+        let document_url = format!("{}{}/{}/verify", self.uri, ROCKET_DOC_API, url_encode(pid));
+        let client = Client::new();
+
+        debug!("calling {}", &document_url);
+        let mut response = client
+            .get(document_url.as_str())
+            .header(CONTENT_TYPE, HeaderValue::from_static("application/json"))
+            .bearer_auth(token)
+            .send()?;
+
+        debug!("Status Code: {}", &response.status());
+        match response.status(){
+            StatusCode::OK => {
+                let report: ChainReport = response.json()?;
+                Ok(report)
+            }
+            _ => bail!("Error while calling verify_chain(): status {} content {:?}", response.status(), response.text())
+        }
+    }
+
+    pub fn create_document(&self, token: &String, doc: &Document) -> Result<SignedDocumentReceipt> {
+        self.send_document(token, doc, None)
+    }
+
+    /// Stores the document unless a request with the same idempotency key already stored it.
+    /// Retries of the request return the receipt of the stored document.
+    pub fn create_document_with_idempotency_key(&self, token: &String, doc: &Document, key: &str) -> Result<SignedDocumentReceipt> {
+        self.send_document(token, doc, Some(key))
+    }
+
+    fn send_document(&self, token: &String, doc: &Document, idempotency_key: Option<&str>) -> Result<SignedDocumentReceipt> {
         let document_url = format!("{}{}", self.uri, ROCKET_DOC_API);
         let client = Client::new();
 
         let json_data = serde_json::to_string(doc)?;
         debug!("calling {}", &document_url);
+        let mut request = client
+            .post(document_url.as_str())
+            .header(CONTENT_TYPE, HeaderValue::from_static("application/json"))
+            .bearer_auth(token);
+        if let Some(key) = idempotency_key {
+            request = request.header(IDEMPOTENCY_KEY_HEADER, key);
+        }
+        let mut response = request.body(json_data).send()?;
+
+        debug!("Status Code: {}", &response.status());
+        match &response.status(){
+            // the document was stored by an earlier attempt of the request
+            &StatusCode::CREATED | &StatusCode::OK => {
+                let receipt: SignedDocumentReceipt = response.json()?;
+                println!("Payload: {:?}", receipt);
+                Ok(receipt)
+            },
+            _ => bail!("Error while calling create_document(): status {} content {:?}", response.status(), response.text())
+        }
+
+    }
+
+    /// Stores a batch of documents of the process in the given order.
+    /// Returns the receipt or the error of each document in the same order, also if no document was stored.
+    pub fn create_documents(&self, token: &String, pid: &String, docs: &[Document]) -> Result<Vec<DocumentBatchItem>> {
+        let document_url = format!("{}{}/{}/batch", self.uri, ROCKET_DOC_API, url_encode(pid));
+        let client = Client::new();
+
+        let json_data = serde_json::to_string(docs)?;
+        debug!("calling {}", &document_url);
         let mut response = client
             .post(document_url.as_str())
             .header(CONTENT_TYPE, HeaderValue::from_static("application/json"))
             .bearer_auth(token)
             .body(json_data).send()?;
 
+        debug!("Status Code: {}", &response.status());
+        match &response.status(){
+            &StatusCode::CREATED | &StatusCode::MULTI_STATUS => {
+                let items: Vec<DocumentBatchItem> = response.json()?;
+                Ok(items)
+            },
+            status => {
+                // the errors of the documents are returned, if the batch was processed
+                let content = response.text()?;
+                match serde_json::from_str::<Vec<DocumentBatchItem>>(&content){
+                    Ok(items) => Ok(items),
+                    Err(_) => bail!("Error while calling create_documents(): status {} content {:?}", status, content)
+                }
+            }
+        }
+    }
+
+    /// Stores a document whose payload is streamed from the reader and stored in chunks by the document api.
+    /// The other parts of the document are sent as query parameters.
+    pub fn create_document_with_large_payload<R: Read + Send + 'static>(&self, token: &String, pid: &String, dt_id: &String, parts: &[DocumentPart], payload: R) -> Result<SignedDocumentReceipt> {
+        let document_url = format!("{}{}/{}/large", self.uri, ROCKET_DOC_API, url_encode(pid));
+        let client = Client::new();
+
+        let mut query = vec!((String::from("dt_id"), dt_id.clone()));
+        for part in parts.iter(){
+            if let Some(content) = part.content.as_ref(){
+                query.push((part.name.clone(), content.clone()));
+            }
+        }
+        debug!("calling {}", &document_url);
+        let mut response = client
+            .post(document_url.as_str())
+            .header(CONTENT_TYPE, HeaderValue::from_static("application/octet-stream"))
+            .query(&query)
+            .bearer_auth(token)
+            .body(Body::new(payload)).send()?;
+
         debug!("Status Code: {}", &response.status());
         match &response.status(){
             &StatusCode::CREATED => {
-                let receipt = response.json()?;
-                println!("Payload: {:?}", receipt);
+                let receipt: SignedDocumentReceipt = response.json()?;
                 Ok(receipt)
             },
-            _ => bail!("Error while calling create_document(): status {} content {:?}", response.status(), response.text())
+            _ => bail!("Error while calling create_document_with_large_payload(): status {} content {:?}", response.status(), response.text())
         }
+    }
+
+    /// Retrieves the payload of a document that is stored in chunks. The payload is streamed while it is read.
+    pub fn get_large_payload(&self, token: &String, pid: &String, id: &String) -> Result<impl Read>{
+        let document_url = format!("{}{}/{}/{}/payload", self.uri, ROCKET_DOC_API, url_encode(pid), url_encode(id));
+        let client = Client::new();
+
+        debug!("calling {}", &document_url);
+        let mut response = client
+            .get(document_url.as_str())
+            .bearer_auth(token)
+            .send()?;
 
+        debug!("Status Code: {}", &response.status());
+        match response.status(){
+            StatusCode::OK => Ok(response),
+            _ => bail!("Error while calling get_large_payload(): status {} content {:?}", response.status(), response.text())
+        }
     }
 
     pub fn delete_document(&self, token: &String, pid: &String, id: &String) -> Result<bool>{
@@ -119,4 +453,65 @@ impl DocumentApiClient{
         debug!("Status Code: {}", &response.status());
         Ok(response.status().is_success())
     }
- }
\ No newline at end of file
+ }
+/// Iterator over the pages of the documents of a process. Iteration stops after the last page or the first error.
+pub struct DocumentPages<'a>{
+    client: &'a DocumentApiClient,
+    token: &'a String,
+    pid: &'a String,
+    query: DocumentQuery,
+    finished: bool,
+}
+
+impl<'a> Iterator for DocumentPages<'a>{
+    type Item = Result<DocumentPage>;
+
+    fn next(&mut self) -> Option<Result<DocumentPage>>{
+        if self.finished{
+            return None
+        }
+        match self.client.get_documents_for_pid(self.token, self.pid, &self.query){
+            Ok(page) => {
+                match page.next_after_tc{
+                    Some(next) => self.query.after_tc = Some(next),
+                    None => self.finished = true
+                }
+                Some(Ok(page))
+            },
+            Err(e) => {
+                self.finished = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+/// Documents read from an NDJSON response, one document per line.
+/// Documents that could not be decrypted by the document api are returned as error.
+pub struct DocumentStream{
+    lines: Lines<BufReader<Response>>,
+}
+
+/// Line of an NDJSON response: a document or the reason why a document could not be decrypted
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum DocumentLine{
+    Failure{ failure: DocumentFailure },
+    Document(Document),
+}
+
+impl Iterator for DocumentStream{
+    type Item = Result<Document>;
+
+    fn next(&mut self) -> Option<Result<Document>>{
+        let line = match self.lines.next()?{
+            Ok(line) => line,
+            Err(e) => return Some(Err(Error::from(e)))
+        };
+        match serde_json::from_str(&line){
+            Ok(DocumentLine::Document(doc)) => Some(Ok(doc)),
+            Ok(DocumentLine::Failure{ failure }) => Some(Err(format!("Document {} with tc {} could not be decrypted: {}", failure.id, failure.tc, failure.reason).into())),
+            Err(e) => Some(Err(Error::from(e)))
+        }
+    }
+}
@@ -1,9 +1,10 @@
-use reqwest::Client;
-use reqwest::header::{CONTENT_TYPE, HeaderValue};
+use reqwest::{Client, StatusCode};
+use reqwest::header::{ACCEPT, CONTENT_TYPE, HeaderValue};
 use crate::api::ApiClient;
 use crate::errors::*;
-use crate::constants::{ROCKET_KEYRING_API, KEYRING_API_URL};
+use crate::constants::{ROCKET_DOC_TYPE_API, ROCKET_KEYRING_API, KEYRING_API_URL};
 use crate::model::crypto::{KeyMap, KeyMapListItem, KeyCtList};
+use crate::model::doc_type::DocumentType;
 
 #[derive(Clone)]
 pub struct KeyringApiClient {
@@ -41,16 +42,55 @@ impl KeyringApiClient {
         Ok(key_map)
     }
 
+    /// Calls the keyring api to generate new aes keys for multiple documents at once.
+    /// Returns one key map for each of the document types in the same order.
+    pub fn generate_multiple_keys(&self, token: &String, pid: &str, dt_ids: &[String]) -> Result<Vec<KeyMap>> {
+        let keys_url = format!("{}{}/generate_keys/{}", self.uri, ROCKET_KEYRING_API, pid);
+        let client = Client::new();
+
+        let json_data = serde_json::to_string(dt_ids)?;
+
+        debug!("calling {}", &keys_url);
+        let mut result = client.post(keys_url.as_str())
+            .header(CONTENT_TYPE, HeaderValue::from_static("application/json"))
+            .bearer_auth(token)
+            .body(json_data)
+            .send()?;
+
+        debug!("Status Code: {}", result.status());
+        if !result.status().is_success() {
+            bail!("Error while calling generate_multiple_keys(): status {} content {:?}", result.status(), result.text())
+        }
+        let key_maps: Vec<KeyMap> = result.json()?;
+        if key_maps.len() != dt_ids.len() {
+            bail!("Received {} key maps for {} documents", key_maps.len(), dt_ids.len())
+        }
+        Ok(key_maps)
+    }
+
     /// Calls the keyring api to decrypt aes keys
     pub fn decrypt_keys(&self, token: &String, pid: &str, dt_id: &str, ct: &[u8]) -> Result<KeyMap>{
+        self.restore_keys(token, pid, dt_id, ct, None)
+    }
+
+    /// Calls the keyring api to decrypt only the aes keys of the given parts
+    pub fn decrypt_part_keys(&self, token: &String, pid: &str, dt_id: &str, ct: &[u8], parts: &[String]) -> Result<KeyMap>{
+        self.restore_keys(token, pid, dt_id, ct, Some(parts))
+    }
+
+    fn restore_keys(&self, token: &String, pid: &str, dt_id: &str, ct: &[u8], parts: Option<&[String]>) -> Result<KeyMap>{
         let keys_url = format!("{}{}/decrypt_keys/{}/{}", self.uri, ROCKET_KEYRING_API, pid, hex::encode_upper(ct));
         let client = Client::new();
 
+        let mut query = vec!(("dt_id", String::from(dt_id)));
+        if let Some(parts) = parts {
+            query.push(("parts", parts.join(",")));
+        }
         debug!("calling {}", &keys_url);
         let mut result = client.get(keys_url.as_str())
             .header(CONTENT_TYPE, HeaderValue::from_static("application/json"))
             .bearer_auth(token)
-            .query(&[("dt_id", dt_id)])
+            .query(&query)
             .send()?;
 
         debug!("Status Code: {}", &result.status());
@@ -78,4 +118,31 @@ impl KeyringApiClient {
         debug!("Payload: {:?}", key_maps);
         Ok(key_maps)
     }
-}
\ No newline at end of file
+
+    /// Calls the keyring api to get the document type. Returns `None` if the document type does not exist.
+    pub fn get_document_type(&self, token: &String, pid: &str, dt_id: &str) -> Result<Option<DocumentType>>{
+        let dt_url = format!("{}{}/{}/{}", self.uri, ROCKET_DOC_TYPE_API, pid, dt_id);
+        let client = Client::new();
+
+        debug!("calling {}", &dt_url);
+        let mut result = client.get(dt_url.as_str())
+            .header(CONTENT_TYPE, HeaderValue::from_static("application/json"))
+            .header(ACCEPT, HeaderValue::from_static("application/json"))
+            .bearer_auth(token)
+            .send()?;
+
+        debug!("Status Code: {}", &result.status());
+        match result.status(){
+            StatusCode::OK => {
+                let dt: Option<DocumentType> = result.json()?;
+                debug!("Payload: {:?}", dt);
+                Ok(dt)
+            }
+            StatusCode::NOT_FOUND => {
+                debug!("Document type '{}' not found", dt_id);
+                Ok(None)
+            }
+            _ => bail!("Error while calling get_document_type(): status {} content {:?}", result.status(), result.text())
+        }
+    }
+}
@@ -1,17 +1,21 @@
 use rocket::fairing::{self, Fairing, Info, Kind};
 use rocket::{Rocket, Build};
 use crate::api::ApiClient;
+use crate::api::client::blockchain_api::BlockchainApiClient;
 use crate::api::client::daps_api::DapsApiClient;
 use crate::api::client::keyring_api::KeyringApiClient;
 use crate::api::client::document_api::DocumentApiClient;
 
+pub mod blockchain_api;
 pub mod document_api;
 pub mod keyring_api;
 pub mod daps_api;
+pub mod tsa_api;
 
 
 #[derive(Clone, Debug)]
 pub enum ApiClientEnum{
+    Blockchain,
     Daps,
     Document,
     Keyring
@@ -34,6 +38,12 @@ impl ApiClientConfigurator{
 impl Fairing for ApiClientConfigurator {
     fn info(&self) -> Info {
         match self.api {
+            ApiClientEnum::Blockchain => {
+                Info {
+                    name: "Configuring Blockchain Api Client",
+                    kind: Kind::Ignite
+                }
+            },
             ApiClientEnum::Daps => {
                 Info {
                     name: "Configuring Daps Api Client",
@@ -57,6 +67,10 @@ impl Fairing for ApiClientConfigurator {
 
     async fn on_ignite(&self, rocket: Rocket<Build>) -> fairing::Result {
         let config_key = match self.api {
+            ApiClientEnum::Blockchain => {
+                debug!("Configuring Blockchain Api Client...");
+                BlockchainApiClient::get_conf_param()
+            },
             ApiClientEnum::Daps => {
                 debug!("Configuring Daps Api Client...");
                 DapsApiClient::get_conf_param()
@@ -70,10 +84,14 @@ impl Fairing for ApiClientConfigurator {
                 KeyringApiClient::get_conf_param()
             }
         };
-        let api_url: String = rocket.figment().extract_inner(&config_key).unwrap_or(String::new());
-        if api_url.len() > 0 {
+        let api_url: String = rocket.figment().extract_inner(&config_key).unwrap_or_default();
+        if !api_url.is_empty() {
             debug!("...found api url: {}", &api_url);
             match self.api {
+                ApiClientEnum::Blockchain => {
+                    let client: BlockchainApiClient = ApiClient::new(&api_url);
+                    Ok(rocket.manage(client))
+                },
                 ApiClientEnum::Daps => {
                     let client: DapsApiClient = ApiClient::new(&api_url);
                     Ok(rocket.manage(client))
@@ -0,0 +1,57 @@
+use reqwest::Client;
+use reqwest::StatusCode;
+use reqwest::header::{HeaderValue, CONTENT_TYPE};
+use rocket::tokio::task;
+use crate::api::ApiClient;
+use crate::constants::TSA_URL;
+use crate::errors::*;
+use crate::timestamp::{create_timestamp_request, read_timestamp_response};
+
+/// Client of an RFC 3161 time-stamp authority
+#[derive(Clone)]
+pub struct TsaApiClient {
+    uri: String,
+}
+
+impl ApiClient for TsaApiClient {
+    fn new(uri: &str) -> TsaApiClient {
+        let uri = String::from(uri);
+        TsaApiClient {
+            uri,
+        }
+    }
+
+    fn get_conf_param() -> String {
+        String::from(TSA_URL)
+    }
+}
+
+impl TsaApiClient {
+    /// Requests a time-stamp token over the chain hash.
+    /// Returns the DER encoded token and the nonce of the request.
+    pub async fn get_timestamp_token(&self, chain_hash: &str) -> Result<(Vec<u8>, String)>{
+        let uri = self.uri.clone();
+        let (request, nonce) = create_timestamp_request(chain_hash)?;
+        // the http client is blocking, so the request is moved to the blocking thread pool of the runtime
+        let response = task::spawn_blocking(move || -> Result<Vec<u8>> {
+            let client = Client::new();
+
+            debug!("calling {}", &uri);
+            let mut response = client.post(uri.as_str())
+                .header(CONTENT_TYPE, HeaderValue::from_static("application/timestamp-query"))
+                .body(request)
+                .send()?;
+
+            debug!("Status Code: {}", &response.status());
+            match response.status(){
+                StatusCode::OK => {
+                    let mut body = vec!();
+                    response.copy_to(&mut body)?;
+                    Ok(body)
+                }
+                _ => bail!("Error while calling get_timestamp_token(): status {} content {:?}", response.status(), response.text())
+            }
+        }).await.chain_err(|| "Error while calling get_timestamp_token()")??;
+        Ok((read_timestamp_response(&response)?, nonce))
+    }
+}
@@ -1,9 +1,16 @@
+use biscuit::{CompactJson, Empty, jwk::JWKSet};
 use std::string::ToString;
 use rocket::serde::json::Value;
+use crate::errors::*;
+use crate::signing::verify_signature;
+use crate::timestamp::TimestampToken;
 
 pub mod auth;
 pub mod claims;
 pub mod client;
+pub mod query;
+
+pub use query::{DocumentFailure, DocumentPage, DocumentQuery, SortField, SortOrder};
 
 pub trait ApiClient{
     fn new(url: &str) -> Self;
@@ -22,29 +29,123 @@ pub enum ApiResponse {
     SuccessOk(Value),
     #[response(status = 204, content_type = "json")]
     SuccessNoContent(String),
+    #[response(status = 207, content_type = "json")]
+    MultiStatus(Value),
     #[response(status = 401, content_type = "json")]
     Unauthorized(String),
     #[response(status = 404, content_type = "json")]
     NotFound(String),
+    #[response(status = 409, content_type = "json")]
+    IntegrityViolation(String),
     #[response(status = 500, content_type = "json")]
     InternalError(String),
 }
 
-#[derive(Clone, Serialize, Deserialize, Debug)]
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
 pub struct DocumentReceipt{
     pub timestamp: i64,
     pub pid: String,
     pub doc_id: String,
+    pub tc: i64,
     pub chain_hash: String,
+    /// hash of the document itself, i.e. the chain hash of its successor. Empty in receipts of earlier versions.
+    #[serde(default)]
+    pub doc_hash: String,
 }
 
 impl DocumentReceipt{
-    pub fn new(timestamp: i64, pid: &str, doc_id: &str, chain_hash: &str) -> DocumentReceipt{
+    pub fn new(timestamp: i64, pid: &str, doc_id: &str, tc: i64, chain_hash: &str, doc_hash: &str) -> DocumentReceipt{
         DocumentReceipt{
             timestamp,
             pid: pid.to_string(),
             doc_id: doc_id.to_string(),
+            tc,
             chain_hash: chain_hash.to_string(),
+            doc_hash: doc_hash.to_string(),
+        }
+    }
+}
+
+impl CompactJson for DocumentReceipt {
+}
+
+/// Hash of a process anchored in the ledger of the blockchain api.
+/// `id` is the pid of the process and `c_id` the tc of the document whose chain hash is anchored.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct BlockchainMessage{
+    pub id: String,
+    pub c_id: String,
+    pub hash: String,
+}
+
+impl BlockchainMessage{
+    pub fn new(id: String, c_id: String, hash: String) -> BlockchainMessage{
+        BlockchainMessage{
+            id,
+            c_id,
+            hash,
+        }
+    }
+}
+
+/// Receipt of a stored document together with the JWS of the receipt signed by the clearing house
+/// and, if the document api is configured with a TSA, the time-stamp token of the new chain hash
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct SignedDocumentReceipt{
+    pub receipt: DocumentReceipt,
+    pub signature: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp_token: Option<TimestampToken>,
+}
+
+impl SignedDocumentReceipt{
+    pub fn new(receipt: DocumentReceipt, signature: String, timestamp_token: Option<TimestampToken>) -> SignedDocumentReceipt{
+        SignedDocumentReceipt{
+            receipt,
+            signature,
+            timestamp_token,
+        }
+    }
+
+    /// checks the signature against the JWKS of the clearing house and that it was issued for this receipt
+    pub fn verify(&self, jwks: &JWKSet<Empty>) -> Result<DocumentReceipt>{
+        let signed_receipt = verify_receipt(&self.signature, jwks)?;
+        if signed_receipt != self.receipt{
+            bail!("Signature was not issued for receipt of document {}", &self.receipt.doc_id)
+        }
+        Ok(signed_receipt)
+    }
+}
+
+/// Result of a single document of a batch: the receipt of the stored document or the reason why it was not stored
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct DocumentBatchItem{
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub receipt: Option<SignedDocumentReceipt>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl DocumentBatchItem{
+    pub fn success(id: String, receipt: SignedDocumentReceipt) -> DocumentBatchItem{
+        DocumentBatchItem{
+            id,
+            receipt: Some(receipt),
+            error: None,
+        }
+    }
+
+    pub fn failure(id: String, error: String) -> DocumentBatchItem{
+        DocumentBatchItem{
+            id,
+            receipt: None,
+            error: Some(error),
         }
     }
 }
+
+/// Verifies the JWS of a receipt against the JWKS of the clearing house and returns the signed receipt
+pub fn verify_receipt(jws: &str, jwks: &JWKSet<Empty>) -> Result<DocumentReceipt>{
+    verify_signature(jws, jwks)
+}
@@ -0,0 +1,104 @@
+use crate::model::document::Document;
+use crate::util::split_list;
+
+/// Field by which the documents of a process are sorted. Documents with the same `ts` are sorted by `tc`.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, FromFormField)]
+#[serde(rename_all = "lowercase")]
+#[derive(Default)]
+pub enum SortField{
+    #[field(value = "tc")]
+    #[default]
+    Tc,
+    #[field(value = "ts")]
+    Ts,
+}
+
+
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, FromFormField)]
+#[serde(rename_all = "lowercase")]
+#[derive(Default)]
+pub enum SortOrder{
+    #[field(value = "asc")]
+    #[default]
+    Asc,
+    #[field(value = "desc")]
+    Desc,
+}
+
+
+/// Query for the documents of a process. Without `limit` all matching documents are returned at once,
+/// otherwise pages of at most `limit` documents, each starting after the document with tc `after_tc`.
+/// `from` and `to` restrict the `ts` of the documents to `from <= ts < to`.
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq, FromForm)]
+pub struct DocumentQuery{
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub doc_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub after_tc: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sort: Option<SortField>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub order: Option<SortOrder>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub to: Option<i64>,
+    /// comma separated names of the parts that are decrypted, all parts if not given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parts: Option<String>,
+}
+
+impl DocumentQuery{
+    pub fn sort_field(&self) -> SortField{
+        self.sort.unwrap_or_default()
+    }
+
+    pub fn sort_order(&self) -> SortOrder{
+        self.order.unwrap_or_default()
+    }
+
+    pub fn part_names(&self) -> Option<Vec<String>>{
+        self.parts.as_ref().map(|parts| split_list(parts))
+    }
+}
+
+/// Documents of a process matching a query. If more documents match, `next_after_tc` is the cursor of the next page.
+/// Documents of the page that could not be decrypted are listed in `failures` instead.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct DocumentPage{
+    pub documents: Vec<Document>,
+    #[serde(default)]
+    pub failures: Vec<DocumentFailure>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_after_tc: Option<i64>,
+}
+
+impl DocumentPage{
+    pub fn new(documents: Vec<Document>, failures: Vec<DocumentFailure>, next_after_tc: Option<i64>) -> DocumentPage{
+        DocumentPage{
+            documents,
+            failures,
+            next_after_tc,
+        }
+    }
+}
+
+/// Stored document that could not be decrypted and the reason why
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct DocumentFailure{
+    pub id: String,
+    pub tc: i64,
+    pub reason: String,
+}
+
+impl DocumentFailure{
+    pub fn new(id: String, tc: i64, reason: String) -> DocumentFailure{
+        DocumentFailure{
+            id,
+            tc,
+            reason,
+        }
+    }
+}
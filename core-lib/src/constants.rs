@@ -1,73 +1,113 @@
 // definition of daps constants
-pub const DAPS_AUD: &'static str = "idsc:IDS_CONNECTORS_ALL";
+pub const DAPS_AUD: &str = "idsc:IDS_CONNECTORS_ALL";
 //pub const DAPS_JWKS: &'static str = ".well-known/jwks.json";
-pub const DAPS_JWKS: &'static str = "auth/realms/Daekin/protocol/openid-connect/certs";
+pub const DAPS_JWKS: &str = "auth/realms/Daekin/protocol/openid-connect/certs";
 //pub const DAPS_KID: &'static str = "default";
-pub const DAPS_KID: &'static str = "LFOHnttpacm2iKEaJL-rSiIiELYLlI1FlfBP_gu32hw";
-pub const DAPS_AUTHHEADER: &'static str = "Authorization";
-pub const DAPS_AUTHBEARER: &'static str = "Bearer";
-pub const DAPS_CERTIFICATES: &'static str = "certs";
+pub const DAPS_KID: &str = "LFOHnttpacm2iKEaJL-rSiIiELYLlI1FlfBP_gu32hw";
+pub const DAPS_AUTHHEADER: &str = "Authorization";
+pub const DAPS_AUTHBEARER: &str = "Bearer";
+pub const DAPS_CERTIFICATES: &str = "certs";
+
+// definition of request headers
+pub const IDEMPOTENCY_KEY_HEADER: &str = "Idempotency-Key";
 
 // definition of config parameters (in config files)
-pub const DATABASE_URL: &'static str = "database_url";
-pub const DOCUMENT_API_URL: &'static str = "document_api_url";
-pub const KEYRING_API_URL: &'static str = "keyring_api_url";
-pub const DAPS_API_URL: &'static str = "daps_api_url";
-pub const CLEAR_DB: &'static str = "clear_db";
+pub const DATABASE_URL: &str = "database_url";
+pub const DOCUMENT_API_URL: &str = "document_api_url";
+pub const KEYRING_API_URL: &str = "keyring_api_url";
+pub const DAPS_API_URL: &str = "daps_api_url";
+pub const BLOCKCHAIN_API_URL: &str = "blockchain_api_url";
+pub const ANCHOR_INTERVAL: &str = "anchor_interval";
+pub const TSA_URL: &str = "tsa_url";
+pub const TSA_CERTIFICATE: &str = "tsa_certificate";
+pub const SCAN_INTERVAL: &str = "scan_interval";
+pub const ADMINS: &str = "admins";
+pub const CLEAR_DB: &str = "clear_db";
+pub const SIGNING_KEY: &str = "signing_key";
+pub const HASH_ALGORITHM: &str = "hash_algorithm";
+pub const CONNECTOR_ID: &str = "connector_id";
 
 // define here the config options from environment variables
-pub const ENV_API_LOG_LEVEL: &'static str = "API_LOG_LEVEL";
+pub const ENV_API_LOG_LEVEL: &str = "API_LOG_LEVEL";
 
 // definition of rocket mount points
-pub const ROCKET_DOC_API: &'static str = "/doc";
-pub const ROCKET_DOC_TYPE_API: &'static str = "/doctype";
-pub const ROCKET_POLICY_API: &'static str = "/policy";
-pub const ROCKET_STATISTICS: &'static str = "/statistics";
-pub const ROCKET_PROCESS_API: &'static str = "/process";
-pub const ROCKET_KEYRING_API: &'static str = "/keyring";
-pub const ROCKET_USER_API: &'static str = "/users";
+pub const ROCKET_DOC_API: &str = "/doc";
+pub const ROCKET_DOC_TYPE_API: &str = "/doctype";
+pub const ROCKET_POLICY_API: &str = "/policy";
+pub const ROCKET_STATISTICS: &str = "/statistics";
+pub const ROCKET_PROCESS_API: &str = "/process";
+pub const ROCKET_KEYRING_API: &str = "/keyring";
+pub const ROCKET_USER_API: &str = "/users";
+pub const ROCKET_JWKS: &str = "/.well-known/jwks.json";
+pub const ROCKET_ADMIN_API: &str = "/admin";
 
 // definition of database clients
-pub const DOCUMENT_DB_CLIENT: &'static str = "document-api";
-pub const KEYRING_DB_CLIENT: &'static str = "keyring-api";
-pub const PROCESS_DB_CLIENT: &'static str = "clearing-house-api";
+pub const DOCUMENT_DB_CLIENT: &str = "document-api";
+pub const KEYRING_DB_CLIENT: &str = "keyring-api";
+pub const PROCESS_DB_CLIENT: &str = "clearing-house-api";
 
 // definition of table names
-pub const MONGO_DB: &'static str = "ch_ids";
-pub const DOCUMENT_DB: &'static str = "document";
-pub const KEYRING_DB: &'static str = "keyring";
-pub const PROCESS_DB: &'static str = "process";
-pub const MONGO_COLL_DOCUMENTS: &'static str = "documents";
-pub const MONGO_COLL_DOC_TYPES: &'static str = "doc_types";
-pub const MONGO_COLL_DOC_PARTS: &'static str = "parts";
-pub const MONGO_COLL_PROCESSES: &'static str = "processes";
-pub const MONGO_COLL_TRANSACTIONS: &'static str = "transactions";
-pub const MONGO_COLL_MASTER_KEY: &'static str = "keys";
+pub const MONGO_DB: &str = "ch_ids";
+pub const DOCUMENT_DB: &str = "document";
+pub const KEYRING_DB: &str = "keyring";
+pub const PROCESS_DB: &str = "process";
+pub const MONGO_COLL_DOCUMENTS: &str = "documents";
+pub const MONGO_COLL_DOC_TYPES: &str = "doc_types";
+pub const MONGO_COLL_DOC_PARTS: &str = "parts";
+pub const MONGO_COLL_PROCESSES: &str = "processes";
+pub const MONGO_COLL_TRANSACTIONS: &str = "transactions";
+pub const MONGO_COLL_MERKLE_LEAVES: &str = "merkle_leaves";
+pub const MONGO_COLL_ALERTS: &str = "alerts";
+pub const MONGO_COLL_ANCHORS: &str = "anchors";
+pub const MONGO_COLL_MASTER_KEY: &str = "keys";
+pub const MONGO_BUCKET_PAYLOADS: &str = "payloads";
 
 // definition of database fields
-pub const MONGO_ID: &'static str = "id";
-pub const MONGO_MKEY: &'static str = "msk";
-pub const MONGO_PID: &'static str = "pid";
-pub const MONGO_DT_ID: &'static str = "dt_id";
-pub const MONGO_NAME: &'static str = "name";
-pub const MONGO_OWNER: &'static str = "owner";
-pub const MONGO_TS: &'static str = "ts";
-pub const MONGO_TC: &'static str = "tc";
+pub const MONGO_ID: &str = "id";
+pub const MONGO_MKEY: &str = "msk";
+pub const MONGO_PID: &str = "pid";
+pub const MONGO_DT_ID: &str = "dt_id";
+pub const MONGO_NAME: &str = "name";
+pub const MONGO_OWNER: &str = "owner";
+pub const MONGO_TS: &str = "ts";
+pub const MONGO_TC: &str = "tc";
+pub const MONGO_HASH: &str = "hash";
+pub const MONGO_DOC_ID: &str = "doc_id";
+pub const MONGO_KIND: &str = "kind";
+pub const MONGO_DESCRIPTION: &str = "description";
+pub const MONGO_FIRST_SEEN: &str = "first_seen";
+pub const MONGO_LAST_SEEN: &str = "last_seen";
+pub const MONGO_IDEMPOTENCY_KEY: &str = "idempotency_key";
+pub const MONGO_LEAF: &str = "leaf";
+pub const MONGO_TREE_SIZE: &str = "tree_size";
 
 // definition of default database values
-pub const DEFAULT_PROCESS_ID: &'static str = "default";
+pub const DEFAULT_PROCESS_ID: &str = "default";
 
 // split string symbols for vec_to_string and string_to_vec
-pub const SPLIT_QUOTE: &'static str = "'";
-pub const SPLIT_SIGN: &'static str = "~";
-pub const SPLIT_CT: &'static str = "::";
+pub const SPLIT_QUOTE: &str = "'";
+pub const SPLIT_SIGN: &str = "~";
+pub const SPLIT_CT: &str = "::";
+
+// first byte of the plaintexts of document parts in framed formats, these bytes can not start a UTF-8 string
+// plaintexts of earlier versions have the format "name::content"
+pub const PT_FORMAT_V1: u8 = 0xFE;
+// format version 2 records the compression of the content, it is only used for compressed parts
+pub const PT_FORMAT_V2: u8 = 0xFD;
 
 
 // definition of file names and folders
-pub const FOLDER_DB: &'static str = "db_init";
-pub const FOLDER_DATA: &'static str = "data";
-pub const FILE_DOC: &'static str = "document.json";
-pub const FILE_DEFAULT_DOC_TYPE: &'static str = "init_db/default_doc_type.json";
+pub const FOLDER_DB: &str = "db_init";
+pub const FOLDER_DATA: &str = "data";
+pub const FILE_DOC: &str = "document.json";
+pub const FILE_DEFAULT_DOC_TYPE: &str = "init_db/default_doc_type.json";
+
+// definition of document types
+pub const IDS_MESSAGE_DOC_TYPE: &str = "IDS_MESSAGE";
 
 // definition of special document parts
-pub const PAYLOAD_PART: &'static str = "payload";
+pub const PAYLOAD_PART: &str = "payload";
+
+// definition of payloads that are stored in chunks
+pub const PAYLOAD_CHUNK_SIZE: usize = 262144;
+pub const LIMIT_LARGE_PAYLOAD: &str = "large_payload";
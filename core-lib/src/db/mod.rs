@@ -9,7 +9,7 @@ pub trait DataStoreApi{
 pub async fn init_database_client<T: DataStoreApi>(db_url: &str, client_name: Option<String>) -> Result<T>{
     let mut client_options;
 
-    match ClientOptions::parse(&format!("{}", db_url)).await{
+    match ClientOptions::parse(&db_url.to_string()).await{
         Ok(co) => {client_options = co;}
         Err(_) => {
             bail!("Can't parse database connection string");
@@ -1,3 +1,6 @@
+// the errors of error_chain are large and the apis take `&String` throughout
+#![allow(clippy::result_large_err, clippy::ptr_arg)]
+
 extern crate biscuit;
 extern crate chrono;
 extern crate fern;
@@ -6,6 +9,8 @@ extern crate mongodb;
 #[macro_use] extern crate serde_derive;
 
 #[macro_use] extern crate error_chain;
+// error_chain checks a cfg of its own crate, which is unknown here
+#[allow(unexpected_cfgs)]
 pub mod errors {
     // Create the Error, ErrorKind, ResultExt, and Result types
     error_chain!{
@@ -20,6 +25,13 @@ pub mod errors {
             SerdeJson(serde_json::error::Error);
             Uft8Error(std::string::FromUtf8Error);
             BiscuitError(biscuit::errors::Error);
+            OpenSsl(openssl::error::ErrorStack);
+        }
+        errors {
+            IntegrityViolation(id: String) {
+                description("integrity violation")
+                display("Integrity violation for document '{}'", id)
+            }
         }
     }
 }
@@ -28,4 +40,6 @@ pub mod api;
 pub mod constants;
 pub mod db;
 pub mod model;
+pub mod signing;
+pub mod timestamp;
 pub mod util;
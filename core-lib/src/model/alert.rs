@@ -0,0 +1,77 @@
+use std::collections::BTreeMap;
+use crate::model::chain::{verify_chain, UnreadableDocument};
+use crate::model::document::EncryptedDocument;
+use crate::model::new_uuid;
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub enum AlertKind {
+    BrokenChain,
+    MissingDocuments,
+    MalformedDocument,
+    UnreadableDocument,
+}
+
+/// Violation found by the tamper detection of the document api.
+/// The same violation is only recorded once, `last_seen` is updated each time it is found again.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct Alert {
+    pub id: String,
+    pub pid: String,
+    pub doc_id: Option<String>,
+    pub kind: AlertKind,
+    pub description: String,
+    pub first_seen: i64,
+    pub last_seen: i64,
+}
+
+impl Alert{
+    pub fn new(pid: String, doc_id: Option<String>, kind: AlertKind, description: String, ts: i64) -> Alert{
+        Alert{
+            id: new_uuid(),
+            pid,
+            doc_id,
+            kind,
+            description,
+            first_seen: ts,
+            last_seen: ts,
+        }
+    }
+}
+
+/// Verifies the chains of all processes and the structure of all documents and returns the violations found
+pub fn scan_documents(docs: &[EncryptedDocument], ts: i64) -> Vec<Alert>{
+    let mut alerts = vec!();
+    let mut processes: BTreeMap<&str, Vec<EncryptedDocument>> = BTreeMap::new();
+    for doc in docs.iter(){
+        for violation in doc.structural_violations(){
+            alerts.push(Alert::new(doc.pid.clone(), Some(doc.id.clone()), AlertKind::MalformedDocument, violation, ts));
+        }
+        processes.entry(&doc.pid).or_default().push(doc.clone());
+    }
+
+    for (pid, chain) in processes.iter(){
+        let report = verify_chain(pid, chain);
+        if let Some(link) = report.first_broken_link{
+            let description = format!("Document with tc {} has chain hash '{}', expected '{}'", link.tc, &link.stored_hash, &link.expected_hash);
+            alerts.push(Alert::new(pid.to_string(), Some(link.id), AlertKind::BrokenChain, description, ts));
+        }
+        if !report.missing_tcs.is_empty(){
+            let description = format!("Documents with tcs {:?} are missing", &report.missing_tcs);
+            alerts.push(Alert::new(pid.to_string(), None, AlertKind::MissingDocuments, description, ts));
+        }
+    }
+    alerts
+}
+
+/// Returns a violation for each stored document of the process with pid that could not be read
+pub fn unreadable_document_alerts(pid: &str, unreadable: &[UnreadableDocument], ts: i64) -> Vec<Alert>{
+    unreadable.iter()
+        .map(|doc| {
+            let description = match doc.tc{
+                Some(tc) => format!("Document with tc {} could not be read: {}", tc, &doc.reason),
+                None => format!("Document could not be read: {}", &doc.reason)
+            };
+            Alert::new(pid.to_string(), doc.id.clone(), AlertKind::UnreadableDocument, description, ts)
+        })
+        .collect()
+}
@@ -0,0 +1,69 @@
+use crate::api::BlockchainMessage;
+use crate::model::chain::UnreadableDocument;
+use crate::model::document::EncryptedDocument;
+
+/// Chain hash anchored in the ledger compared to the chain hash currently stored for the same tc
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct AnchorComparison {
+    pub tc: Option<i64>,
+    pub anchored_hash: String,
+    pub current_hash: Option<String>,
+    pub matches: bool,
+}
+
+impl AnchorComparison{
+    pub fn new(tc: Option<i64>, anchored_hash: String, current_hash: Option<String>) -> AnchorComparison{
+        AnchorComparison{
+            tc,
+            matches: current_hash.as_ref() == Some(&anchored_hash),
+            anchored_hash,
+            current_hash,
+        }
+    }
+}
+
+/// Result of the comparison of all anchored hashes of a process with its current chain
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct AnchorReport {
+    pub pid: String,
+    pub valid: bool,
+    pub anchors: Vec<AnchorComparison>,
+    #[serde(default)]
+    pub unreadable_documents: Vec<UnreadableDocument>,
+}
+
+impl AnchorReport{
+    pub fn new(pid: String, anchors: Vec<AnchorComparison>) -> AnchorReport{
+        AnchorReport{
+            pid,
+            valid: anchors.iter().all(|a| a.matches),
+            anchors,
+            unreadable_documents: vec!(),
+        }
+    }
+
+    /// adds the documents of the process that could not be read. The comparison is not valid if there are any.
+    pub fn with_unreadable_documents(mut self, unreadable_documents: Vec<UnreadableDocument>) -> AnchorReport{
+        self.valid = self.valid && unreadable_documents.is_empty();
+        self.unreadable_documents = unreadable_documents;
+        self
+    }
+}
+
+/// Compares the hashes anchored for a process with the chain hashes of its documents.
+/// An anchor for a document that no longer exists does not match.
+pub fn compare_anchors(pid: &str, anchors: &[BlockchainMessage], docs: &[EncryptedDocument]) -> AnchorReport{
+    debug!("comparing {} anchored hashes with {} documents for pid {}", anchors.len(), docs.len(), pid);
+    let comparisons = anchors.iter()
+        .filter(|a| a.id == pid)
+        .map(|a| {
+            let tc = a.c_id.parse::<i64>().ok();
+            let current_hash = tc.and_then(|tc| docs.iter().find(|d| d.tc == tc)).map(|d| d.hash());
+            if current_hash.as_ref() != Some(&a.hash){
+                warn!("Anchored hash of pid {} for tc {} does not match the chain", pid, &a.c_id);
+            }
+            AnchorComparison::new(tc, a.hash.clone(), current_hash)
+        })
+        .collect();
+    AnchorReport::new(pid.to_string(), comparisons)
+}
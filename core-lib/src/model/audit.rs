@@ -0,0 +1,149 @@
+use biscuit::{Empty, jwk::JWKSet};
+use std::collections::BTreeMap;
+use crate::api::{BlockchainMessage, SignedDocumentReceipt};
+use crate::model::anchor::compare_anchors;
+use crate::model::chain::verify_chain;
+use crate::model::document::EncryptedDocument;
+use crate::timestamp::TimestampToken;
+
+/// Outcome of a single check of the offline verification
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct AuditCheck {
+    pub subject: String,
+    pub passed: bool,
+    pub failures: Vec<String>,
+}
+
+impl AuditCheck{
+    pub fn new(subject: String, failures: Vec<String>) -> AuditCheck{
+        AuditCheck{
+            subject,
+            passed: failures.is_empty(),
+            failures,
+        }
+    }
+}
+
+/// Result of the offline verification of an exported process log
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct AuditReport {
+    pub valid: bool,
+    pub checks: Vec<AuditCheck>,
+}
+
+impl AuditReport{
+    pub fn new(checks: Vec<AuditCheck>) -> AuditReport{
+        AuditReport{
+            valid: checks.iter().all(|c| c.passed),
+            checks,
+        }
+    }
+}
+
+/// Verifies an exported process log without access to any of the services of the clearing house:
+/// the hash chains of all processes, the receipts and their signatures against the `jwks` of the
+/// clearing house, the hashes anchored in the ledger and, if the certificate of the TSA is given,
+/// the time-stamp tokens of documents and receipts.
+pub fn audit_documents(docs: &[EncryptedDocument], receipts: &[SignedDocumentReceipt], jwks: Option<&JWKSet<Empty>>, anchors: &[BlockchainMessage], tsa_certificate: Option<&[u8]>) -> AuditReport{
+    let mut checks = vec!();
+    let mut processes: BTreeMap<&str, Vec<EncryptedDocument>> = BTreeMap::new();
+    for doc in docs.iter(){
+        processes.entry(&doc.pid).or_default().push(doc.clone());
+    }
+
+    for (pid, chain) in processes.iter(){
+        let report = verify_chain(pid, chain);
+        let mut failures = vec!();
+        if let Some(link) = report.first_broken_link{
+            failures.push(format!("Document with tc {} has chain hash '{}', expected '{}'", link.tc, &link.stored_hash, &link.expected_hash));
+        }
+        if !report.missing_tcs.is_empty(){
+            failures.push(format!("Documents with tcs {:?} are missing", &report.missing_tcs));
+        }
+        checks.push(AuditCheck::new(format!("Chain of process {} ({} documents)", pid, report.length), failures));
+
+        if let Some(cert) = tsa_certificate{
+            for (doc, token) in chain.iter().filter_map(|d| d.timestamp_token.as_ref().map(|t| (d, t))){
+                let failures = check_timestamp_token(token, doc, cert);
+                checks.push(AuditCheck::new(format!("Time-stamp token of document {}", &doc.id), failures));
+            }
+        }
+    }
+
+    for receipt in receipts.iter(){
+        let failures = check_receipt(receipt, docs, jwks, tsa_certificate);
+        checks.push(AuditCheck::new(format!("Receipt of document {}", &receipt.receipt.doc_id), failures));
+    }
+
+    let anchored_pids: Vec<&str> = anchors.iter().fold(vec!(), |mut pids, a| {
+        if !pids.contains(&a.id.as_str()){
+            pids.push(&a.id);
+        }
+        pids
+    });
+    for pid in anchored_pids{
+        let chain = processes.get(pid).map(|c| c.as_slice()).unwrap_or(&[]);
+        let report = compare_anchors(pid, anchors, chain);
+        let failures = report.anchors.iter()
+            .filter(|a| !a.matches)
+            .map(|a| match (a.tc, &a.current_hash){
+                (Some(tc), Some(current)) => format!("Anchored hash '{}' for tc {} does not match chain hash '{}'", &a.anchored_hash, tc, current),
+                _ => format!("Anchored hash '{}' has no matching document in the log", &a.anchored_hash)
+            })
+            .collect();
+        checks.push(AuditCheck::new(format!("Anchors of process {} ({} anchors)", pid, report.anchors.len()), failures));
+    }
+
+    AuditReport::new(checks)
+}
+
+fn check_receipt(receipt: &SignedDocumentReceipt, docs: &[EncryptedDocument], jwks: Option<&JWKSet<Empty>>, tsa_certificate: Option<&[u8]>) -> Vec<String>{
+    let mut failures = vec!();
+    match jwks{
+        Some(jwks) => {
+            if let Err(e) = receipt.verify(jwks){
+                failures.push(format!("Invalid signature: {}", e));
+            }
+        },
+        None => failures.push(String::from("No JWKS to verify the signature"))
+    }
+
+    let r = &receipt.receipt;
+    let doc = match docs.iter().find(|d| d.id == r.doc_id){
+        Some(doc) => doc,
+        None => {
+            failures.push(String::from("Document is missing in the log"));
+            return failures
+        }
+    };
+    if doc.pid != r.pid{
+        failures.push(format!("Receipt was issued for process {}, document belongs to process {}", &r.pid, &doc.pid));
+    }
+    if doc.tc != r.tc{
+        failures.push(format!("Receipt was issued for tc {}, document has tc {}", r.tc, doc.tc));
+    }
+    if doc.ts != r.timestamp{
+        failures.push(format!("Receipt was issued at {}, document was stored at {}", r.timestamp, doc.ts));
+    }
+    if doc.hash != r.chain_hash{
+        failures.push(format!("Receipt contains chain hash '{}', document has chain hash '{}'", &r.chain_hash, &doc.hash));
+    }
+    if !r.doc_hash.is_empty() && doc.hash() != r.doc_hash{
+        failures.push(format!("Receipt contains document hash '{}', document has hash '{}'", &r.doc_hash, doc.hash()));
+    }
+    if let (Some(token), Some(cert)) = (&receipt.timestamp_token, tsa_certificate){
+        failures.extend(check_timestamp_token(token, doc, cert));
+    }
+    failures
+}
+
+fn check_timestamp_token(token: &TimestampToken, doc: &EncryptedDocument, tsa_certificate: &[u8]) -> Vec<String>{
+    let mut failures = vec!();
+    if token.hash != doc.hash(){
+        failures.push(format!("Time-stamp token was issued for hash '{}', document has hash '{}'", &token.hash, doc.hash()));
+    }
+    if let Err(e) = token.verify(tsa_certificate){
+        failures.push(format!("Invalid time-stamp token: {}", e));
+    }
+    failures
+}
@@ -0,0 +1,107 @@
+use crate::model::document::EncryptedDocument;
+
+/// A link in the hash chain of a process that could not be verified
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct BrokenLink {
+    pub id: String,
+    pub tc: i64,
+    pub expected_hash: String,
+    pub stored_hash: String,
+}
+
+impl BrokenLink{
+    pub fn new(id: String, tc: i64, expected_hash: String, stored_hash: String) -> BrokenLink{
+        BrokenLink{
+            id,
+            tc,
+            expected_hash,
+            stored_hash,
+        }
+    }
+}
+
+/// A stored document of a process that could not be read, e.g. because it was modified
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct UnreadableDocument {
+    pub id: Option<String>,
+    pub tc: Option<i64>,
+    pub reason: String,
+}
+
+impl UnreadableDocument{
+    pub fn new(id: Option<String>, tc: Option<i64>, reason: String) -> UnreadableDocument{
+        UnreadableDocument{
+            id,
+            tc,
+            reason,
+        }
+    }
+}
+
+/// Result of the verification of the hash chain of a process
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct ChainReport {
+    pub pid: String,
+    pub valid: bool,
+    pub length: usize,
+    pub head_hash: Option<String>,
+    pub first_broken_link: Option<BrokenLink>,
+    pub missing_tcs: Vec<i64>,
+    #[serde(default)]
+    pub unreadable_documents: Vec<UnreadableDocument>,
+}
+
+impl ChainReport{
+    pub fn new(pid: String, length: usize, head_hash: Option<String>, first_broken_link: Option<BrokenLink>, missing_tcs: Vec<i64>) -> ChainReport{
+        ChainReport{
+            pid,
+            valid: first_broken_link.is_none() && missing_tcs.is_empty(),
+            length,
+            head_hash,
+            first_broken_link,
+            missing_tcs,
+            unreadable_documents: vec!(),
+        }
+    }
+
+    /// adds the documents of the process that could not be read. The chain is not valid if there are any.
+    pub fn with_unreadable_documents(mut self, unreadable_documents: Vec<UnreadableDocument>) -> ChainReport{
+        self.valid = self.valid && unreadable_documents.is_empty();
+        self.unreadable_documents = unreadable_documents;
+        self
+    }
+}
+
+/// Verifies the hash chain of the documents of a single process.
+/// Each document needs to carry the hash of its predecessor, the first document of a chain the hash "0".
+/// Links to documents that are missing in the chain can not be verified and are reported as missing tcs.
+pub fn verify_chain(pid: &str, docs: &[EncryptedDocument]) -> ChainReport{
+    debug!("verifying chain of {} documents for pid {}", docs.len(), pid);
+    let mut docs = docs.to_vec();
+    docs.sort_by_key(|d| d.tc);
+
+    let mut first_broken_link = None;
+    let mut missing_tcs = vec!();
+    let mut previous: Option<&EncryptedDocument> = None;
+    for doc in docs.iter(){
+        let expected_tc = previous.map(|p| p.tc + 1).unwrap_or(0);
+        if doc.tc > expected_tc {
+            missing_tcs.extend(expected_tc..doc.tc);
+        }
+        else if first_broken_link.is_none(){
+            // either the first document of the chain or the direct successor of the previous document
+            let expected_hash = match previous{
+                Some(p) => p.hash(),
+                None => String::from("0")
+            };
+            if doc.tc < expected_tc || doc.hash != expected_hash {
+                warn!("Chain of pid {} is broken at tc {}", pid, doc.tc);
+                first_broken_link = Some(BrokenLink::new(doc.id.clone(), doc.tc, expected_hash, doc.hash.clone()));
+            }
+        }
+        previous = Some(doc);
+    }
+
+    let head_hash = previous.map(|p| p.hash());
+    ChainReport::new(pid.to_string(), docs.len(), head_hash, first_broken_link, missing_tcs)
+}
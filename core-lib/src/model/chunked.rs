@@ -0,0 +1,174 @@
+use aes_gcm_siv::Aes256GcmSiv;
+use aes_gcm_siv::aead::Aead;
+use ring::digest;
+use crate::errors::*;
+use crate::model::crypto::{cipher_nonce, new_cipher};
+
+const EXP_KEY_SIZE: usize = 32;
+const EXP_NONCE_SIZE: usize = 12;
+// the nonce of a chunk consists of a prefix of the part nonce, the index of the chunk and the last chunk flag
+const NONCE_PREFIX_SIZE: usize = 7;
+/// size of the authentication tag added to each chunk
+pub const CHUNK_TAG_SIZE: usize = 16;
+
+/// Reference to a payload that is stored encrypted in chunks outside of the document, e.g. in GridFS.
+/// `digest` is the SHA-256 hash of the stored chunks and covered by the chain hash of the document.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct ChunkedPayload {
+    pub file_id: String,
+    pub key_id: String,
+    pub chunk_size: i64,
+    pub length: i64,
+    pub digest: String,
+}
+
+impl ChunkedPayload{
+    pub fn new(file_id: String, key_id: String, chunk_size: i64, length: i64, digest: String) -> ChunkedPayload{
+        ChunkedPayload{
+            file_id,
+            key_id,
+            chunk_size,
+            length,
+            digest,
+        }
+    }
+
+    /// number of chunks the payload is split into. An empty payload is stored as one empty chunk.
+    pub fn chunk_count(&self) -> i64{
+        if self.length == 0 || self.chunk_size <= 0{
+            1
+        }
+        else{
+            (self.length + self.chunk_size - 1) / self.chunk_size
+        }
+    }
+
+    /// size of a stored chunk, i.e. of an encrypted chunk that is not the last chunk
+    pub fn stored_chunk_size(&self) -> usize{
+        self.chunk_size as usize + CHUNK_TAG_SIZE
+    }
+}
+
+fn create_cipher(key: &[u8], nonce: &[u8]) -> Result<(Aes256GcmSiv, Vec<u8>)>{
+    if key.len() != EXP_KEY_SIZE {
+        error!("Given key has size {} but expected {} bytes", key.len(), EXP_KEY_SIZE);
+        bail!("Incorrect key size")
+    }
+    if nonce.len() != EXP_NONCE_SIZE {
+        error!("Given nonce has size {} but expected {} bytes", nonce.len(), EXP_NONCE_SIZE);
+        bail!("Incorrect nonce size")
+    }
+    Ok((new_cipher(key), nonce[..NONCE_PREFIX_SIZE].to_vec()))
+}
+
+fn chunk_nonce(prefix: &[u8], index: u32, last: bool) -> Vec<u8>{
+    let mut nonce = prefix.to_vec();
+    nonce.extend_from_slice(&index.to_be_bytes());
+    nonce.push(last as u8);
+    nonce
+}
+
+/// Encrypts a payload chunk by chunk. Each chunk is authenticated on its own and its nonce contains
+/// the index of the chunk and marks the last chunk, so that chunks can neither be reordered nor dropped.
+pub struct ChunkEncryptor {
+    cipher: Aes256GcmSiv,
+    nonce_prefix: Vec<u8>,
+    chunk_size: usize,
+    index: u32,
+    length: i64,
+    digest: digest::Context,
+    finished: bool,
+}
+
+impl ChunkEncryptor{
+    pub fn new(key: &[u8], nonce: &[u8], chunk_size: usize) -> Result<ChunkEncryptor>{
+        let (cipher, nonce_prefix) = create_cipher(key, nonce)?;
+        Ok(ChunkEncryptor{
+            cipher,
+            nonce_prefix,
+            chunk_size,
+            index: 0,
+            length: 0,
+            digest: digest::Context::new(&digest::SHA256),
+            finished: false,
+        })
+    }
+
+    /// encrypts the next chunk. All chunks but the last one need to have the full chunk size.
+    pub fn encrypt_chunk(&mut self, chunk: &[u8], last: bool) -> Result<Vec<u8>>{
+        if self.finished {
+            bail!("Last chunk was already encrypted")
+        }
+        if chunk.len() > self.chunk_size || (!last && chunk.len() != self.chunk_size) {
+            bail!("Chunk {} has size {} but expected {} bytes", self.index, chunk.len(), self.chunk_size)
+        }
+        let nonce = chunk_nonce(&self.nonce_prefix, self.index, last);
+        let ct = match self.cipher.encrypt(cipher_nonce(&nonce), chunk){
+            Ok(ct) => ct,
+            Err(e) => bail!("Error while encrypting chunk {}: {}", self.index, e)
+        };
+        self.digest.update(&ct);
+        self.length += chunk.len() as i64;
+        self.index = self.index.checked_add(1).chain_err(|| "Payload has too many chunks")?;
+        self.finished = last;
+        Ok(ct)
+    }
+
+    /// creates the reference to the encrypted payload once the last chunk is encrypted
+    pub fn finish(self, file_id: String, key_id: String) -> Result<ChunkedPayload>{
+        if !self.finished {
+            bail!("Last chunk is missing")
+        }
+        let digest = base64::encode(self.digest.finish().as_ref());
+        Ok(ChunkedPayload::new(file_id, key_id, self.chunk_size as i64, self.length, digest))
+    }
+}
+
+/// Decrypts the chunks of a payload in the order in which they were encrypted.
+/// The digest of the stored chunks is checked before the last chunk is returned.
+pub struct ChunkDecryptor {
+    cipher: Aes256GcmSiv,
+    nonce_prefix: Vec<u8>,
+    index: u32,
+    payload: ChunkedPayload,
+    digest: digest::Context,
+}
+
+impl ChunkDecryptor{
+    pub fn new(key: &[u8], nonce: &[u8], payload: &ChunkedPayload) -> Result<ChunkDecryptor>{
+        let (cipher, nonce_prefix) = create_cipher(key, nonce)?;
+        Ok(ChunkDecryptor{
+            cipher,
+            nonce_prefix,
+            index: 0,
+            payload: payload.clone(),
+            digest: digest::Context::new(&digest::SHA256),
+        })
+    }
+
+    /// true once all chunks of the payload are decrypted
+    pub fn is_finished(&self) -> bool{
+        self.index as i64 >= self.payload.chunk_count()
+    }
+
+    pub fn decrypt_chunk(&mut self, chunk: &[u8]) -> Result<Vec<u8>>{
+        if self.is_finished() {
+            bail!("Payload has more chunks than expected")
+        }
+        let last = self.index as i64 == self.payload.chunk_count() - 1;
+        let nonce = chunk_nonce(&self.nonce_prefix, self.index, last);
+        let pt = match self.cipher.decrypt(cipher_nonce(&nonce), chunk){
+            Ok(pt) => pt,
+            Err(e) => bail!("Error while decrypting chunk {}: {}", self.index, e)
+        };
+        self.digest.update(chunk);
+        self.index += 1;
+        if last {
+            let digest = base64::encode(self.digest.clone().finish().as_ref());
+            if digest != self.payload.digest {
+                bail!("Digest of payload does not match the document")
+            }
+        }
+        Ok(pt)
+    }
+}
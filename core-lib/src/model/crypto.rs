@@ -1,10 +1,30 @@
+use aes_gcm_siv::Aes256GcmSiv;
+use aes_gcm_siv::aead::NewAead;
+use aes_gcm_siv::aead::consts::U12;
 use std::collections::HashMap;
+use crate::model::document::Compression;
+
+/// Creates an AES-256-GCM-SIV cipher, the key has to have 32 bytes.
+/// generic-array 0.14, which aes-gcm-siv builds on, is deprecated, so it is only used here and in `cipher_nonce`.
+#[allow(deprecated)]
+pub fn new_cipher(key: &[u8]) -> Aes256GcmSiv {
+    Aes256GcmSiv::new(generic_array::GenericArray::from_slice(key))
+}
+
+/// Nonce of an AES-256-GCM-SIV cipher, the nonce has to have 12 bytes
+#[allow(deprecated)]
+pub fn cipher_nonce(nonce: &[u8]) -> &generic_array::GenericArray<u8, U12> {
+    generic_array::GenericArray::from_slice(nonce)
+}
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct KeyEntry {
     pub id: String,
     pub key: Vec<u8>,
     pub nonce: Vec<u8>,
+    /// compression of the part before encryption, as configured in the document type
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compression: Option<Compression>,
 }
 
 impl KeyEntry{
@@ -12,7 +32,8 @@ impl KeyEntry{
         KeyEntry{
             id,
             key,
-            nonce
+            nonce,
+            compression: None
         }
     }
 }
@@ -52,14 +73,18 @@ impl KeyCt{
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct KeyCtList {
     pub dt: String,
-    pub cts: Vec<KeyCt>
+    pub cts: Vec<KeyCt>,
+    /// names of the parts whose keys are restored, all parts if not given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parts: Option<Vec<String>>
 }
 
 impl KeyCtList{
     pub fn new(dt: String, cts: Vec<KeyCt>) -> KeyCtList{
         KeyCtList{
             dt,
-            cts
+            cts,
+            parts: None
         }
     }
 }
@@ -0,0 +1,180 @@
+use jsonschema::JSONSchema;
+use serde_json::Value;
+use std::fmt;
+use crate::model::document::{Compression, Document, PartEncoding};
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct DocumentType {
+    pub id: String,
+    pub pid: String,
+    pub parts: Vec<DocumentTypePart>,
+    /// compression of all parts before encryption, unless a part configures its own
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compression: Option<Compression>,
+}
+
+impl DocumentType {
+    pub fn new(id: String, pid: String, parts: Vec<DocumentTypePart>) -> DocumentType {
+        DocumentType{
+            id,
+            pid,
+            parts,
+            compression: None,
+        }
+    }
+
+    /// compression of `part` before encryption
+    pub fn part_compression(&self, part: &DocumentTypePart) -> Option<Compression> {
+        part.compression.or(self.compression)
+    }
+
+    /// Checks the parts of the document against the definitions of their parts in the document type.
+    /// Returns all violations, an empty list means the document is valid.
+    pub fn validate(&self, doc: &Document) -> Vec<PartViolation> {
+        let mut violations = vec!();
+        for (i, part) in doc.parts.iter().enumerate() {
+            if doc.parts[..i].iter().any(|p| p.name == part.name) {
+                violations.push(PartViolation::new(&part.name, String::from("occurs more than once")));
+                continue;
+            }
+            let dt_part = match self.parts.iter().find(|p| p.name == part.name) {
+                Some(dt_part) => dt_part,
+                None => {
+                    violations.push(PartViolation::new(&part.name, format!("is not defined in document type '{}'", &self.id)));
+                    continue;
+                }
+            };
+            match part.bytes() {
+                Ok(Some(content)) => {
+                    violations.extend(dt_part.validate(&content, part.encoding).into_iter()
+                        .map(|reason| PartViolation::new(&part.name, reason)));
+                },
+                Ok(None) => (),
+                Err(_) => violations.push(PartViolation::new(&part.name, String::from("content is not base64 encoded")))
+            }
+        }
+        for dt_part in self.parts.iter().filter(|p| p.required) {
+            if !doc.parts.iter().any(|p| p.name == dt_part.name && p.content.is_some()) {
+                violations.push(PartViolation::new(&dt_part.name, String::from("is required")));
+            }
+        }
+        violations
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct DocumentTypePart {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compression: Option<Compression>,
+    /// documents without content for this part are rejected
+    #[serde(default)]
+    pub required: bool,
+    /// media type of the content. Content of JSON types has to be valid JSON, content of text types has to be UTF-8.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+    /// maximum size of the content in bytes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_size: Option<u64>,
+    /// JSON Schema the content has to match, implies JSON content
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schema: Option<Value>,
+}
+
+impl DocumentTypePart {
+    pub fn new(name: String) -> DocumentTypePart{
+        DocumentTypePart{
+            name,
+            compression: None,
+            required: false,
+            content_type: None,
+            max_size: None,
+            schema: None,
+        }
+    }
+
+    fn is_json(&self) -> bool {
+        match &self.content_type {
+            Some(content_type) => {
+                let media_type = content_type.split(';').next().unwrap_or("").trim().to_lowercase();
+                media_type == "application/json" || media_type.ends_with("+json")
+            },
+            None => false
+        }
+    }
+
+    fn is_text(&self) -> bool {
+        match &self.content_type {
+            Some(content_type) => content_type.trim().to_lowercase().starts_with("text/"),
+            None => false
+        }
+    }
+
+    /// returns the reasons why the content does not match the part
+    fn validate(&self, content: &[u8], encoding: PartEncoding) -> Vec<String> {
+        let mut reasons = vec!();
+        if let Some(max_size) = self.max_size {
+            if content.len() as u64 > max_size {
+                reasons.push(format!("has {} bytes, at most {} bytes are allowed", content.len(), max_size));
+            }
+        }
+        if self.is_text() && encoding != PartEncoding::Utf8 {
+            reasons.push(format!("has binary content, expected {}", self.content_type.as_ref().unwrap()));
+        }
+        if self.is_json() || self.schema.is_some() {
+            match serde_json::from_slice::<Value>(content) {
+                Ok(value) => {
+                    if let Some(schema) = &self.schema {
+                        reasons.extend(validate_schema(schema, &value));
+                    }
+                },
+                Err(e) => reasons.push(format!("content is not valid JSON: {}", e))
+            }
+        }
+        reasons
+    }
+}
+
+fn validate_schema(schema: &Value, value: &Value) -> Vec<String> {
+    let compiled = match JSONSchema::compile(schema) {
+        Ok(compiled) => compiled,
+        Err(e) => return vec!(format!("schema of the document type is invalid: {}", e))
+    };
+    let result = match compiled.validate(value) {
+        Ok(()) => vec!(),
+        Err(errors) => errors
+            .map(|e| {
+                let path = e.instance_path.to_string();
+                if path.is_empty() {
+                    format!("does not match schema: {}", e)
+                }
+                else {
+                    format!("does not match schema at '{}': {}", path, e)
+                }
+            })
+            .collect()
+    };
+    result
+}
+
+/// Violation of the document type by a part of a document
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct PartViolation {
+    pub part: String,
+    pub reason: String,
+}
+
+impl PartViolation {
+    pub fn new(part: &str, reason: String) -> PartViolation {
+        PartViolation{
+            part: String::from(part),
+            reason,
+        }
+    }
+}
+
+impl fmt::Display for PartViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Part '{}' {}", self.part, self.reason)
+    }
+}
@@ -1,19 +1,122 @@
-use aes_gcm_siv::Aes256GcmSiv;
-use aes_gcm_siv::aead::{Aead, NewAead};
+use aes_gcm_siv::aead::Aead;
 use blake2_rfc::blake2b::Blake2b;
-use generic_array::GenericArray;
+use flate2::Compression as DeflateLevel;
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use ring::digest;
 use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fmt;
+use std::io::{Read, Write};
+use std::str::FromStr;
 use uuid::Uuid;
 use crate::errors::*;
-use crate::constants::{SPLIT_CT, SPLIT_QUOTE, SPLIT_SIGN};
+use crate::constants::{PT_FORMAT_V1, PT_FORMAT_V2, SPLIT_CT, SPLIT_QUOTE, SPLIT_SIGN};
 use crate::model::new_uuid;
-use crate::model::crypto::{KeyEntry, KeyMap};
+use crate::model::chunked::ChunkedPayload;
+use crate::model::crypto::{cipher_nonce, new_cipher, KeyEntry, KeyMap};
+use crate::timestamp::TimestampToken;
 use chrono::Utc;
 
+/// Encoding of the content of a document part in the json api.
+/// Content of `Base64` parts is decoded before encryption and stored as raw bytes.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PartEncoding {
+    Utf8,
+    Base64,
+}
+
+impl Default for PartEncoding {
+    /// parts without encoding contain text
+    fn default() -> Self {
+        PartEncoding::Utf8
+    }
+}
+
+impl TryFrom<u8> for PartEncoding {
+    type Error = Error;
+
+    fn try_from(encoding: u8) -> Result<Self> {
+        match encoding {
+            0 => Ok(PartEncoding::Utf8),
+            1 => Ok(PartEncoding::Base64),
+            _ => bail!("Unknown part encoding {}", encoding)
+        }
+    }
+}
+
+impl From<PartEncoding> for u8 {
+    fn from(encoding: PartEncoding) -> u8 {
+        match encoding {
+            PartEncoding::Utf8 => 0,
+            PartEncoding::Base64 => 1,
+        }
+    }
+}
+
+/// Compression that is applied to the content of a document part before encryption.
+/// The algorithm is recorded in the plaintext, so that decryption does not need to know it.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Compression {
+    Deflate,
+    Zstd,
+}
+
+impl Compression {
+    const ZSTD_LEVEL: i32 = 3;
+
+    pub fn compress(&self, data: &[u8]) -> Result<Vec<u8>> {
+        match self {
+            Compression::Deflate => {
+                let mut encoder = DeflateEncoder::new(Vec::new(), DeflateLevel::default());
+                encoder.write_all(data).chain_err(|| "Error while compressing")?;
+                Ok(encoder.finish().chain_err(|| "Error while compressing")?)
+            },
+            Compression::Zstd => Ok(zstd::encode_all(data, Compression::ZSTD_LEVEL).chain_err(|| "Error while compressing")?)
+        }
+    }
+
+    pub fn decompress(&self, data: &[u8]) -> Result<Vec<u8>> {
+        match self {
+            Compression::Deflate => {
+                let mut decompressed = vec!();
+                DeflateDecoder::new(data).read_to_end(&mut decompressed).chain_err(|| "Error while decompressing")?;
+                Ok(decompressed)
+            },
+            Compression::Zstd => Ok(zstd::decode_all(data).chain_err(|| "Error while decompressing")?)
+        }
+    }
+}
+
+impl TryFrom<u8> for Compression {
+    type Error = Error;
+
+    fn try_from(compression: u8) -> Result<Self> {
+        match compression {
+            1 => Ok(Compression::Deflate),
+            2 => Ok(Compression::Zstd),
+            _ => bail!("Unknown compression {}", compression)
+        }
+    }
+}
+
+impl From<Compression> for u8 {
+    fn from(compression: Compression) -> u8 {
+        match compression {
+            Compression::Deflate => 1,
+            Compression::Zstd => 2,
+        }
+    }
+}
+
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct DocumentPart {
     pub name: String,
     pub content: Option<String>,
+    #[serde(default)]
+    pub encoding: PartEncoding,
 }
 
 impl DocumentPart{
@@ -21,10 +124,38 @@ impl DocumentPart{
         DocumentPart{
             name,
             content,
+            encoding: PartEncoding::Utf8,
+        }
+    }
+
+    /// creates a part with binary content, which is base64 encoded in the json api
+    pub fn from_bytes(name: String, content: &[u8]) -> DocumentPart{
+        DocumentPart{
+            name,
+            content: Some(base64::encode(content)),
+            encoding: PartEncoding::Base64,
+        }
+    }
+
+    /// returns the raw bytes of the content
+    pub fn bytes(&self) -> Result<Option<Vec<u8>>>{
+        match (&self.content, self.encoding){
+            (Some(content), PartEncoding::Utf8) => Ok(Some(content.clone().into_bytes())),
+            (Some(content), PartEncoding::Base64) => {
+                let bytes = base64::decode(content).chain_err(|| format!("Content of part '{}' is not base64 encoded", &self.name))?;
+                Ok(Some(bytes))
+            },
+            (None, _) => Ok(None)
         }
     }
 
     pub fn encrypt(&self, key: &[u8], nonce: &[u8]) -> Result<Vec<u8>>{
+        self.encrypt_compressed(key, nonce, None)
+    }
+
+    /// encrypts the part after compressing its content with `compression`.
+    /// The content is stored uncompressed if compression does not make it smaller.
+    pub fn encrypt_compressed(&self, key: &[u8], nonce: &[u8], compression: Option<Compression>) -> Result<Vec<u8>>{
         const EXP_KEY_SIZE: usize = 32;
         const EXP_NONCE_SIZE: usize = 12;
         // check key size
@@ -38,37 +169,40 @@ impl DocumentPart{
             bail!("Incorrect nonce size")
         }
         else{
-            let key = GenericArray::from_slice(key);
-            let nonce = GenericArray::from_slice(nonce);
-            let cipher = Aes256GcmSiv::new(key);
-
-            match &self.content{
-                Some(pt) => {
-                    let pt = format_pt_for_storage(&self.name, pt);
-                    match cipher.encrypt(nonce, pt.as_bytes()){
-                        Ok(ct) => Ok(ct),
-                        Err(e) => bail!("Error while encrypting {}", e)
-                    }
+            let nonce = cipher_nonce(nonce);
+            let cipher = new_cipher(key);
+
+            let pt = match self.bytes()?{
+                Some(pt) => match compression{
+                    Some(c) => {
+                        let compressed = c.compress(&pt)?;
+                        if compressed.len() < pt.len(){
+                            format_compressed_pt_for_storage(&self.name, self.encoding, c, &compressed)
+                        }
+                        else{
+                            format_pt_for_storage(&self.name, self.encoding, &pt)
+                        }
+                    },
+                    None => format_pt_for_storage(&self.name, self.encoding, &pt)
                 },
                 None => {
                     error!("Tried to encrypt empty document part.");
                     bail!("Nothing to encrypt");
                 }
+            };
+            match cipher.encrypt(nonce, pt.as_slice()){
+                Ok(ct) => Ok(ct),
+                Err(e) => bail!("Error while encrypting {}", e)
             }
         }
     }
 
     pub fn decrypt(key: &[u8], nonce: &[u8], ct: &[u8]) -> Result<DocumentPart>{
-        let key = GenericArray::from_slice(key);
-        let nonce = GenericArray::from_slice(nonce);
-        let cipher = Aes256GcmSiv::new(key);
+        let nonce = cipher_nonce(nonce);
+        let cipher = new_cipher(key);
 
         match cipher.decrypt(nonce, ct){
-            Ok(pt) => {
-                let pt = String::from_utf8(pt)?;
-                let (name, content) = restore_pt_no_dt(&pt)?;
-                Ok(DocumentPart::new(name, Some(content)))
-            },
+            Ok(pt) => restore_part(pt),
             Err(e) => {
                 bail!("Error while decrypting: {}", e)
             }
@@ -83,6 +217,8 @@ pub struct Document {
     pub dt_id: String,
     pub pid: String,
     pub ts: i64,
+    /// assigned by the document api, client supplied values are ignored
+    #[serde(default)]
     pub tc: i64,
     pub parts: Vec<DocumentPart>,
 }
@@ -100,15 +236,12 @@ impl Document{
         let mut cts = vec!();
 
         let keys = key_map.keys;
-        let key_ct;
-        match key_map.keys_enc{
-            Some(ct) => {
-                key_ct = hex::encode(ct);
-            },
+        let key_ct = match key_map.keys_enc{
+            Some(ct) => hex::encode(ct),
             None => {
                 bail!("Missing key ct");
             }
-        }
+        };
 
         for part in self.parts.iter() {
             if part.content.is_none(){
@@ -122,7 +255,7 @@ impl Document{
             }
             // get the key for this part
             let key_entry = keys.get(&part.name).unwrap();
-            let ct = part.encrypt(key_entry.key.as_slice(), key_entry.nonce.as_slice());
+            let ct = part.encrypt_compressed(key_entry.key.as_slice(), key_entry.nonce.as_slice(), key_entry.compression);
             if ct.is_err(){
                 warn!("Encryption error. No ct received!");
                 bail!("Encryption error. No ct received!");
@@ -160,6 +293,47 @@ impl Document{
         }
     }
 
+    /// true if both documents belong to the same process and document type and contain the same parts,
+    /// regardless of their ids, timestamps and the order of the parts
+    pub fn same_content(&self, other: &Document) -> bool{
+        self.pid == other.pid && self.dt_id == other.dt_id && self.sorted_contents() == other.sorted_contents()
+    }
+
+    /// keyed hash of the content of the document, documents with the same content according to `same_content`
+    /// have the same hash. Without the key the hash does not reveal anything about the content.
+    pub fn content_hash(&self, key: &[u8]) -> String{
+        let mut hasher = Blake2b::with_key(64, key);
+        let mut push_field = |field: &[u8]| {
+            hasher.update(&(field.len() as u64).to_be_bytes());
+            hasher.update(field);
+        };
+        push_field(self.pid.as_bytes());
+        push_field(self.dt_id.as_bytes());
+        let contents = self.sorted_contents();
+        push_field(&(contents.len() as u64).to_be_bytes());
+        for (name, content) in contents.iter(){
+            push_field(name.as_bytes());
+            match content{
+                Some(content) => {
+                    push_field(&[1]);
+                    push_field(content);
+                },
+                None => push_field(&[0])
+            }
+        }
+        base64::encode(hasher.finalize().as_bytes())
+    }
+
+    /// names and contents of the parts with content, sorted by name
+    fn sorted_contents(&self) -> Vec<(String, Option<Vec<u8>>)>{
+        let mut contents: Vec<(String, Option<Vec<u8>>)> = self.parts.iter()
+            .filter(|p| p.content.is_some())
+            .map(|p| (p.name.clone(), p.bytes().ok().flatten()))
+            .collect();
+        contents.sort();
+        contents
+    }
+
     fn restore(id: String, pid: String, dt_id: String, ts: i64, tc: i64, parts: Vec<DocumentPart>) -> Document{
         Document{
             id,
@@ -179,7 +353,7 @@ impl Document{
                 _ => vec.push(String::from(item))
             }
         }
-        if vec.len() > 0 {
+        if !vec.is_empty() {
             Some(vec)
         }
         else {
@@ -197,6 +371,75 @@ impl Document{
     }
 }
 
+/// Scheme used by `EncryptedDocument::hash`. Stored as number in `EncryptedDocument::hash_version`.
+/// Version 0 concatenates the fields without separators and is only kept to verify old documents.
+/// Later versions hash a canonical encoding in which each field is prefixed with its length.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "i32", into = "i32")]
+pub enum HashVersion {
+    Legacy,
+    Blake2b512,
+    Sha256,
+}
+
+impl HashVersion {
+    /// hash version used for new documents if no other algorithm is configured
+    pub const CURRENT: HashVersion = HashVersion::Blake2b512;
+}
+
+impl Default for HashVersion {
+    /// documents stored without hash version use version 0
+    fn default() -> Self {
+        HashVersion::Legacy
+    }
+}
+
+impl TryFrom<i32> for HashVersion {
+    type Error = String;
+
+    fn try_from(version: i32) -> std::result::Result<Self, Self::Error> {
+        match version {
+            0 => Ok(HashVersion::Legacy),
+            1 => Ok(HashVersion::Blake2b512),
+            2 => Ok(HashVersion::Sha256),
+            _ => Err(format!("Unknown hash version {}", version))
+        }
+    }
+}
+
+impl From<HashVersion> for i32 {
+    fn from(version: HashVersion) -> i32 {
+        match version {
+            HashVersion::Legacy => 0,
+            HashVersion::Blake2b512 => 1,
+            HashVersion::Sha256 => 2,
+        }
+    }
+}
+
+/// parses the name of the algorithm of a hash version with canonical encoding, e.g. in the config
+impl FromStr for HashVersion {
+    type Err = Error;
+
+    fn from_str(algorithm: &str) -> Result<Self> {
+        match algorithm.to_lowercase().as_str() {
+            "blake2b" => Ok(HashVersion::Blake2b512),
+            "sha256" => Ok(HashVersion::Sha256),
+            _ => bail!("Unsupported hash algorithm '{}'", algorithm)
+        }
+    }
+}
+
+impl fmt::Display for HashVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HashVersion::Legacy => write!(f, "legacy"),
+            HashVersion::Blake2b512 => write!(f, "blake2b"),
+            HashVersion::Sha256 => write!(f, "sha256"),
+        }
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct EncryptedDocument {
     pub id: String,
@@ -205,8 +448,26 @@ pub struct EncryptedDocument {
     pub ts: i64,
     pub tc: i64,
     pub hash: String,
+    #[serde(default)]
+    pub hash_version: HashVersion,
     pub keys_ct: String,
     pub cts: Vec<String>,
+    /// time-stamp token over `hash()`, if the document api is configured with a TSA
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp_token: Option<TimestampToken>,
+    /// payload stored in chunks outside of the document instead of in `cts`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payload: Option<ChunkedPayload>,
+    /// key of the request that created the document, to recognize retries. Not covered by the hash.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idempotency_key: Option<String>,
+    /// keyed hash of the content of the document, to compare retries with the stored document without decrypting it.
+    /// Not covered by the hash.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_hash: Option<String>,
+    /// `hash()` of the document when it was stored, i.e. its leaf in the merkle tree of its process. Not covered by the hash.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub leaf: Option<String>,
 }
 
 impl EncryptedDocument{
@@ -214,24 +475,35 @@ impl EncryptedDocument{
     /// Note: KeyMap keys need to be KeyEntry.ids in this case
     // Decryption is done without checking the hashes. Do this before calling this method
     pub fn decrypt(&self, keys: HashMap<String, KeyEntry>) -> Result<Document>{
+        self.decrypt_cts(keys, false)
+    }
+
+    /// Decrypts only the parts whose keys are given, e.g. the keys of selected parts. The other parts are left out.
+    pub fn decrypt_parts(&self, keys: HashMap<String, KeyEntry>) -> Result<Document>{
+        self.decrypt_cts(keys, true)
+    }
+
+    fn decrypt_cts(&self, keys: HashMap<String, KeyEntry>, skip_missing_keys: bool) -> Result<Document>{
 
         let mut pts = vec!();
         for ct in self.cts.iter(){
-            let ct_parts = ct.split(SPLIT_CT).collect::<Vec<&str>>();
-            if ct_parts.len() != 2 {
-                bail!("Integrity violation! Ciphertexts modified");
-            }
+            let (key_id, ct) = match split_ct(ct){
+                Some(ct_parts) => ct_parts,
+                None => bail!("Integrity violation! Ciphertexts modified")
+            };
             // get key and nonce
-            let key_entry = keys.get(ct_parts[0]);
+            let key_entry = keys.get(key_id);
             if key_entry.is_none(){
-                bail!("Key for id '{}' does not exist!", ct_parts[0]);
+                if skip_missing_keys{
+                    continue;
+                }
+                bail!("Key for id '{}' does not exist!", key_id);
             }
             let key = key_entry.unwrap().key.as_slice();
             let nonce = key_entry.unwrap().nonce.as_slice();
 
             // get ciphertext
-            //TODO: use error_chain?
-            let ct = hex::decode(ct_parts[1]).unwrap();
+            let ct = hex::decode(ct).chain_err(|| "Integrity violation! Ciphertexts modified")?;
 
             // decrypt
             match DocumentPart::decrypt(key, nonce, ct.as_slice()){
@@ -249,7 +521,41 @@ impl EncryptedDocument{
         format_tc(self.tc)
     }
 
+    /// Checks the structure of the stored document without decrypting it: the ct of the keys needs
+    /// to be hex encoded and each ct needs to have the format `id::HEX`. Returns the violations found.
+    pub fn structural_violations(&self) -> Vec<String>{
+        let mut violations = vec!();
+        if self.keys_ct.is_empty() || hex::decode(&self.keys_ct).is_err(){
+            violations.push(String::from("keys_ct is not hex encoded"));
+        }
+        for (i, ct) in self.cts.iter().enumerate(){
+            let valid = match split_ct(ct){
+                Some((id, ct)) => !id.is_empty() && !ct.is_empty() && hex::decode(ct).is_ok(),
+                None => false
+            };
+            if !valid{
+                violations.push(format!("ct {} does not have the format 'id{}HEX'", i, SPLIT_CT));
+            }
+        }
+        violations
+    }
+
+    /// hash of the document using the scheme of its `hash_version`
     pub fn hash(&self) -> String{
+        let res = match self.hash_version{
+            HashVersion::Legacy => self.legacy_hash(),
+            HashVersion::Blake2b512 => {
+                let mut hasher = Blake2b::new(64);
+                hasher.update(&self.canonical_encoding());
+                base64::encode(&hasher.finalize())
+            },
+            HashVersion::Sha256 => base64::encode(digest::digest(&digest::SHA256, &self.canonical_encoding()).as_ref())
+        };
+        debug!("hashed cts: '{}'", &res);
+        res
+    }
+
+    fn legacy_hash(&self) -> String{
         let mut hasher = Blake2b::new(64);
 
         hasher.update(self.id.as_bytes());
@@ -264,10 +570,49 @@ impl EncryptedDocument{
         for ct in cts.iter() {
             hasher.update(ct.as_bytes());
         }
+        if let Some(payload) = self.payload.as_ref(){
+            hasher.update(payload.file_id.as_bytes());
+            hasher.update(payload.key_id.as_bytes());
+            hasher.update(payload.chunk_size.to_string().as_bytes());
+            hasher.update(payload.length.to_string().as_bytes());
+            hasher.update(payload.digest.as_bytes());
+        }
 
-        let res = base64::encode(&hasher.finalize());
-        debug!("hashed cts: '{}'", &res);
-        res
+        base64::encode(&hasher.finalize())
+    }
+
+    /// Encoding of all hashed fields in which each field is prefixed with its length (u64, big endian),
+    /// so that no two documents share the same encoding. The hash version is encoded first.
+    /// The reference to a chunked payload is only appended if the document has one.
+    fn canonical_encoding(&self) -> Vec<u8>{
+        fn push_field(encoding: &mut Vec<u8>, field: &[u8]){
+            encoding.extend_from_slice(&(field.len() as u64).to_be_bytes());
+            encoding.extend_from_slice(field);
+        }
+
+        let mut encoding = vec!();
+        push_field(&mut encoding, &i32::from(self.hash_version).to_be_bytes());
+        push_field(&mut encoding, self.id.as_bytes());
+        push_field(&mut encoding, self.pid.as_bytes());
+        push_field(&mut encoding, self.dt_id.as_bytes());
+        push_field(&mut encoding, &self.tc.to_be_bytes());
+        push_field(&mut encoding, &self.ts.to_be_bytes());
+        push_field(&mut encoding, self.hash.as_bytes());
+        push_field(&mut encoding, self.keys_ct.as_bytes());
+        let mut cts = self.cts.clone();
+        cts.sort();
+        push_field(&mut encoding, &(cts.len() as u64).to_be_bytes());
+        for ct in cts.iter() {
+            push_field(&mut encoding, ct.as_bytes());
+        }
+        if let Some(payload) = self.payload.as_ref(){
+            push_field(&mut encoding, payload.file_id.as_bytes());
+            push_field(&mut encoding, payload.key_id.as_bytes());
+            push_field(&mut encoding, &payload.chunk_size.to_be_bytes());
+            push_field(&mut encoding, &payload.length.to_be_bytes());
+            push_field(&mut encoding, payload.digest.as_bytes());
+        }
+        encoding
     }
 
     pub fn new(id: String, pid: String, dt_id: String, ts: i64, tc: i64, keys_ct: String, cts: Vec<String>) -> EncryptedDocument {
@@ -278,13 +623,19 @@ impl EncryptedDocument{
             ts,
             tc,
             hash: String::from("0"),
+            hash_version: HashVersion::CURRENT,
             keys_ct,
             cts,
+            timestamp_token: None,
+            payload: None,
+            idempotency_key: None,
+            content_hash: None,
+            leaf: None,
         }
     }
 }
 
-/// companion to format_pt_for_storage
+/// reads plaintexts with document type in the format of earlier versions
 pub fn restore_pt(pt: &str) -> Result<(String, String, String)> {
     debug!("Trying to restore plain text");
     let vec: Vec<&str> = pt.split(SPLIT_CT).collect();
@@ -294,7 +645,7 @@ pub fn restore_pt(pt: &str) -> Result<(String, String, String)> {
     Ok((String::from(vec[0]), String::from(vec[1]), String::from(vec[2])))
 }
 
-/// companion to format_pt_for_storage_no_dt
+/// reads plaintexts in the format "name::content" of earlier versions
 pub fn restore_pt_no_dt(pt: &str) -> Result<(String, String)> {
     debug!("Trying to restore plain text");
     let vec: Vec<&str> = pt.split(SPLIT_CT).collect();
@@ -304,9 +655,82 @@ pub fn restore_pt_no_dt(pt: &str) -> Result<(String, String)> {
     Ok((String::from(vec[0]), String::from(vec[1])))
 }
 
-/// formats the pt before encryption
-fn format_pt_for_storage(field_name: &str, pt: &str) -> String {
-    format!("{}{}{}", field_name, SPLIT_CT, pt)
+/// companion to format_pt_for_storage. Reads the formats of earlier versions as well.
+pub fn restore_part(pt: Vec<u8>) -> Result<DocumentPart> {
+    match pt.first(){
+        Some(&PT_FORMAT_V1) => {
+            let (name, encoding, content) = restore_framed_pt(&pt)?;
+            restore_content(name, encoding, content.to_vec())
+        },
+        Some(&PT_FORMAT_V2) => {
+            let (name, encoding, compression, content) = restore_compressed_pt(&pt)?;
+            restore_content(name, encoding, compression.decompress(content)?)
+        },
+        _ => {
+            let pt = String::from_utf8(pt)?;
+            let (name, content) = restore_pt_no_dt(&pt)?;
+            Ok(DocumentPart::new(name, Some(content)))
+        }
+    }
+}
+
+/// reads the name, encoding and content of a plaintext in format version 1
+fn restore_framed_pt(pt: &[u8]) -> Result<(String, PartEncoding, &[u8])> {
+    const HEADER_SIZE: usize = 6;
+    if pt.len() < HEADER_SIZE {
+        bail!("Could not restore plaintext");
+    }
+    let encoding = PartEncoding::try_from(pt[1])?;
+    let mut name_len = [0u8; 4];
+    name_len.copy_from_slice(&pt[2..HEADER_SIZE]);
+    let name_end = HEADER_SIZE + u32::from_be_bytes(name_len) as usize;
+    if pt.len() < name_end {
+        bail!("Could not restore plaintext");
+    }
+    let name = String::from_utf8(pt[HEADER_SIZE..name_end].to_vec())?;
+    Ok((name, encoding, &pt[name_end..]))
+}
+
+fn restore_content(name: String, encoding: PartEncoding, content: Vec<u8>) -> Result<DocumentPart> {
+    match encoding{
+        PartEncoding::Utf8 => Ok(DocumentPart::new(name, Some(String::from_utf8(content)?))),
+        PartEncoding::Base64 => Ok(DocumentPart::from_bytes(name, &content))
+    }
+}
+
+/// reads the name, encoding, compression and compressed content of a plaintext in format version 2
+fn restore_compressed_pt(pt: &[u8]) -> Result<(String, PartEncoding, Compression, &[u8])> {
+    if pt.len() < 3 {
+        bail!("Could not restore plaintext");
+    }
+    let compression = Compression::try_from(pt[1])?;
+    // after the compression the header continues like the header of format version 1
+    let (name, encoding, content) = restore_framed_pt(&pt[1..])?;
+    Ok((name, encoding, compression, content))
+}
+
+/// formats the pt before encryption: format version, encoding, length-prefixed name and content
+fn format_pt_for_storage(field_name: &str, encoding: PartEncoding, pt: &[u8]) -> Vec<u8> {
+    let mut formatted = vec!(PT_FORMAT_V1, u8::from(encoding));
+    formatted.extend_from_slice(&(field_name.len() as u32).to_be_bytes());
+    formatted.extend_from_slice(field_name.as_bytes());
+    formatted.extend_from_slice(pt);
+    formatted
+}
+
+/// formats a compressed pt before encryption: format version, compression, encoding, length-prefixed name and content
+fn format_compressed_pt_for_storage(field_name: &str, encoding: PartEncoding, compression: Compression, pt: &[u8]) -> Vec<u8> {
+    let mut formatted = vec!(PT_FORMAT_V2, u8::from(compression), u8::from(encoding));
+    formatted.extend_from_slice(&(field_name.len() as u32).to_be_bytes());
+    formatted.extend_from_slice(field_name.as_bytes());
+    formatted.extend_from_slice(pt);
+    formatted
+}
+
+/// splits an entry of `EncryptedDocument::cts` into the key entry id and the hex encoded ct.
+/// Splits at the last separator, since it can not be part of the hex encoded ct.
+fn split_ct(ct: &str) -> Option<(&str, &str)> {
+    ct.rfind(SPLIT_CT).map(|pos| (&ct[..pos], &ct[pos + SPLIT_CT.len()..]))
 }
 
 fn format_tc(tc: i64) -> String{
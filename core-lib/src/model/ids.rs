@@ -0,0 +1,154 @@
+use chrono::{SecondsFormat, Utc};
+use serde_json::{json, Map, Value};
+use crate::constants::{IDS_MESSAGE_DOC_TYPE, PAYLOAD_PART};
+use crate::errors::*;
+use crate::model::document::{Document, DocumentPart};
+use crate::model::new_uuid;
+
+const IDS_NAMESPACE: &str = "https://w3id.org/idsa/core/";
+const IDS_CODE_NAMESPACE: &str = "https://w3id.org/idsa/code/";
+const IDS_AUTOGEN: &str = "https://w3id.org/idsa/autogen";
+const IDS_MODEL_VERSION: &str = "4.0.0";
+const XSD_DATE_TIME_STAMP: &str = "http://www.w3.org/2001/XMLSchema#dateTimeStamp";
+
+/// fields of the IDS message header that are logged as parts of the IDS_MESSAGE document type
+const HEADER_PARTS: [(&str, &str); 10] = [
+    ("@id", "message_id"),
+    ("modelVersion", "model_version"),
+    ("correlationMessage", "correlation_message"),
+    ("transferContract", "transfer_contract"),
+    ("issued", "issued"),
+    ("issuerConnector", "issuer_connector"),
+    ("contentVersion", "content_version"),
+    ("recipientConnector", "recipient_connector"),
+    ("senderAgent", "sender_agent"),
+    ("recipientAgent", "recipient_agent"),
+];
+const PAYLOAD_TYPE_PART: &str = "payload_type";
+
+/// Reasons for the rejection of an IDS message
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RejectionReason {
+    BadParameters,
+    InternalRecipientError,
+    MalformedMessage,
+    NotAuthenticated,
+    NotFound,
+}
+
+impl RejectionReason {
+    fn code(&self) -> &'static str {
+        match self {
+            RejectionReason::BadParameters => "BAD_PARAMETERS",
+            RejectionReason::InternalRecipientError => "INTERNAL_RECIPIENT_ERROR",
+            RejectionReason::MalformedMessage => "MALFORMED_MESSAGE",
+            RejectionReason::NotAuthenticated => "NOT_AUTHENTICATED",
+            RejectionReason::NotFound => "NOT_FOUND",
+        }
+    }
+}
+
+/// returns the field of the header, using either the `ids` prefix or the full IRI
+fn header_field<'a>(header: &'a Map<String, Value>, field: &str) -> Option<&'a Value> {
+    if field.starts_with('@') {
+        return header.get(field)
+    }
+    header.get(&format!("ids:{}", field))
+        .or_else(|| header.get(&format!("{}{}", IDS_NAMESPACE, field)))
+}
+
+/// flattens a JSON-LD value: references and typed literals are reduced to their IRI or value,
+/// lists are kept as JSON array of the flattened values
+fn ids_value(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        Value::Object(o) => o.get("@id").or_else(|| o.get("@value")).and_then(ids_value),
+        Value::Array(a) => {
+            let values: Vec<Value> = a.iter().filter_map(ids_value).map(Value::String).collect();
+            Some(Value::Array(values).to_string())
+        },
+        Value::Null => None
+    }
+}
+
+/// Derives the process of an IDS message from its transfer contract.
+/// The id of the process is the last segment of the IRI of the contract.
+pub fn derive_pid(header: &Value) -> Result<String> {
+    let header = header.as_object().chain_err(|| "IDS message header is not a JSON object")?;
+    let contract = header_field(header, "transferContract")
+        .and_then(ids_value)
+        .chain_err(|| "IDS message has no transfer contract to derive the process from")?;
+    let pid = contract.trim_end_matches('/').rsplit(['/', ':', '#']).next().unwrap_or("");
+    if pid.is_empty() || !pid.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.') {
+        bail!("Could not derive the process from the transfer contract '{}'", contract)
+    }
+    Ok(String::from(pid))
+}
+
+/// Maps the header and payload of an IDS message to the parts of a document of the IDS_MESSAGE document type.
+/// Payloads that are not UTF-8 are stored as binary part.
+pub fn document_from_ids_message(pid: String, header: &Value, payload: Option<&[u8]>, payload_type: Option<String>) -> Result<Document> {
+    let fields = header.as_object().chain_err(|| "IDS message header is not a JSON object")?;
+    let mut parts = vec!();
+    for (field, part) in HEADER_PARTS.iter() {
+        if let Some(value) = header_field(fields, field).and_then(ids_value) {
+            parts.push(DocumentPart::new(String::from(*part), Some(value)));
+        }
+    }
+    if let Some(payload) = payload {
+        let part = match std::str::from_utf8(payload) {
+            Ok(text) => DocumentPart::new(String::from(PAYLOAD_PART), Some(String::from(text))),
+            Err(_) => DocumentPart::from_bytes(String::from(PAYLOAD_PART), payload)
+        };
+        parts.push(part);
+        if let Some(payload_type) = payload_type {
+            parts.push(DocumentPart::new(String::from(PAYLOAD_TYPE_PART), Some(payload_type)));
+        }
+    }
+    Ok(Document::new(pid, String::from(IDS_MESSAGE_DOC_TYPE), 0, parts))
+}
+
+fn response_header(message_type: &str, request: &Value, connector_id: &str) -> Map<String, Value> {
+    let mut header = Map::new();
+    header.insert(String::from("@context"), json!({"ids": IDS_NAMESPACE, "idsc": IDS_CODE_NAMESPACE}));
+    header.insert(String::from("@type"), json!(format!("ids:{}", message_type)));
+    let type_segment = format!("{}{}", message_type[..1].to_lowercase(), &message_type[1..]);
+    header.insert(String::from("@id"), json!(format!("{}/{}/{}", IDS_AUTOGEN, type_segment, new_uuid())));
+    let model_version = request.as_object()
+        .and_then(|r| header_field(r, "modelVersion"))
+        .and_then(ids_value)
+        .unwrap_or(String::from(IDS_MODEL_VERSION));
+    header.insert(String::from("ids:modelVersion"), json!(model_version));
+    header.insert(String::from("ids:issued"), json!({
+        "@value": Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+        "@type": XSD_DATE_TIME_STAMP
+    }));
+    header.insert(String::from("ids:issuerConnector"), json!({"@id": connector_id}));
+    header.insert(String::from("ids:senderAgent"), json!({"@id": connector_id}));
+    if let Some(request) = request.as_object() {
+        if let Some(id) = header_field(request, "@id").and_then(ids_value) {
+            header.insert(String::from("ids:correlationMessage"), json!({"@id": id}));
+        }
+        if let Some(issuer) = header_field(request, "issuerConnector").and_then(ids_value) {
+            header.insert(String::from("ids:recipientConnector"), json!([{"@id": issuer}]));
+        }
+        if let Some(sender) = header_field(request, "senderAgent").and_then(ids_value) {
+            header.insert(String::from("ids:recipientAgent"), json!([{"@id": sender}]));
+        }
+    }
+    header
+}
+
+/// Header of the answer to an IDS message that was logged. The receipt is sent as payload.
+pub fn message_processed_notification(request: &Value, connector_id: &str) -> Value {
+    Value::Object(response_header("MessageProcessedNotificationMessage", request, connector_id))
+}
+
+/// Header of the answer to an IDS message that could not be logged. The reason is explained in the payload.
+pub fn rejection_message(request: &Value, reason: RejectionReason, connector_id: &str) -> Value {
+    let mut header = response_header("RejectionMessage", request, connector_id);
+    header.insert(String::from("ids:rejectionReason"), json!({"@id": format!("idsc:{}", reason.code())}));
+    Value::Object(header)
+}
@@ -0,0 +1,281 @@
+use biscuit::CompactJson;
+use blake2_rfc::blake2b::Blake2b;
+
+// domain separation of leaves and inner nodes as in RFC 6962
+const LEAF_PREFIX: u8 = 0x00;
+const NODE_PREFIX: u8 = 0x01;
+
+/// Head of the Merkle tree over the first `tree_size` documents of a process
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct TreeHead {
+    pub pid: String,
+    pub tree_size: usize,
+    pub root_hash: String,
+    pub timestamp: i64,
+}
+
+impl CompactJson for TreeHead {
+}
+
+impl TreeHead{
+    pub fn new(pid: String, tree_size: usize, root_hash: String, timestamp: i64) -> TreeHead{
+        TreeHead{
+            pid,
+            tree_size,
+            root_hash,
+            timestamp,
+        }
+    }
+}
+
+/// Proof that a document is included in the Merkle tree of its process.
+/// `signed_tree_head` is the JWS of the `TreeHead` the audit path leads to.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct InclusionProof {
+    pub pid: String,
+    pub doc_id: String,
+    pub leaf_index: usize,
+    pub tree_size: usize,
+    pub leaf_hash: String,
+    pub audit_path: Vec<String>,
+    pub signed_tree_head: String,
+}
+
+impl InclusionProof{
+    pub fn new(pid: String, doc_id: String, leaf_index: usize, tree_size: usize, leaf_hash: String, audit_path: Vec<String>, signed_tree_head: String) -> InclusionProof{
+        InclusionProof{
+            pid,
+            doc_id,
+            leaf_index,
+            tree_size,
+            leaf_hash,
+            audit_path,
+            signed_tree_head,
+        }
+    }
+
+    /// checks that the audit path leads from the leaf to the root hash of the given tree head
+    pub fn verify(&self, tree_head: &TreeHead) -> bool{
+        if tree_head.pid != self.pid || tree_head.tree_size != self.tree_size{
+            return false;
+        }
+        match (decode_hash(&self.leaf_hash), decode_hashes(&self.audit_path), decode_hash(&tree_head.root_hash)){
+            (Some(leaf), Some(path), Some(root)) => verify_inclusion(&leaf, self.leaf_index, self.tree_size, &path, &root),
+            _ => false
+        }
+    }
+}
+
+/// Proof that the Merkle tree of a process of size `first_size` is a prefix of the tree of size `second_size`.
+/// `signed_tree_head` is the JWS of the `TreeHead` of size `second_size`.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct ConsistencyProof {
+    pub pid: String,
+    pub first_size: usize,
+    pub second_size: usize,
+    pub proof: Vec<String>,
+    pub signed_tree_head: String,
+}
+
+impl ConsistencyProof{
+    pub fn new(pid: String, first_size: usize, second_size: usize, proof: Vec<String>, signed_tree_head: String) -> ConsistencyProof{
+        ConsistencyProof{
+            pid,
+            first_size,
+            second_size,
+            proof,
+            signed_tree_head,
+        }
+    }
+
+    /// checks that the tree of the second head was created by appending to the tree of the first head
+    pub fn verify(&self, first: &TreeHead, second: &TreeHead) -> bool{
+        if first.pid != self.pid || second.pid != self.pid || first.tree_size != self.first_size || second.tree_size != self.second_size{
+            return false;
+        }
+        match (decode_hash(&first.root_hash), decode_hash(&second.root_hash), decode_hashes(&self.proof)){
+            (Some(first_root), Some(second_root), Some(proof)) => verify_consistency(self.first_size, self.second_size, &first_root, &second_root, &proof),
+            _ => false
+        }
+    }
+}
+
+fn hash(prefix: Option<u8>, parts: &[&[u8]]) -> Vec<u8>{
+    let mut hasher = Blake2b::new(64);
+    if let Some(p) = prefix{
+        hasher.update(&[p]);
+    }
+    for part in parts.iter(){
+        hasher.update(part);
+    }
+    hasher.finalize().as_bytes().to_vec()
+}
+
+/// The leaf of a document is the chain hash of the document, i.e. `EncryptedDocument::hash`
+pub fn leaf_hash(doc_hash: &str) -> Vec<u8>{
+    hash(Some(LEAF_PREFIX), &[doc_hash.as_bytes()])
+}
+
+fn node_hash(left: &[u8], right: &[u8]) -> Vec<u8>{
+    hash(Some(NODE_PREFIX), &[left, right])
+}
+
+// largest power of two smaller than n
+fn split_point(n: usize) -> usize{
+    let mut k = 1;
+    while k << 1 < n {
+        k <<= 1;
+    }
+    k
+}
+
+/// Merkle tree hash over the given leaf hashes
+pub fn root_hash(leaves: &[Vec<u8>]) -> Vec<u8>{
+    match leaves.len(){
+        0 => hash(None, &[]),
+        1 => leaves[0].clone(),
+        n => {
+            let k = split_point(n);
+            node_hash(&root_hash(&leaves[..k]), &root_hash(&leaves[k..]))
+        }
+    }
+}
+
+/// Audit path of the leaf with index `m` in the tree over the given leaf hashes
+pub fn audit_path(m: usize, leaves: &[Vec<u8>]) -> Vec<Vec<u8>>{
+    let n = leaves.len();
+    if n <= 1 || m >= n {
+        return vec!();
+    }
+    let k = split_point(n);
+    if m < k {
+        let mut path = audit_path(m, &leaves[..k]);
+        path.push(root_hash(&leaves[k..]));
+        path
+    }
+    else{
+        let mut path = audit_path(m - k, &leaves[k..]);
+        path.push(root_hash(&leaves[..k]));
+        path
+    }
+}
+
+/// Consistency proof between the tree over the first `m` leaves and the tree over all leaves
+pub fn consistency_proof(m: usize, leaves: &[Vec<u8>]) -> Vec<Vec<u8>>{
+    if m == 0 || m >= leaves.len() {
+        return vec!();
+    }
+    sub_proof(m, leaves, true)
+}
+
+fn sub_proof(m: usize, leaves: &[Vec<u8>], complete: bool) -> Vec<Vec<u8>>{
+    let n = leaves.len();
+    if m == n {
+        return match complete{
+            true => vec!(),
+            false => vec!(root_hash(leaves))
+        };
+    }
+    let k = split_point(n);
+    if m <= k {
+        let mut proof = sub_proof(m, &leaves[..k], complete);
+        proof.push(root_hash(&leaves[k..]));
+        proof
+    }
+    else{
+        let mut proof = sub_proof(m - k, &leaves[k..], false);
+        proof.push(root_hash(&leaves[..k]));
+        proof
+    }
+}
+
+/// Verifies that the tree with `first_root` of size `first_size` is a prefix of the tree with
+/// `second_root` of size `second_size`
+pub fn verify_consistency(first_size: usize, second_size: usize, first_root: &[u8], second_root: &[u8], proof: &[Vec<u8>]) -> bool{
+    if first_size > second_size {
+        return false;
+    }
+    if first_size == second_size {
+        return proof.is_empty() && first_root == second_root;
+    }
+    if first_size == 0 {
+        // every tree is an extension of the empty tree
+        return proof.is_empty();
+    }
+
+    let mut path = proof.to_vec();
+    // the first tree is a complete subtree, so its root is the starting point
+    if first_size.is_power_of_two() {
+        path.insert(0, first_root.to_vec());
+    }
+    if path.is_empty() {
+        return false;
+    }
+    let mut f_n = first_size - 1;
+    let mut s_n = second_size - 1;
+    while f_n & 1 == 1 {
+        f_n >>= 1;
+        s_n >>= 1;
+    }
+    let mut f_r = path[0].clone();
+    let mut s_r = path[0].clone();
+    for c in path[1..].iter(){
+        if s_n == 0 {
+            return false;
+        }
+        if f_n & 1 == 1 || f_n == s_n {
+            f_r = node_hash(c, &f_r);
+            s_r = node_hash(c, &s_r);
+            while f_n & 1 == 0 && f_n != 0 {
+                f_n >>= 1;
+                s_n >>= 1;
+            }
+        }
+        else{
+            s_r = node_hash(&s_r, c);
+        }
+        f_n >>= 1;
+        s_n >>= 1;
+    }
+    s_n == 0 && f_r.as_slice() == first_root && s_r.as_slice() == second_root
+}
+
+/// Verifies the audit path of the leaf with index `leaf_index` in a tree of size `tree_size`
+pub fn verify_inclusion(leaf: &[u8], leaf_index: usize, tree_size: usize, path: &[Vec<u8>], root: &[u8]) -> bool{
+    if leaf_index >= tree_size {
+        return false;
+    }
+    let mut f_n = leaf_index;
+    let mut s_n = tree_size - 1;
+    let mut r = leaf.to_vec();
+    for p in path.iter(){
+        if s_n == 0 {
+            return false;
+        }
+        if f_n & 1 == 1 || f_n == s_n {
+            r = node_hash(p, &r);
+            while f_n & 1 == 0 && f_n != 0 {
+                f_n >>= 1;
+                s_n >>= 1;
+            }
+        }
+        else{
+            r = node_hash(&r, p);
+        }
+        f_n >>= 1;
+        s_n >>= 1;
+    }
+    s_n == 0 && r.as_slice() == root
+}
+
+pub fn encode_hash(hash: &[u8]) -> String{
+    base64::encode(hash)
+}
+
+pub fn decode_hash(hash: &str) -> Option<Vec<u8>>{
+    base64::decode(hash).ok()
+}
+
+fn decode_hashes(hashes: &[String]) -> Option<Vec<Vec<u8>>>{
+    hashes.iter().map(|h| decode_hash(h)).collect()
+}
@@ -1,5 +1,13 @@
+pub mod alert;
+pub mod anchor;
+pub mod audit;
+pub mod chain;
+pub mod chunked;
 pub mod crypto;
+pub mod doc_type;
+pub mod ids;
 pub mod document;
+pub mod merkle;
 pub mod process;
 
 #[cfg(test)] mod tests;
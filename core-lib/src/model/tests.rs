@@ -1,13 +1,26 @@
-use crate::model::crypto::{KeyEntry, KeyMap};
-use crate::model::document::{Document, DocumentPart, EncryptedDocument};
+use crate::api::{BlockchainMessage, DocumentReceipt, SignedDocumentReceipt, verify_receipt};
+use crate::model::alert::{scan_documents, unreadable_document_alerts, AlertKind};
+use crate::model::anchor::compare_anchors;
+use crate::model::audit::audit_documents;
+use crate::model::chain::{verify_chain, UnreadableDocument};
+use crate::model::chunked::{ChunkDecryptor, ChunkEncryptor, ChunkedPayload};
+use crate::model::crypto::{cipher_nonce, new_cipher, KeyEntry, KeyMap};
+use crate::model::doc_type::{DocumentType, DocumentTypePart};
+use crate::model::ids::{derive_pid, document_from_ids_message, message_processed_notification, rejection_message, RejectionReason};
+use crate::model::document::{Compression, Document, DocumentPart, EncryptedDocument, HashVersion, PartEncoding};
+use crate::model::merkle::{audit_path, consistency_proof, encode_hash, leaf_hash, root_hash, verify_consistency, verify_inclusion, ConsistencyProof, InclusionProof, TreeHead};
+use crate::signing::{SigningKey, verify_signature};
+use crate::timestamp::{create_timestamp_request, verify_timestamp_token, TimestampToken};
 use crate::errors::*;
 use std::collections::HashMap;
 use chrono::Utc;
+use aes_gcm_siv::aead::Aead;
 
 fn create_test_doc(dt_id: String) -> Document{
-    let mut doc_parts = vec!();
-    doc_parts.push(DocumentPart::new(String::from("part1"), Some(String::from("MODEL_VERSION"))));
-    doc_parts.push(DocumentPart::new(String::from("part2"), Some(String::from("CORRELATION_MESSAGE"))));
+    let doc_parts = vec!(
+        DocumentPart::new(String::from("part1"), Some(String::from("MODEL_VERSION"))),
+        DocumentPart::new(String::from("part2"), Some(String::from("CORRELATION_MESSAGE")))
+    );
     Document::new(Document::create_uuid(), dt_id, 3241, doc_parts)
 }
 
@@ -24,7 +37,7 @@ fn create_key_enc_map() -> KeyMap{
     map.insert(String::from("part1"), e1);
     map.insert(String::from("part2"), e2);
 
-    return KeyMap::new(true, map, Some(key_ct));
+    KeyMap::new(true, map, Some(key_ct))
 }
 
 fn create_key_dec_map() -> KeyMap{
@@ -39,7 +52,7 @@ fn create_key_dec_map() -> KeyMap{
     map.insert(String::from("1"), e1);
     map.insert(String::from("2"), e2);
 
-    return KeyMap::new(false, map, None);
+    KeyMap::new(false, map, None)
 }
 
 #[test]
@@ -47,7 +60,7 @@ fn test_document_part_encryption() -> Result<()>{
 
     // prepare test data
     let part = DocumentPart::new(String::from("model_version"), Some(String::from("MODEL_VERSION")));
-    let expected_ct = hex::decode("7F80228F5187DBD7FC6F7DA93510905102D39EF790FB84097EAC541E9DABF3D035FB4E910E6F52E3DB31C935").unwrap();
+    let expected_ct = hex::decode("71B583ECFBB9A939E814422245CC58CFD66296FC447926E2C1F98CA5711E3A65B430585E3ED1299C0C2709743F7647B4").unwrap();
 
     // create key and nonce
     let key = String::from("an example very very secret key.");
@@ -64,7 +77,7 @@ fn test_document_part_encryption() -> Result<()>{
 #[test]
 fn test_document_part_decryption() -> Result<()>{
 
-    // prepare test data
+    // prepare test data: ciphertext of the plaintext format of earlier versions
     let ct = hex::decode("7F80228F5187DBD7FC6F7DA93510905102D39EF790FB84097EAC541E9DABF3D035FB4E910E6F52E3DB31C935").unwrap();
     let expected_part = DocumentPart::new(String::from("model_version"), Some(String::from("MODEL_VERSION")));
 
@@ -82,6 +95,324 @@ fn test_document_part_decryption() -> Result<()>{
     Ok(())
 }
 
+#[test]
+fn test_document_part_framing() -> Result<()>{
+
+    // prepare test data: the separator of the format of earlier versions in name and content
+    let key = String::from("an example very very secret key.");
+    let nonce = String::from("unique nonce");
+    let part = DocumentPart::new(String::from("connector::address"), Some(String::from("urn:ids:connector::[2001:db8::1]")));
+
+    // run the test
+    let ct = part.encrypt(key.as_bytes(), nonce.as_bytes())?;
+    let result = DocumentPart::decrypt(key.as_bytes(), nonce.as_bytes(), ct.as_slice())?;
+
+    // check
+    assert_eq!(result.name, part.name);
+    assert_eq!(result.content, part.content);
+    assert_eq!(result.encoding, PartEncoding::Utf8);
+
+    // parts in the format "name::content" of earlier versions can still be read
+    let cipher = new_cipher(key.as_bytes());
+    let ct = cipher.encrypt(cipher_nonce(nonce.as_bytes()), b"payload::message".as_ref()).unwrap();
+    let result = DocumentPart::decrypt(key.as_bytes(), nonce.as_bytes(), ct.as_slice())?;
+    assert_eq!(result.name, "payload");
+    assert_eq!(result.content, Some(String::from("message")));
+
+    // plaintexts that are neither framed nor UTF-8 are rejected
+    let ct = cipher.encrypt(cipher_nonce(nonce.as_bytes()), [0xFFu8, 0x00, 0x3A, 0x3A].as_ref()).unwrap();
+    assert!(DocumentPart::decrypt(key.as_bytes(), nonce.as_bytes(), ct.as_slice()).is_err());
+
+    // truncated frames are rejected
+    let truncated = vec!(0xFE, 0x00, 0x00, 0x00, 0x00, 0x10, b'a');
+    let ct = cipher.encrypt(cipher_nonce(nonce.as_bytes()), truncated.as_slice()).unwrap();
+    assert!(DocumentPart::decrypt(key.as_bytes(), nonce.as_bytes(), ct.as_slice()).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_compressed_document_part_encryption() -> Result<()>{
+
+    // prepare test data
+    let key = String::from("an example very very secret key.");
+    let nonce = String::from("unique nonce");
+    let part = DocumentPart::new(String::from("message"), Some("MODEL_VERSION ".repeat(100)));
+    let binary = DocumentPart::from_bytes(String::from("image"), &[0x00u8; 2000]);
+    let uncompressed_ct = part.encrypt(key.as_bytes(), nonce.as_bytes())?;
+
+    for compression in [Compression::Deflate, Compression::Zstd]{
+        // run the test
+        let ct = part.encrypt_compressed(key.as_bytes(), nonce.as_bytes(), Some(compression))?;
+        let result = DocumentPart::decrypt(key.as_bytes(), nonce.as_bytes(), ct.as_slice())?;
+
+        // check: compression is transparent for decryption
+        assert!(ct.len() < uncompressed_ct.len());
+        assert_eq!(result.name, part.name);
+        assert_eq!(result.content, part.content);
+
+        let ct = binary.encrypt_compressed(key.as_bytes(), nonce.as_bytes(), Some(compression))?;
+        let result = DocumentPart::decrypt(key.as_bytes(), nonce.as_bytes(), ct.as_slice())?;
+        assert_eq!(result.encoding, PartEncoding::Base64);
+        assert_eq!(result.bytes()?, binary.bytes()?);
+    }
+
+    // content that does not get smaller is stored uncompressed
+    let short = DocumentPart::new(String::from("message"), Some(String::from("x")));
+    let ct = short.encrypt_compressed(key.as_bytes(), nonce.as_bytes(), Some(Compression::Zstd))?;
+    assert_eq!(ct, short.encrypt(key.as_bytes(), nonce.as_bytes())?);
+
+    Ok(())
+}
+
+#[test]
+fn test_compressed_document_encryption() -> Result<()>{
+    // prepare test data
+    let dt_id = String::from("DT");
+    let mut doc = create_test_doc(dt_id.clone());
+    doc.parts[0].content = Some("MODEL_VERSION ".repeat(100));
+    let mut key_map = create_key_enc_map();
+    key_map.keys.get_mut("part1").unwrap().compression = Some(Compression::Deflate);
+
+    // run the test
+    let enc_doc = doc.encrypt(key_map)?;
+    let result = enc_doc.decrypt(create_key_dec_map().keys)?;
+
+    // check
+    assert_eq!(result.parts.iter().find(|p| p.name == "part1").unwrap().content, doc.parts[0].content);
+    assert_eq!(result.parts.iter().find(|p| p.name == "part2").unwrap().content, doc.parts[1].content);
+
+    Ok(())
+}
+
+#[test]
+fn test_document_same_content() -> Result<()>{
+    // prepare test data
+    let doc = create_test_doc(String::from("dt"));
+    let mut reordered = doc.clone();
+    reordered.id = Document::create_uuid();
+    reordered.parts.reverse();
+    let mut changed = doc.clone();
+    changed.parts[0].content = Some(String::from("OTHER_VERSION"));
+    let mut missing = doc.clone();
+    missing.parts.pop();
+    let mut other_type = doc.clone();
+    other_type.dt_id = String::from("other_dt");
+
+    // ids and the order of parts do not matter
+    assert_ne!(doc.id, reordered.id);
+    assert!(doc.same_content(&reordered));
+    assert!(!doc.same_content(&changed));
+    assert!(!doc.same_content(&missing));
+    assert!(!doc.same_content(&other_type));
+
+    // the content hash follows the comparison and depends on the key
+    let key = b"content key";
+    assert_eq!(doc.content_hash(key), reordered.content_hash(key));
+    assert_ne!(doc.content_hash(key), changed.content_hash(key));
+    assert_ne!(doc.content_hash(key), missing.content_hash(key));
+    assert_ne!(doc.content_hash(key), other_type.content_hash(key));
+    assert_ne!(doc.content_hash(key), doc.content_hash(b"other key"));
+
+    Ok(())
+}
+
+fn create_test_document_type() -> DocumentType{
+    let mut header = DocumentTypePart::new(String::from("header"));
+    header.required = true;
+    header.content_type = Some(String::from("application/ld+json"));
+    header.schema = Some(serde_json::json!({
+        "type": "object",
+        "required": ["@type"],
+        "properties": {"@type": {"type": "string"}}
+    }));
+    let mut payload = DocumentTypePart::new(String::from("payload"));
+    payload.required = true;
+    payload.max_size = Some(16);
+    let mut note = DocumentTypePart::new(String::from("note"));
+    note.content_type = Some(String::from("text/plain"));
+    DocumentType::new(String::from("DT"), String::from("default"), vec!(header, payload, note))
+}
+
+#[test]
+fn test_document_type_validation() -> Result<()>{
+    // prepare test data
+    let dt = create_test_document_type();
+    let parts = vec!(
+        DocumentPart::new(String::from("header"), Some(String::from("{\"@type\": \"ids:LogMessage\"}"))),
+        DocumentPart::from_bytes(String::from("payload"), &[0x00, 0xFF])
+    );
+    let valid = Document::new(String::from("pid"), String::from("DT"), 0, parts);
+
+    // optional parts can be omitted
+    assert!(dt.validate(&valid).is_empty());
+
+    // every failing part is reported
+    let parts = vec!(
+        DocumentPart::new(String::from("header"), Some(String::from("{\"@type\": 3}"))),
+        DocumentPart::new(String::from("note"), Some(String::from("text"))),
+        DocumentPart::from_bytes(String::from("note"), &[0x00]),
+        DocumentPart::new(String::from("unknown"), Some(String::from("text")))
+    );
+    let invalid = Document::new(String::from("pid"), String::from("DT"), 0, parts);
+    let violations = dt.validate(&invalid);
+    let parts: Vec<&str> = violations.iter().map(|v| v.part.as_str()).collect();
+    assert_eq!(parts, vec!("header", "note", "unknown", "payload"));
+    assert!(violations[0].reason.contains("does not match schema"));
+    assert_eq!(violations[1].reason, "occurs more than once");
+    assert_eq!(violations[3].reason, "is required");
+
+    // size, JSON content and text content are checked
+    let parts = vec!(
+        DocumentPart::new(String::from("header"), Some(String::from("not json"))),
+        DocumentPart::new(String::from("payload"), Some("x".repeat(17))),
+        DocumentPart::from_bytes(String::from("note"), &[0x00])
+    );
+    let invalid = Document::new(String::from("pid"), String::from("DT"), 0, parts);
+    let violations = dt.validate(&invalid);
+    assert_eq!(violations.len(), 3);
+    assert!(violations[0].reason.starts_with("content is not valid JSON"));
+    assert_eq!(violations[1].to_string(), "Part 'payload' has 17 bytes, at most 16 bytes are allowed");
+    assert_eq!(violations[2].reason, "has binary content, expected text/plain");
+
+    Ok(())
+}
+
+#[test]
+fn test_document_type_serialization() -> Result<()>{
+    // document types of earlier versions only define the names of their parts
+    let dt: DocumentType = serde_json::from_str("{\"id\": \"DT\", \"pid\": \"default\", \"parts\": [{\"name\": \"payload\"}]}")?;
+    assert!(!dt.parts[0].required);
+    assert!(dt.parts[0].schema.is_none());
+
+    let json = serde_json::to_string(&create_test_document_type())?;
+    let result: DocumentType = serde_json::from_str(&json)?;
+    assert_eq!(result.parts[0].schema, create_test_document_type().parts[0].schema);
+    assert_eq!(result.parts[1].max_size, Some(16));
+    assert_eq!(result.parts[2].content_type, Some(String::from("text/plain")));
+
+    Ok(())
+}
+
+fn create_test_ids_header() -> serde_json::Value{
+    serde_json::json!({
+        "@context": {"ids": "https://w3id.org/idsa/core/"},
+        "@type": "ids:LogMessage",
+        "@id": "https://w3id.org/idsa/autogen/logMessage/c6c15a91-7b2d-4d0d-b5b3-1f7d3f8e3b2a",
+        "ids:modelVersion": "4.0.0",
+        "ids:issued": {"@value": "2021-06-23T11:25:06.473+02:00", "@type": "http://www.w3.org/2001/XMLSchema#dateTimeStamp"},
+        "ids:issuerConnector": {"@id": "https://connector.example.org"},
+        "ids:senderAgent": {"@id": "https://agent.example.org"},
+        "ids:recipientConnector": [{"@id": "https://ch.example.org"}, {"@id": "https://other.example.org"}],
+        "https://w3id.org/idsa/core/transferContract": {"@id": "https://w3id.org/idsa/autogen/contract/0f3c9b2e-77a1"}
+    })
+}
+
+#[test]
+fn test_document_from_ids_message() -> Result<()>{
+    // prepare test data
+    let header = create_test_ids_header();
+
+    // run the test
+    let pid = derive_pid(&header)?;
+    let doc = document_from_ids_message(pid, &header, Some(b"{\"data\": 1}"), Some(String::from("application/json")))?;
+    let part = |name: &str| doc.parts.iter().find(|p| p.name == name).and_then(|p| p.content.clone());
+
+    // check
+    assert_eq!(doc.pid, "0f3c9b2e-77a1");
+    assert_eq!(doc.dt_id, "IDS_MESSAGE");
+    assert_eq!(part("message_id"), Some(String::from("https://w3id.org/idsa/autogen/logMessage/c6c15a91-7b2d-4d0d-b5b3-1f7d3f8e3b2a")));
+    assert_eq!(part("issued"), Some(String::from("2021-06-23T11:25:06.473+02:00")));
+    assert_eq!(part("issuer_connector"), Some(String::from("https://connector.example.org")));
+    assert_eq!(part("recipient_connector"), Some(String::from("[\"https://ch.example.org\",\"https://other.example.org\"]")));
+    assert_eq!(part("transfer_contract"), Some(String::from("https://w3id.org/idsa/autogen/contract/0f3c9b2e-77a1")));
+    assert_eq!(part("payload"), Some(String::from("{\"data\": 1}")));
+    assert_eq!(part("payload_type"), Some(String::from("application/json")));
+    assert_eq!(part("correlation_message"), None);
+
+    // binary payloads are kept as they are
+    let doc = document_from_ids_message(String::from("pid"), &header, Some(&[0xFF, 0x00]), None)?;
+    let payload = doc.parts.iter().find(|p| p.name == "payload").unwrap();
+    assert_eq!(payload.bytes()?, Some(vec!(0xFF, 0x00)));
+
+    // the process can not be derived without transfer contract
+    assert!(derive_pid(&serde_json::json!({"@type": "ids:LogMessage"})).is_err());
+    assert!(derive_pid(&serde_json::json!({"ids:transferContract": "https://example.org/contract?id=1"})).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_ids_response_messages() -> Result<()>{
+    // prepare test data
+    let request = create_test_ids_header();
+    let connector_id = "https://ch.example.org";
+
+    // run the test
+    let notification = message_processed_notification(&request, connector_id);
+    let rejection = rejection_message(&request, RejectionReason::MalformedMessage, connector_id);
+
+    // check
+    assert_eq!(notification["@type"], "ids:MessageProcessedNotificationMessage");
+    assert!(notification["@id"].as_str().unwrap().starts_with("https://w3id.org/idsa/autogen/messageProcessedNotificationMessage/"));
+    assert_eq!(notification["ids:correlationMessage"]["@id"], request["@id"]);
+    assert_eq!(notification["ids:issuerConnector"]["@id"], connector_id);
+    assert_eq!(notification["ids:recipientConnector"][0]["@id"], "https://connector.example.org");
+    assert_eq!(notification["ids:recipientAgent"][0]["@id"], "https://agent.example.org");
+    assert_eq!(rejection["@type"], "ids:RejectionMessage");
+    assert_eq!(rejection["ids:rejectionReason"]["@id"], "idsc:MALFORMED_MESSAGE");
+
+    // messages that could not be parsed are rejected without correlation
+    let rejection = rejection_message(&serde_json::Value::Null, RejectionReason::MalformedMessage, connector_id);
+    assert!(rejection.get("ids:correlationMessage").is_none());
+    assert_eq!(rejection["ids:modelVersion"], "4.0.0");
+
+    Ok(())
+}
+
+#[test]
+fn test_binary_document_part_encryption() -> Result<()>{
+
+    // prepare test data: content that is not valid UTF-8
+    let content: Vec<u8> = vec!(0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A, 0xFF, 0x00, 0x3A, 0x3A);
+    let part = DocumentPart::from_bytes(String::from("image"), &content);
+    let key = String::from("an example very very secret key.");
+    let nonce = String::from("unique nonce");
+
+    // run the test
+    let ct = part.encrypt(key.as_bytes(), nonce.as_bytes())?;
+    let result = DocumentPart::decrypt(key.as_bytes(), nonce.as_bytes(), ct.as_slice())?;
+
+    // check: raw bytes are stored, the content is returned base64 encoded
+    assert_eq!(ct.len(), 2 + 4 + "image".len() + content.len() + 16);
+    assert_eq!(result.name, "image");
+    assert_eq!(result.encoding, PartEncoding::Base64);
+    assert_eq!(result.content, part.content);
+    assert_eq!(result.bytes()?, Some(content));
+
+    // content that is not base64 encoded is rejected
+    let mut invalid = part.clone();
+    invalid.content = Some(String::from("not base64!"));
+    assert!(invalid.bytes().is_err());
+    assert!(invalid.encrypt(key.as_bytes(), nonce.as_bytes()).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_document_part_encoding_serialization() -> Result<()>{
+    // parts without encoding are text parts
+    let part: DocumentPart = serde_json::from_str(r#"{"name":"payload","content":"text"}"#)?;
+    assert_eq!(part.encoding, PartEncoding::Utf8);
+    assert_eq!(part.bytes()?, Some(b"text".to_vec()));
+
+    let part: DocumentPart = serde_json::from_str(r#"{"name":"payload","content":"AAH/","encoding":"base64"}"#)?;
+    assert_eq!(part.bytes()?, Some(vec!(0x00, 0x01, 0xFF)));
+    assert_eq!(serde_json::to_string(&part)?, r#"{"name":"payload","content":"AAH/","encoding":"base64"}"#);
+
+    Ok(())
+}
+
 #[test]
 fn test_document_encryption() -> Result<()>{
 
@@ -91,9 +422,10 @@ fn test_document_encryption() -> Result<()>{
     let doc = create_test_doc(dt.clone());
     let ts = Utc::now().timestamp();
     let key_ct = String::from("very secret key ciphertext");
-    let mut cts = vec!();
-    cts.push(String::from("1::4EBC3F1C2B8CB16C52E41424502FD112015D9C25919C2401514B5DD5B4233B65593CF0A4"));
-    cts.push(String::from("2::FE2195305E95B9F931660CBA20B4707A1D92123022371CEDD2E70A538A8771EE7540D9F34845BBAEECEC"));
+    let cts = vec!(
+        String::from("1::D1F3D7DAF2D3FDE1F9B6FD95F183183F15B17006CB8393ABBB55866545AFA3CAC89D7743B7333726"),
+        String::from("2::52194341BB3BADD4D048FAC97E7AF915156C1C3312B5A5655802BFDA417B91AF3FB5CA9C0D82BF7FAE71B694470B")
+    );
     let expected_doc = EncryptedDocument::new(doc.id.clone(), pid, dt, ts, 3241, key_ct, cts);
 
     // create KeyMap for encryption
@@ -118,7 +450,7 @@ fn test_document_encryption() -> Result<()>{
 #[test]
 fn test_document_decryption() -> Result<()>{
 
-    // prepare test data
+    // prepare test data: ciphertexts of the plaintext format of earlier versions
     let mut cts = vec!();
     let ts = Utc::now().timestamp();
     cts.push(String::from("1::4EBC3F1C2B8CB16C52E41424502FD112015D9C25919C2401514B5DD5B4233B65593CF0A4"));
@@ -148,6 +480,38 @@ fn test_document_decryption() -> Result<()>{
         assert_eq!(expected_doc.parts[i].content, result.parts[i].content);
     }
 
+    // a ciphertext that is not hex encoded is an error
+    let mut tampered = enc_doc.clone();
+    tampered.cts[1] = String::from("2::not hex");
+    assert!(tampered.decrypt(create_key_dec_map().keys).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_document_decryption_of_selected_parts() -> Result<()>{
+    // prepare test data
+    let cts = vec!(
+        String::from("1::4EBC3F1C2B8CB16C52E41424502FD112015D9C25919C2401514B5DD5B4233B65593CF0A4"),
+        String::from("2::FE2195305E95B9F931660CBA20B4707A1D92123022371CEDD2E70A538A8771EE7540D9F34845BBAEECEC")
+    );
+    let dt = String::from("ids_message");
+    let expected_doc = create_test_doc(dt.clone());
+    let enc_doc = EncryptedDocument::new(expected_doc.id.clone(), String::from("test_pid"), dt, Utc::now().timestamp(), 3241, String::from("very secure key ct"), cts);
+
+    // only the key of the second part is given
+    let mut dec_keys = create_key_dec_map();
+    dec_keys.keys.remove("1");
+
+    // decrypt
+    let result = enc_doc.decrypt_parts(dec_keys.keys.clone())?;
+    assert_eq!(result.parts.len(), 1);
+    assert_eq!(result.parts[0].name, expected_doc.parts[1].name);
+    assert_eq!(result.parts[0].content, expected_doc.parts[1].content);
+
+    // decryption of the whole document needs all keys
+    assert!(enc_doc.decrypt(dec_keys.keys).is_err());
+
     Ok(())
 }
 
@@ -168,9 +532,651 @@ fn test_encryption_hash() -> Result<()> {
     // need to fix otherwise random id
     expected_doc.id = String::from("a9a30044-7dfd-476f-a217-db1dc27aeb75");
 
-    let enc_doc = EncryptedDocument::new(expected_doc.id.clone(), pid, dt.clone(), ts_fixed, tc, key_ct, cts);
+    let mut enc_doc = EncryptedDocument::new(expected_doc.id.clone(), pid, dt.clone(), ts_fixed, tc, key_ct, cts);
+    // documents without hash version are hashed with version 0
+    enc_doc.hash_version = HashVersion::Legacy;
     let hash = enc_doc.hash();
     assert_eq!(expected_hash, hash);
 
     Ok(())
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_hash_versions() -> Result<()> {
+    // prepare test data: the fields of the documents differ, but their concatenation is the same
+    let cts = vec!(String::from("1::4EBC3F1C2B8CB16C"));
+    let mut doc1 = EncryptedDocument::new(String::from("ab"), String::from("c"), String::from("ids_message"), 1630413850, 1, String::from("4EBC3F1C"), cts.clone());
+    let mut doc2 = EncryptedDocument::new(String::from("a"), String::from("bc"), String::from("ids_message"), 1630413850, 1, String::from("4EBC3F1C"), cts.clone());
+
+    // run the test
+    for version in [HashVersion::Blake2b512, HashVersion::Sha256]{
+        doc1.hash_version = version;
+        doc2.hash_version = version;
+        assert_ne!(doc1.hash(), doc2.hash(), "version {}", version);
+    }
+    doc1.hash_version = HashVersion::Legacy;
+    doc2.hash_version = HashVersion::Legacy;
+    assert_eq!(doc1.hash(), doc2.hash());
+
+    // the algorithms produce different hashes
+    doc1.hash_version = HashVersion::Blake2b512;
+    let blake2b_hash = doc1.hash();
+    doc1.hash_version = HashVersion::Sha256;
+    assert_ne!(blake2b_hash, doc1.hash());
+    assert_eq!(base64::decode(&doc1.hash()).unwrap().len(), 32);
+
+    Ok(())
+}
+
+#[test]
+fn test_hash_version_serialization() -> Result<()> {
+    // documents stored before the hash version was introduced use version 0
+    let mut doc = create_test_chain("test_pid", 1).pop().unwrap();
+    let mut json = serde_json::to_value(&doc)?;
+    assert_eq!(json["hash_version"], serde_json::json!(1));
+    json.as_object_mut().unwrap().remove("hash_version");
+    let stored: EncryptedDocument = serde_json::from_value(json.clone())?;
+    assert_eq!(stored.hash_version, HashVersion::Legacy);
+
+    // unknown hash versions are rejected
+    json["hash_version"] = serde_json::json!(99);
+    assert!(serde_json::from_value::<EncryptedDocument>(json).is_err());
+
+    // chains with documents of different hash versions can be verified
+    doc.hash_version = HashVersion::Legacy;
+    let mut next = create_test_chain("test_pid", 2).pop().unwrap();
+    next.hash_version = HashVersion::Sha256;
+    next.hash = doc.hash();
+    assert!(verify_chain("test_pid", &[doc, next]).valid);
+
+    Ok(())
+}
+
+fn create_test_chain(pid: &str, length: i64) -> Vec<EncryptedDocument>{
+    let mut chain: Vec<EncryptedDocument> = vec!();
+    for tc in 0..length{
+        let cts = vec!(
+            format!("1::{:08}", tc)
+        );
+        let mut doc = EncryptedDocument::new(format!("doc_{}", tc), String::from(pid), String::from("ids_message"), 1630413850 + tc, tc, String::from("4EBC3F1C2B8CB16C"), cts);
+        if let Some(previous) = chain.last(){
+            doc.hash = previous.hash();
+        }
+        chain.push(doc);
+    }
+    chain
+}
+
+#[test]
+fn test_verify_chain() -> Result<()> {
+    // prepare test data
+    let chain = create_test_chain("test_pid", 4);
+
+    // run the test
+    let report = verify_chain("test_pid", &chain);
+
+    // check
+    assert!(report.valid);
+    assert_eq!(report.length, 4);
+    assert_eq!(report.head_hash, Some(chain[3].hash()));
+    assert!(report.first_broken_link.is_none());
+    assert!(report.missing_tcs.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn test_verify_chain_detects_modification() -> Result<()> {
+    // prepare test data
+    let mut chain = create_test_chain("test_pid", 4);
+    chain[1].cts[0] = String::from("1::00000042");
+
+    // run the test
+    let report = verify_chain("test_pid", &chain);
+
+    // the successor of the modified document no longer matches
+    assert!(!report.valid);
+    let broken_link = report.first_broken_link.unwrap();
+    assert_eq!(broken_link.tc, 2);
+    assert_eq!(broken_link.id, chain[2].id);
+    assert_eq!(broken_link.expected_hash, chain[1].hash());
+    assert_eq!(broken_link.stored_hash, chain[2].hash);
+
+    Ok(())
+}
+
+#[test]
+fn test_verify_chain_detects_missing_documents() -> Result<()> {
+    // prepare test data
+    let mut chain = create_test_chain("test_pid", 5);
+    chain.remove(3);
+    chain.remove(0);
+
+    // run the test
+    let report = verify_chain("test_pid", &chain);
+
+    // check
+    assert!(!report.valid);
+    assert_eq!(report.length, 3);
+    assert_eq!(report.missing_tcs, vec!(0, 3));
+    assert!(report.first_broken_link.is_none());
+
+    Ok(())
+}
+
+#[test]
+fn test_verify_chain_with_unreadable_documents() -> Result<()> {
+    // prepare test data: the last document of the chain could not be read
+    let mut chain = create_test_chain("test_pid", 3);
+    let last = chain.pop().unwrap();
+    assert!(verify_chain("test_pid", &chain).valid);
+    let unreadable = UnreadableDocument::new(Some(last.id.clone()), Some(last.tc), String::from("invalid type"));
+
+    // run the test
+    let report = verify_chain("test_pid", &chain).with_unreadable_documents(vec!(unreadable.clone()));
+
+    // check
+    assert!(!report.valid);
+    assert!(report.missing_tcs.is_empty());
+    assert_eq!(report.unreadable_documents, vec!(unreadable));
+
+    Ok(())
+}
+
+const TEST_TSA_CERTIFICATE: &str = "tests/tsa/tsa_cert.der";
+// time-stamp token over "test_chain_hash" created by the TSA of TEST_TSA_CERTIFICATE
+const TEST_TIMESTAMP_TOKEN: &str = "tests/tsa/test_token.der";
+// CMS SignedData over the TSTInfo of TEST_TIMESTAMP_TOKEN signed with the key of the TSA, but without signing certificate attribute
+const TEST_TOKEN_WITHOUT_SIGNING_CERTIFICATE: &str = "tests/tsa/test_token_without_signing_certificate.der";
+const TEST_SIGNING_KEY: &str = "tests/keys/signing_key.der";
+
+#[test]
+fn test_structural_violations() -> Result<()> {
+    // prepare test data
+    let mut doc = create_test_chain("test_pid", 1).pop().unwrap();
+    doc.cts = vec!(String::from("1::4EBC3F1C2B8CB16C"), String::from("2::FE2195305E95B9F9"));
+    assert!(doc.structural_violations().is_empty());
+
+    // run the test
+    doc.keys_ct = String::from("not hex");
+    doc.cts.push(String::from("3::not hex"));
+    doc.cts.push(String::from("4EBC3F1C2B8CB16C"));
+
+    // check
+    assert_eq!(doc.structural_violations().len(), 3);
+
+    Ok(())
+}
+
+#[test]
+fn test_scan_documents() -> Result<()> {
+    // prepare test data: a valid chain, a modified chain and a chain with a malformed document
+    let mut docs = create_test_chain("test_pid_1", 3);
+    let mut modified = create_test_chain("test_pid_2", 3);
+    modified[1].ts += 1;
+    let mut malformed = create_test_chain("test_pid_3", 1);
+    malformed[0].cts.push(String::from("not a ct"));
+    docs.extend(modified.clone());
+    docs.extend(malformed.clone());
+
+    // run the test
+    let alerts = scan_documents(&docs, 1630413850);
+
+    // check: only the chain hash of the successor of the modified document and the malformed document are reported
+    assert_eq!(alerts.len(), 2);
+    let broken_chain = alerts.iter().find(|a| a.kind == AlertKind::BrokenChain).unwrap();
+    assert_eq!(broken_chain.pid, String::from("test_pid_2"));
+    assert_eq!(broken_chain.doc_id, Some(modified[2].id.clone()));
+    let malformed_doc = alerts.iter().find(|a| a.kind == AlertKind::MalformedDocument).unwrap();
+    assert_eq!(malformed_doc.doc_id, Some(malformed[0].id.clone()));
+
+    Ok(())
+}
+
+#[test]
+fn test_unreadable_document_alerts() -> Result<()> {
+    // prepare test data: one document with a known tc and one without
+    let unreadable = vec!(
+        UnreadableDocument::new(Some(String::from("test_id_1")), Some(1), String::from("missing field `ts`")),
+        UnreadableDocument::new(None, None, String::from("missing field `id`"))
+    );
+
+    // run the test
+    let alerts = unreadable_document_alerts("test_pid", &unreadable, 1630413850);
+
+    // check
+    assert_eq!(alerts.len(), 2);
+    assert!(alerts.iter().all(|a| a.kind == AlertKind::UnreadableDocument && a.pid == "test_pid"));
+    assert_eq!(alerts[0].doc_id, Some(String::from("test_id_1")));
+    assert_eq!(alerts[0].description, String::from("Document with tc 1 could not be read: missing field `ts`"));
+    assert_eq!(alerts[1].doc_id, None);
+
+    Ok(())
+}
+
+#[test]
+fn test_compare_anchors() -> Result<()> {
+    // prepare test data
+    let chain = create_test_chain("test_pid", 4);
+    let anchors = vec!(
+        BlockchainMessage::new(String::from("test_pid"), String::from("1"), chain[1].hash()),
+        BlockchainMessage::new(String::from("test_pid"), String::from("3"), chain[3].hash()),
+        BlockchainMessage::new(String::from("other_pid"), String::from("0"), String::from("other_hash"))
+    );
+
+    // run the test
+    let report = compare_anchors("test_pid", &anchors, &chain);
+
+    // check: anchors of other processes are ignored
+    assert!(report.valid);
+    assert_eq!(report.anchors.len(), 2);
+    assert_eq!(report.anchors[1].tc, Some(3));
+
+    Ok(())
+}
+
+#[test]
+fn test_compare_anchors_detects_modification() -> Result<()> {
+    // prepare test data
+    let mut chain = create_test_chain("test_pid", 4);
+    let anchors = vec!(
+        BlockchainMessage::new(String::from("test_pid"), String::from("2"), chain[2].hash()),
+        BlockchainMessage::new(String::from("test_pid"), String::from("5"), String::from("unknown_hash"))
+    );
+
+    // modify an anchored document
+    chain[2].cts[0] = String::from("1::modified");
+
+    // run the test
+    let report = compare_anchors("test_pid", &anchors, &chain);
+
+    // check
+    assert!(!report.valid);
+    assert!(!report.anchors[0].matches);
+    assert_eq!(report.anchors[0].current_hash, Some(chain[2].hash()));
+    assert_eq!(report.anchors[1].current_hash, None);
+
+    Ok(())
+}
+
+#[test]
+fn test_merkle_inclusion() -> Result<()> {
+    // every leaf in trees of different sizes has to be verifiable
+    for size in 1..20{
+        let leaves: Vec<Vec<u8>> = (0..size).map(|i| leaf_hash(&format!("doc_hash_{}", i))).collect();
+        let root = root_hash(&leaves);
+        for index in 0..size{
+            let path = audit_path(index, &leaves);
+            assert!(verify_inclusion(&leaves[index], index, size, &path, &root), "leaf {} in tree of size {}", index, size);
+            // other leaves or indices must not verify
+            assert!(!verify_inclusion(&leaf_hash("other"), index, size, &path, &root));
+            if size > 1 {
+                assert!(!verify_inclusion(&leaves[index], (index + 1) % size, size, &path, &root));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_merkle_root_changes_with_leaves() -> Result<()> {
+    let leaves: Vec<Vec<u8>> = (0..5).map(|i| leaf_hash(&format!("doc_hash_{}", i))).collect();
+    let mut modified = leaves.clone();
+    modified[3] = leaf_hash("modified");
+
+    assert_ne!(root_hash(&leaves), root_hash(&modified));
+    assert_ne!(root_hash(&leaves), root_hash(&leaves[..4]));
+
+    Ok(())
+}
+
+#[test]
+fn test_signed_inclusion_proof() -> Result<()> {
+    // prepare test data
+    let key = SigningKey::from_file(TEST_SIGNING_KEY)?;
+    let leaves: Vec<Vec<u8>> = (0..7).map(|i| leaf_hash(&format!("doc_hash_{}", i))).collect();
+    let tree_head = TreeHead::new(String::from("test_pid"), leaves.len(), encode_hash(&root_hash(&leaves)), 1630413850);
+    let path = audit_path(2, &leaves).iter().map(|h| encode_hash(h)).collect();
+    let proof = InclusionProof::new(String::from("test_pid"), String::from("doc_2"), 2, leaves.len(), encode_hash(&leaves[2]), path, key.sign(tree_head.clone())?);
+
+    // run the test
+    let signed_tree_head: TreeHead = verify_signature(&proof.signed_tree_head, &key.jwks())?;
+
+    // check
+    assert_eq!(signed_tree_head, tree_head);
+    assert!(proof.verify(&signed_tree_head));
+
+    // modified signatures are rejected
+    let mut modified = proof.signed_tree_head.clone();
+    modified.push('A');
+    assert!(verify_signature::<TreeHead>(&modified, &key.jwks()).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_generate_signing_key() -> Result<()> {
+    // prepare test data
+    let dir = std::env::temp_dir().join(format!("test_generate_signing_key_{}", Document::create_uuid()));
+    let path = dir.join("keys").join("private_key.der");
+    let path = path.to_str().unwrap();
+
+    // run the test
+    let key = SigningKey::generate(path)?;
+
+    // the stored key is loaded again and its signatures are verified with the published JWKS
+    let loaded = SigningKey::from_file(path)?;
+    assert_eq!(loaded.kid(), key.kid());
+    let receipt = DocumentReceipt::new(1630413850, "test_pid", "test_doc", 3, "test_chain_hash", "test_doc_hash");
+    assert_eq!(verify_receipt(&key.sign(receipt.clone())?, &loaded.jwks())?, receipt);
+
+    // the content hashes do not change when the key is loaded again
+    let doc = create_test_doc(String::from("dt"));
+    assert_eq!(loaded.content_hash(&doc), key.content_hash(&doc));
+
+    // clean up
+    std::fs::remove_dir_all(dir)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_merkle_consistency() -> Result<()> {
+    // every prefix of trees of different sizes has to be consistent with the tree
+    let leaves: Vec<Vec<u8>> = (0..20).map(|i| leaf_hash(&format!("doc_hash_{}", i))).collect();
+    for second_size in 1..leaves.len(){
+        let second_root = root_hash(&leaves[..second_size]);
+        for first_size in 1..second_size + 1{
+            let first_root = root_hash(&leaves[..first_size]);
+            let proof = consistency_proof(first_size, &leaves[..second_size]);
+            assert!(verify_consistency(first_size, second_size, &first_root, &second_root, &proof), "tree of size {} and {}", first_size, second_size);
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_merkle_consistency_detects_rewrite() -> Result<()> {
+    // prepare test data
+    let leaves: Vec<Vec<u8>> = (0..11).map(|i| leaf_hash(&format!("doc_hash_{}", i))).collect();
+    let mut rewritten = leaves.clone();
+    rewritten[4] = leaf_hash("rewritten");
+    let first_root = root_hash(&leaves[..6]);
+
+    // run the test: the rewritten tree is not an extension of the first tree
+    let proof = consistency_proof(6, &rewritten);
+    assert!(!verify_consistency(6, 11, &first_root, &root_hash(&rewritten), &proof));
+
+    // proofs for other sizes do not verify
+    let proof = consistency_proof(6, &leaves);
+    assert!(!verify_consistency(5, 11, &first_root, &root_hash(&leaves), &proof));
+
+    Ok(())
+}
+
+#[test]
+fn test_signed_consistency_proof() -> Result<()> {
+    // prepare test data
+    let key = SigningKey::from_file(TEST_SIGNING_KEY)?;
+    let leaves: Vec<Vec<u8>> = (0..9).map(|i| leaf_hash(&format!("doc_hash_{}", i))).collect();
+    let first = TreeHead::new(String::from("test_pid"), 3, encode_hash(&root_hash(&leaves[..3])), 1630413850);
+    let second = TreeHead::new(String::from("test_pid"), 9, encode_hash(&root_hash(&leaves)), 1630413950);
+    let proof_path = consistency_proof(3, &leaves).iter().map(|h| encode_hash(h)).collect();
+    let proof = ConsistencyProof::new(String::from("test_pid"), 3, 9, proof_path, key.sign(second.clone())?);
+
+    // run the test
+    let signed_tree_head: TreeHead = verify_signature(&proof.signed_tree_head, &key.jwks())?;
+
+    // check
+    assert!(proof.verify(&first, &signed_tree_head));
+    assert!(!proof.verify(&second, &signed_tree_head));
+
+    Ok(())
+}
+
+#[test]
+fn test_verify_signed_receipt() -> Result<()> {
+    // prepare test data
+    let key = SigningKey::from_file(TEST_SIGNING_KEY)?;
+    let receipt = DocumentReceipt::new(1630413850, "test_pid", "test_doc", 3, "test_chain_hash", "test_doc_hash");
+    let signed_receipt = SignedDocumentReceipt::new(receipt.clone(), key.sign(receipt.clone())?, None);
+
+    // run the test
+    assert_eq!(signed_receipt.verify(&key.jwks())?, receipt);
+    assert_eq!(verify_receipt(&signed_receipt.signature, &key.jwks())?, receipt);
+
+    // a signature of another receipt is rejected
+    let mut modified = signed_receipt.clone();
+    modified.receipt.chain_hash = String::from("modified_chain_hash");
+    assert!(modified.verify(&key.jwks()).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_verify_timestamp_token() -> Result<()> {
+    // prepare test data
+    let token = std::fs::read(TEST_TIMESTAMP_TOKEN)?;
+    let tsa_certificate = std::fs::read(TEST_TSA_CERTIFICATE)?;
+
+    // run the test
+    let info = verify_timestamp_token(&token, "test_chain_hash", &tsa_certificate)?;
+
+    // check
+    assert!(info.gen_time > 1760000000);
+    assert_eq!(info.nonce, None);
+    assert_eq!(TimestampToken::new(String::from("test_chain_hash"), &token).verify(&tsa_certificate)?, info);
+
+    Ok(())
+}
+
+#[test]
+fn test_verify_timestamp_token_requires_signing_certificate() -> Result<()> {
+    // prepare test data
+    let token = std::fs::read(TEST_TOKEN_WITHOUT_SIGNING_CERTIFICATE)?;
+    let tsa_certificate = std::fs::read(TEST_TSA_CERTIFICATE)?;
+
+    // run the test: the signature is valid, but the token does not identify the certificate of the TSA
+    let error = verify_timestamp_token(&token, "test_chain_hash", &tsa_certificate).unwrap_err();
+    assert_eq!(error.to_string(), "Time-stamp token does not contain a signing certificate");
+
+    Ok(())
+}
+
+#[test]
+fn test_verify_timestamp_token_detects_modification() -> Result<()> {
+    // prepare test data
+    let token = std::fs::read(TEST_TIMESTAMP_TOKEN)?;
+    let tsa_certificate = std::fs::read(TEST_TSA_CERTIFICATE)?;
+
+    // the token is not valid for another chain hash
+    assert!(verify_timestamp_token(&token, "other_chain_hash", &tsa_certificate).is_err());
+
+    // every modified byte of the TSTInfo (bytes 60 to 148), the signed attributes (220 to 386) and the
+    // signature (last 256 bytes) of the test token is detected
+    for i in (60..149).chain(220..387).chain(token.len() - 256..token.len()){
+        let mut modified = token.clone();
+        modified[i] ^= 0x01;
+        assert!(verify_timestamp_token(&modified, "test_chain_hash", &tsa_certificate).is_err(), "modified byte {}", i);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_create_timestamp_request() -> Result<()> {
+    // run the test
+    let (request, nonce) = create_timestamp_request("test_chain_hash")?;
+    let (other_request, other_nonce) = create_timestamp_request("test_chain_hash")?;
+
+    // check: requests differ only in the nonce
+    assert_ne!(nonce, other_nonce);
+    assert_eq!(request.len() - nonce.len() / 2, other_request.len() - other_nonce.len() / 2);
+    assert!(hex::encode(&request).ends_with(&nonce));
+
+    Ok(())
+}
+
+#[test]
+fn test_audit_documents() -> Result<()> {
+    // prepare test data: a valid chain with signed receipts and anchors
+    let key = SigningKey::from_file(TEST_SIGNING_KEY)?;
+    let docs = create_test_chain("test_pid", 3);
+    let mut receipts = vec!();
+    for doc in docs.iter(){
+        let receipt = DocumentReceipt::new(doc.ts, &doc.pid, &doc.id, doc.tc, &doc.hash, &doc.hash());
+        receipts.push(SignedDocumentReceipt::new(receipt.clone(), key.sign(receipt)?, None));
+    }
+    let anchors = vec!(BlockchainMessage::new(String::from("test_pid"), String::from("2"), docs[2].hash()));
+
+    // run the test
+    let report = audit_documents(&docs, &receipts, Some(&key.jwks()), &anchors, None);
+
+    // check: one check for the chain, each receipt and the anchors
+    assert!(report.valid);
+    assert_eq!(report.checks.len(), 5);
+
+    Ok(())
+}
+
+#[test]
+fn test_audit_documents_detects_tampering() -> Result<()> {
+    // prepare test data
+    let key = SigningKey::from_file(TEST_SIGNING_KEY)?;
+    let mut docs = create_test_chain("test_pid", 3);
+    let receipt = DocumentReceipt::new(docs[1].ts, &docs[1].pid, &docs[1].id, docs[1].tc, &docs[1].hash, &docs[1].hash());
+    let mut forged = receipt.clone();
+    forged.tc = 5;
+    let receipts = vec!(
+        SignedDocumentReceipt::new(receipt.clone(), key.sign(receipt)?, None),
+        SignedDocumentReceipt::new(forged.clone(), key.sign(forged)?, None)
+    );
+    let anchors = vec!(BlockchainMessage::new(String::from("test_pid"), String::from("1"), docs[1].hash()));
+
+    // run the test: modify a document after the receipts were issued and the chain was anchored
+    docs[1].ts += 1;
+    let report = audit_documents(&docs, &receipts, Some(&key.jwks()), &anchors, None);
+
+    // check: the chain, both receipts and the anchor fail
+    assert!(!report.valid);
+    assert_eq!(report.checks.iter().filter(|c| !c.passed).count(), 4);
+
+    // receipts can not be verified without the JWKS of the clearing house
+    let report = audit_documents(&create_test_chain("test_pid", 3), &receipts[0..1], None, &[], None);
+    assert!(!report.valid);
+
+    Ok(())
+}
+
+fn encrypt_chunked(payload: &[u8], chunk_size: usize) -> Result<(Vec<Vec<u8>>, ChunkedPayload)> {
+    let key = String::from("an example very very secret key.");
+    let nonce = String::from("unique nonce");
+    let mut encryptor = ChunkEncryptor::new(key.as_bytes(), nonce.as_bytes(), chunk_size)?;
+    let mut chunks = vec!();
+    let mut pts: Vec<&[u8]> = payload.chunks(chunk_size).collect();
+    if pts.is_empty(){
+        pts.push(&[]);
+    }
+    for (i, pt) in pts.iter().enumerate(){
+        chunks.push(encryptor.encrypt_chunk(pt, i == pts.len() - 1)?);
+    }
+    let reference = encryptor.finish(String::from("test_file"), String::from("1"))?;
+    Ok((chunks, reference))
+}
+
+fn decrypt_chunked(chunks: &[Vec<u8>], reference: &ChunkedPayload) -> Result<Vec<u8>> {
+    let key = String::from("an example very very secret key.");
+    let nonce = String::from("unique nonce");
+    let mut decryptor = ChunkDecryptor::new(key.as_bytes(), nonce.as_bytes(), reference)?;
+    let mut payload = vec!();
+    for chunk in chunks.iter(){
+        payload.extend(decryptor.decrypt_chunk(chunk)?);
+    }
+    if !decryptor.is_finished(){
+        bail!("Payload is incomplete")
+    }
+    Ok(payload)
+}
+
+#[test]
+fn test_chunked_payload_encryption() -> Result<()> {
+    // payloads of different sizes, including an empty payload and a multiple of the chunk size
+    for size in [0, 1, 63, 64, 65, 640, 1000]{
+        // prepare test data
+        let payload: Vec<u8> = (0..size).map(|i| (i % 251) as u8).collect();
+
+        // run the test
+        let (chunks, reference) = encrypt_chunked(&payload, 64)?;
+
+        // check
+        assert_eq!(reference.length, size as i64);
+        assert_eq!(reference.chunk_count(), chunks.len() as i64);
+        assert!(chunks.iter().rev().skip(1).all(|c| c.len() == reference.stored_chunk_size()));
+        assert_eq!(decrypt_chunked(&chunks, &reference)?, payload);
+    }
+
+    // all chunks but the last need the full size and nothing can be encrypted after the last chunk
+    let key = String::from("an example very very secret key.");
+    let nonce = String::from("unique nonce");
+    let mut encryptor = ChunkEncryptor::new(key.as_bytes(), nonce.as_bytes(), 64)?;
+    assert!(encryptor.encrypt_chunk(&[0u8; 10], false).is_err());
+    assert!(encryptor.encrypt_chunk(&[0u8; 65], true).is_err());
+    encryptor.encrypt_chunk(&[0u8; 10], true)?;
+    assert!(encryptor.encrypt_chunk(&[0u8; 10], true).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_chunked_payload_detects_modification() -> Result<()> {
+    // prepare test data
+    let payload: Vec<u8> = (0..200).map(|i| i as u8).collect();
+    let (chunks, reference) = encrypt_chunked(&payload, 64)?;
+
+    // reordered chunks
+    let mut reordered = chunks.clone();
+    reordered.swap(0, 1);
+    assert!(decrypt_chunked(&reordered, &reference).is_err());
+
+    // dropped last chunk: the previous chunk was not encrypted as last chunk
+    let mut truncated = chunks.clone();
+    truncated.pop();
+    let mut truncated_reference = reference.clone();
+    truncated_reference.length = 192;
+    assert!(decrypt_chunked(&truncated, &truncated_reference).is_err());
+    assert!(decrypt_chunked(&truncated, &reference).is_err());
+
+    // modified chunk
+    let mut modified = chunks.clone();
+    modified[2][0] ^= 0x01;
+    assert!(decrypt_chunked(&modified, &reference).is_err());
+
+    // chunks of another payload encrypted with the same key do not match the digest of the document
+    let (other_chunks, _) = encrypt_chunked(&[7u8; 200], 64)?;
+    assert!(decrypt_chunked(&other_chunks, &reference).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_hash_covers_chunked_payload() -> Result<()> {
+    // prepare test data
+    let mut doc = create_test_chain("test_pid", 1).pop().unwrap();
+    let hash_without_payload = doc.hash();
+    let (_, reference) = encrypt_chunked(&[1u8; 100], 64)?;
+
+    // run the test
+    doc.payload = Some(reference.clone());
+    let hash_with_payload = doc.hash();
+
+    // check: documents without payload keep their hash, the payload reference is covered by the hash
+    assert_ne!(hash_without_payload, hash_with_payload);
+    doc.payload.as_mut().unwrap().digest = String::from("modified digest");
+    assert_ne!(doc.hash(), hash_with_payload);
+    doc.payload = None;
+    assert_eq!(doc.hash(), hash_without_payload);
+
+    Ok(())
+}
@@ -0,0 +1,115 @@
+use biscuit::{
+    CompactJson,
+    Empty,
+    jwa::SignatureAlgorithm,
+    jwk::{JWK, JWKSet},
+    jws::{Compact, Header, RegisteredHeader, Secret},
+};
+use blake2_rfc::blake2b::Blake2b;
+use openssl::rsa::Rsa;
+use ring::signature::KeyPair;
+use std::path::Path;
+use crate::errors::*;
+use crate::model::document::Document;
+
+const SIGNING_ALGORITHM: SignatureAlgorithm = SignatureAlgorithm::RS256;
+/// size of generated signing keys in bits
+const SIGNING_KEY_BITS: u32 = 2048;
+/// separates the derivation of the key of the content hashes from other uses of the private key
+const CONTENT_KEY_CONTEXT: &[u8] = b"clearing-house content hash";
+
+/// RSA key the clearing house uses to sign its statements, e.g. tree heads and receipts
+#[derive(Clone)]
+pub struct SigningKey {
+    kid: String,
+    secret: Secret,
+    jwk: JWK<Empty>,
+    /// key of the content hashes of documents, derived from the private key
+    content_key: Vec<u8>,
+}
+
+impl SigningKey {
+    /// Loads a DER encoded RSA private key (RSAPrivateKey format)
+    pub fn from_file(path: &str) -> Result<SigningKey> {
+        let secret = Secret::rsa_keypair_from_file(path)?;
+        let mut hasher = Blake2b::new(64);
+        hasher.update(CONTENT_KEY_CONTEXT);
+        hasher.update(&std::fs::read(path)?);
+        let content_key = hasher.finalize().as_bytes().to_vec();
+        let (kid, jwk) = match &secret {
+            Secret::RsaKeyPair(key_pair) => {
+                let public_key = key_pair.public_key();
+                let n = public_key.modulus().big_endian_without_leading_zero().to_vec();
+                let e = public_key.exponent().big_endian_without_leading_zero().to_vec();
+
+                // the key id is derived from the public key
+                let mut hasher = Blake2b::new(16);
+                hasher.update(public_key.as_ref());
+                let kid = hex::encode(hasher.finalize().as_bytes());
+
+                let jwk: JWK<Empty> = serde_json::from_value(serde_json::json!({
+                    "kty": "RSA",
+                    "use": "sig",
+                    "alg": "RS256",
+                    "kid": &kid,
+                    "n": base64::encode_config(&n, base64::URL_SAFE_NO_PAD),
+                    "e": base64::encode_config(&e, base64::URL_SAFE_NO_PAD),
+                }))?;
+                (kid, jwk)
+            },
+            _ => bail!("Signing key needs to be an RSA key")
+        };
+        debug!("loaded signing key with kid '{}'", &kid);
+        Ok(SigningKey {
+            kid,
+            secret,
+            jwk,
+            content_key,
+        })
+    }
+
+    /// Generates a new RSA key, stores it DER encoded (RSAPrivateKey format) at path and loads it
+    pub fn generate(path: &str) -> Result<SigningKey> {
+        let der = Rsa::generate(SIGNING_KEY_BITS)?.private_key_to_der()?;
+        if let Some(dir) = Path::new(path).parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, der)?;
+        SigningKey::from_file(path)
+    }
+
+    pub fn kid(&self) -> String {
+        self.kid.clone()
+    }
+
+    /// public part of the signing key
+    pub fn jwks(&self) -> JWKSet<Empty> {
+        JWKSet {
+            keys: vec!(self.jwk.clone())
+        }
+    }
+
+    /// Keyed hash of the content of the document, see `Document::content_hash`.
+    /// Documents hashed with another signing key have different hashes.
+    pub fn content_hash(&self, doc: &Document) -> String {
+        doc.content_hash(&self.content_key)
+    }
+
+    /// Signs the payload and returns the JWS in compact serialization
+    pub fn sign<T: CompactJson>(&self, payload: T) -> Result<String> {
+        let header = Header::from_registered_header(RegisteredHeader {
+            algorithm: SIGNING_ALGORITHM,
+            key_id: Some(self.kid.clone()),
+            ..Default::default()
+        });
+        let jws = Compact::new_decoded(header, payload).into_encoded(&self.secret)?;
+        Ok(jws.unwrap_encoded().encode())
+    }
+}
+
+/// Verifies the signature of a JWS created by `SigningKey::sign` and returns its payload
+pub fn verify_signature<T: CompactJson>(jws: &str, jwks: &JWKSet<Empty>) -> Result<T> {
+    let compact: Compact<T, Empty> = Compact::new_encoded(jws);
+    let decoded = compact.decode_with_jwks(jwks, Some(SIGNING_ALGORITHM))?;
+    Ok(decoded.unwrap_decoded().1)
+}
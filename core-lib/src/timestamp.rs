@@ -0,0 +1,315 @@
+//! RFC 3161 time-stamp requests and verification of time-stamp tokens.
+//! The CMS signature of a token is verified by OpenSSL. The attributes specific to time-stamp tokens
+//! and the TSTInfo are read with the parts of the DER encoding that are needed for this.
+use chrono::NaiveDateTime;
+use openssl::cms::{CMSOptions, CmsContentInfo};
+use openssl::stack::Stack;
+use openssl::x509::X509;
+use ring::digest;
+use ring::rand::{SecureRandom, SystemRandom};
+use crate::errors::*;
+
+// DER tags
+const TAG_BOOLEAN: u8 = 0x01;
+const TAG_INTEGER: u8 = 0x02;
+const TAG_OCTET_STRING: u8 = 0x04;
+const TAG_NULL: u8 = 0x05;
+const TAG_OID: u8 = 0x06;
+const TAG_GENERALIZED_TIME: u8 = 0x18;
+const TAG_SEQUENCE: u8 = 0x30;
+const TAG_SET: u8 = 0x31;
+const TAG_CONTEXT_0: u8 = 0xa0;
+
+// object identifiers (content octets only)
+const OID_SHA256: &[u8] = &[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01];
+const OID_SHA384: &[u8] = &[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x02];
+const OID_SHA512: &[u8] = &[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x03];
+const OID_SIGNED_DATA: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x07, 0x02];
+const OID_CONTENT_TYPE: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x09, 0x03];
+const OID_TST_INFO: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x09, 0x10, 0x01, 0x04];
+const OID_SIGNING_CERTIFICATE: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x09, 0x10, 0x02, 0x0c];
+const OID_SIGNING_CERTIFICATE_V2: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x09, 0x10, 0x02, 0x2f];
+
+// PKIStatus granted and grantedWithMods
+const MAX_GRANTED_STATUS: u8 = 1;
+
+/// RFC 3161 time-stamp token over a chain hash. The token is the base64 encoded DER of the
+/// CMS SignedData returned by the TSA.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct TimestampToken {
+    pub hash: String,
+    pub token: String,
+}
+
+impl TimestampToken{
+    pub fn new(hash: String, token: &[u8]) -> TimestampToken{
+        TimestampToken{
+            hash,
+            token: base64::encode(token),
+        }
+    }
+
+    /// verifies the token against the DER encoded certificate of the TSA
+    pub fn verify(&self, tsa_certificate: &[u8]) -> Result<TimestampInfo>{
+        let token = base64::decode(&self.token).chain_err(|| "Time-stamp token is not base64 encoded")?;
+        verify_timestamp_token(&token, &self.hash, tsa_certificate)
+    }
+}
+
+/// The content of a time-stamp token that is relevant for the clearing house
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct TimestampInfo {
+    pub gen_time: i64,
+    pub serial: String,
+    pub nonce: Option<String>,
+}
+
+/// Creates a DER encoded time-stamp request for the SHA-256 hash of the chain hash.
+/// Returns the request and the hex encoded nonce the response needs to contain.
+pub fn create_timestamp_request(chain_hash: &str) -> Result<(Vec<u8>, String)>{
+    let mut nonce = [0u8; 8];
+    SystemRandom::new().fill(&mut nonce).map_err(|_| Error::from("Could not create nonce"))?;
+
+    let hash = digest::digest(&digest::SHA256, chain_hash.as_bytes());
+    let algorithm = encode(TAG_SEQUENCE, &[encode(TAG_OID, OID_SHA256), encode(TAG_NULL, &[])].concat());
+    let message_imprint = encode(TAG_SEQUENCE, &[algorithm, encode(TAG_OCTET_STRING, hash.as_ref())].concat());
+    let request = encode(TAG_SEQUENCE, &[encode_integer(&[1]), message_imprint, encode_integer(&nonce)].concat());
+    Ok((request, hex::encode(trim_integer(&nonce))))
+}
+
+/// Extracts the time-stamp token from a DER encoded time-stamp response, if the request was granted
+pub fn read_timestamp_response(response: &[u8]) -> Result<Vec<u8>>{
+    let mut resp = Der::new(Der::new(response).read(TAG_SEQUENCE)?);
+    let mut status_info = Der::new(resp.read(TAG_SEQUENCE)?);
+    let status = status_info.read(TAG_INTEGER)?;
+    if status.len() != 1 || status[0] > MAX_GRANTED_STATUS {
+        bail!("Time-stamp request was rejected with status {:?}", status);
+    }
+    Ok(resp.read_raw(TAG_SEQUENCE)?.to_vec())
+}
+
+/// Verifies that the DER encoded time-stamp token was signed by the TSA and is issued for the chain hash
+pub fn verify_timestamp_token(token: &[u8], chain_hash: &str, tsa_certificate: &[u8]) -> Result<TimestampInfo>{
+    // the token needs to be signed by the tsa. Only the configured certificate is accepted as signer,
+    // so the certificates in the token and the chain of the certificate are not used
+    let certificate = X509::from_der(tsa_certificate).chain_err(|| "Invalid TSA certificate")?;
+    let mut certificates = Stack::new()?;
+    certificates.push(certificate)?;
+    let mut content_info = CmsContentInfo::from_der(token).chain_err(|| "Time-stamp token is not a CMS ContentInfo")?;
+    let mut tst_info = vec!();
+    let options = CMSOptions::NOINTERN | CMSOptions::NO_SIGNER_CERT_VERIFY | CMSOptions::BINARY;
+    content_info.verify(Some(&certificates), None, None, Some(&mut tst_info), options)
+        .chain_err(|| "Signature of time-stamp token is invalid")?;
+    check_signed_attributes(token, tsa_certificate)?;
+
+    // the token needs to be issued for the chain hash
+    let mut tst_info = Der::new(Der::new(&tst_info).read(TAG_SEQUENCE)?);
+    tst_info.read(TAG_INTEGER)?;
+    tst_info.read(TAG_OID)?;
+    let mut message_imprint = Der::new(tst_info.read(TAG_SEQUENCE)?);
+    let imprint_algorithm = Der::new(message_imprint.read(TAG_SEQUENCE)?).read(TAG_OID)?;
+    let imprint = message_imprint.read(TAG_OCTET_STRING)?;
+    if imprint != digest::digest(digest_algorithm(imprint_algorithm)?, chain_hash.as_bytes()).as_ref() {
+        bail!("Time-stamp token was not issued for chain hash '{}'", chain_hash);
+    }
+    let serial = hex::encode(tst_info.read(TAG_INTEGER)?);
+    let gen_time = parse_generalized_time(tst_info.read(TAG_GENERALIZED_TIME)?)?;
+
+    // skip accuracy and ordering to get the optional nonce
+    let mut nonce = None;
+    while !tst_info.is_empty() {
+        let (tag, value) = tst_info.next()?;
+        if tag == TAG_INTEGER {
+            nonce = Some(hex::encode(value));
+            break;
+        }
+        if tag != TAG_SEQUENCE && tag != TAG_BOOLEAN {
+            break;
+        }
+    }
+    Ok(TimestampInfo {
+        gen_time,
+        serial,
+        nonce,
+    })
+}
+
+/// Checks the signed attributes that RFC 3161 requires in addition to CMS: the content type has to be
+/// TSTInfo and the signing certificate attribute has to identify the certificate of the TSA
+fn check_signed_attributes(token: &[u8], tsa_certificate: &[u8]) -> Result<()>{
+    let mut content_info = Der::new(Der::new(token).read(TAG_SEQUENCE)?);
+    if content_info.read(TAG_OID)? != OID_SIGNED_DATA {
+        bail!("Time-stamp token is not a SignedData");
+    }
+    let mut signed_data = Der::new(Der::new(content_info.read(TAG_CONTEXT_0)?).read(TAG_SEQUENCE)?);
+    signed_data.read(TAG_INTEGER)?;
+    signed_data.read(TAG_SET)?;
+    if Der::new(signed_data.read(TAG_SEQUENCE)?).read(TAG_OID)? != OID_TST_INFO {
+        bail!("Time-stamp token does not contain a TSTInfo");
+    }
+
+    // skip the optional certificates and crls
+    let mut signer_infos = loop {
+        let (tag, value) = signed_data.next()?;
+        if tag == TAG_SET {
+            break Der::new(value);
+        }
+    };
+    let mut signer_info = Der::new(signer_infos.read(TAG_SEQUENCE)?);
+    if !signer_infos.is_empty() {
+        bail!("Time-stamp token has more than one signer");
+    }
+    signer_info.read(TAG_INTEGER)?;
+    signer_info.next()?;
+    signer_info.read(TAG_SEQUENCE)?;
+
+    let mut content_type = None;
+    let mut signing_certificate = None;
+    let mut attrs = Der::new(signer_info.read(TAG_CONTEXT_0)?);
+    while !attrs.is_empty() {
+        let mut attr = Der::new(attrs.read(TAG_SEQUENCE)?);
+        let oid = attr.read(TAG_OID)?;
+        let mut values = Der::new(attr.read(TAG_SET)?);
+        match oid {
+            OID_CONTENT_TYPE => content_type = Some(values.read(TAG_OID)?),
+            OID_SIGNING_CERTIFICATE | OID_SIGNING_CERTIFICATE_V2 => signing_certificate = Some((oid, values.read(TAG_SEQUENCE)?)),
+            _ => ()
+        }
+    }
+
+    if content_type != Some(OID_TST_INFO) {
+        bail!("Signed content type of time-stamp token is not TSTInfo");
+    }
+    match signing_certificate {
+        Some((oid, value)) => check_signing_certificate(oid, value, tsa_certificate),
+        None => bail!("Time-stamp token does not contain a signing certificate")
+    }
+}
+
+/// The first ESSCertID of a SigningCertificate (SHA-1) or ESSCertIDv2 of a SigningCertificateV2
+/// (SHA-256 unless stated otherwise) has to contain the hash of the certificate of the TSA
+fn check_signing_certificate(oid: &[u8], value: &[u8], tsa_certificate: &[u8]) -> Result<()>{
+    let mut cert_ids = Der::new(Der::new(value).read(TAG_SEQUENCE)?);
+    let mut cert_id = Der::new(cert_ids.read(TAG_SEQUENCE)?);
+    let (algorithm, cert_hash) = if oid == OID_SIGNING_CERTIFICATE {
+        (&digest::SHA1_FOR_LEGACY_USE_ONLY, cert_id.read(TAG_OCTET_STRING)?)
+    }
+    else {
+        match cert_id.next()? {
+            (TAG_SEQUENCE, hash_algorithm) => (digest_algorithm(Der::new(hash_algorithm).read(TAG_OID)?)?, cert_id.read(TAG_OCTET_STRING)?),
+            (TAG_OCTET_STRING, cert_hash) => (&digest::SHA256, cert_hash),
+            (t, _) => bail!("Unexpected DER tag {:#x} in signing certificate of time-stamp token", t)
+        }
+    };
+    if digest::digest(algorithm, tsa_certificate).as_ref() != cert_hash {
+        bail!("Time-stamp token was not signed with the certificate of the TSA");
+    }
+    Ok(())
+}
+
+fn digest_algorithm(oid: &[u8]) -> Result<&'static digest::Algorithm>{
+    match oid {
+        OID_SHA256 => Ok(&digest::SHA256),
+        OID_SHA384 => Ok(&digest::SHA384),
+        OID_SHA512 => Ok(&digest::SHA512),
+        _ => bail!("Digest algorithm of time-stamp token is not supported")
+    }
+}
+
+/// GeneralizedTime is UTC in the format YYYYMMDDhhmmss[.s...]Z
+fn parse_generalized_time(value: &[u8]) -> Result<i64>{
+    let time = std::str::from_utf8(value).chain_err(|| "Invalid time in time-stamp token")?;
+    let time = NaiveDateTime::parse_from_str(time.trim_end_matches('Z'), "%Y%m%d%H%M%S%.f")
+        .chain_err(|| "Invalid time in time-stamp token")?;
+    Ok(time.and_utc().timestamp())
+}
+
+fn encode(tag: u8, content: &[u8]) -> Vec<u8>{
+    let mut tlv = vec!(tag);
+    let len = content.len();
+    if len < 0x80 {
+        tlv.push(len as u8);
+    }
+    else{
+        let len_bytes: Vec<u8> = len.to_be_bytes().iter().cloned().skip_while(|b| *b == 0).collect();
+        tlv.push(0x80 | len_bytes.len() as u8);
+        tlv.extend(len_bytes);
+    }
+    tlv.extend_from_slice(content);
+    tlv
+}
+
+// minimal encoding of a positive integer
+fn trim_integer(value: &[u8]) -> Vec<u8>{
+    let mut trimmed: Vec<u8> = value.iter().cloned().skip_while(|b| *b == 0).collect();
+    if trimmed.first().is_none_or(|b| b & 0x80 != 0) {
+        trimmed.insert(0, 0);
+    }
+    trimmed
+}
+
+fn encode_integer(value: &[u8]) -> Vec<u8>{
+    encode(TAG_INTEGER, &trim_integer(value))
+}
+
+/// Reader for consecutive DER encoded values
+struct Der<'a> {
+    input: &'a [u8],
+}
+
+impl<'a> Der<'a> {
+    fn new(input: &'a [u8]) -> Der<'a>{
+        Der {
+            input
+        }
+    }
+
+    fn is_empty(&self) -> bool{
+        self.input.is_empty()
+    }
+
+    /// returns the raw encoding of the next value
+    fn next_raw(&mut self) -> Result<(u8, &'a [u8], &'a [u8])>{
+        if self.input.len() < 2 {
+            bail!("Unexpected end of DER input");
+        }
+        let tag = self.input[0];
+        let (len, header) = match self.input[1] {
+            l if l < 0x80 => (l as usize, 2),
+            l => {
+                let n = (l & 0x7f) as usize;
+                if n == 0 || n > 4 || self.input.len() < 2 + n {
+                    bail!("Invalid DER length");
+                }
+                let len = self.input[2..2 + n].iter().fold(0usize, |len, b| (len << 8) | *b as usize);
+                (len, 2 + n)
+            }
+        };
+        if self.input.len() < header + len {
+            bail!("Unexpected end of DER input");
+        }
+        let raw = &self.input[..header + len];
+        let content = &self.input[header..header + len];
+        self.input = &self.input[header + len..];
+        Ok((tag, raw, content))
+    }
+
+    fn next(&mut self) -> Result<(u8, &'a [u8])>{
+        let (tag, _, content) = self.next_raw()?;
+        Ok((tag, content))
+    }
+
+    fn read(&mut self, tag: u8) -> Result<&'a [u8]>{
+        match self.next()? {
+            (t, content) if t == tag => Ok(content),
+            (t, _) => bail!("Unexpected DER tag {:#x}, expected {:#x}", t, tag)
+        }
+    }
+
+    fn read_raw(&mut self, tag: u8) -> Result<&'a [u8]>{
+        match self.next_raw()? {
+            (t, raw, _) if t == tag => Ok(raw),
+            (t, _, _) => bail!("Unexpected DER tag {:#x}, expected {:#x}", t, tag)
+        }
+    }
+}
@@ -9,18 +9,17 @@ use crate::errors::*;
 use figment::{Figment, providers::{Format, Yaml}};
 
 pub fn load_from_test_config(key: &str, file: &str) -> String{
-    Figment::new().merge(Yaml::file(file)).extract_inner(key).unwrap_or(String::new())
+    Figment::new().merge(Yaml::file(file)).extract_inner(key).unwrap_or_default()
 }
 
 /// setup the fern logger and set log level to environment variable `ENV_API_LOG_LEVEL`
 /// allowed levels: `Off`, `Error`, `Warn`, `Info`, `Debug`, `Trace`
 pub fn setup_logger() -> Result<()> {
-    let log_level;
-    match env::var(ENV_API_LOG_LEVEL){
-        Ok(l) => log_level = l.clone(),
+    let log_level = match env::var(ENV_API_LOG_LEVEL){
+        Ok(l) => l,
         Err(_e) => {
             println!("Log level not set correctly. Logging disabled");
-            log_level = String::from("Off")
+            String::from("Off")
         }
     };
 
@@ -34,7 +33,7 @@ pub fn setup_logger() -> Result<()> {
                 message
             ))
         })
-        .level(log::LevelFilter::from_str(&log_level.as_str())?)
+        .level(log::LevelFilter::from_str(log_level.as_str())?)
         .chain(std::io::stdout())
         .chain(fern::log_file("output.log")?)
         .apply()?;
@@ -51,4 +50,12 @@ pub fn read_file(file: &str) -> Result<String> {
 
 pub fn url_encode(id: &str) -> String{
     utf8_percent_encode(id, NON_ALPHANUMERIC).to_string()
-}
\ No newline at end of file
+}
+/// splits a comma separated list, e.g. of part names given as query parameter. Empty entries are left out.
+pub fn split_list(list: &str) -> Vec<String>{
+    list.split(',')
+        .map(|entry| entry.trim())
+        .filter(|entry| !entry.is_empty())
+        .map(String::from)
+        .collect()
+}
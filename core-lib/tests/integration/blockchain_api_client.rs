@@ -1,29 +1,30 @@
-use core_lib::constants::{CONFIG_FILE, BLOCKCHAIN_API_URL};
-use core_lib::util;
+use core_lib::api::ApiClient;
 use core_lib::errors::*;
 use core_lib::api::client::blockchain_api::BlockchainApiClient;
+use crate::mock_ledger::launch_mock_ledger;
 
-/// before running make sure the blockchain api is available
-#[test]
-fn test_store_hash() -> Result<()>{
+/// Testcase: Standard case: anchor a hash
+#[rocket::async_test]
+async fn test_store_hash() -> Result<()>{
     // configure client_api
-    let config = util::load_config(CONFIG_FILE);
-    let bc_api: BlockchainApiClient = util::configure_api(BLOCKCHAIN_API_URL, &config)?;
+    let api_url = launch_mock_ledger(8010).await;
+    let bc_api = BlockchainApiClient::new(&api_url);
 
     let id = String::from("999");
     let cid = String::from("123");
     let hash = String::from("ABCD-EFGH");
 
-    assert_eq!(bc_api.store_hash(&id, &cid, &hash)?, true);
+    assert!(bc_api.store_hash(&id, &cid, &hash).await?);
 
     Ok(())
 }
 
-#[test]
-fn test_get_hash_list() -> Result<()>{
+/// Testcase: Standard case: retrieve all anchored hashes of an id
+#[rocket::async_test]
+async fn test_get_hash_list() -> Result<()>{
     // configure client_api
-    let config = util::load_config(CONFIG_FILE);
-    let bc_api: BlockchainApiClient = util::configure_api(BLOCKCHAIN_API_URL, &config)?;
+    let api_url = launch_mock_ledger(8011).await;
+    let bc_api = BlockchainApiClient::new(&api_url);
 
     let id = String::from("999");
     let cid1 = String::from("123");
@@ -31,12 +32,14 @@ fn test_get_hash_list() -> Result<()>{
     let cid2 = String::from("5556");
     let hash2 = String::from("ZAZS-QWEA");
 
-    assert_eq!(bc_api.store_hash(&id, &cid1, &hash1)?, true);
-    assert_eq!(bc_api.store_hash(&id, &cid2, &hash2)?, true);
+    assert!(bc_api.store_hash(&id, &cid1, &hash1).await?);
+    assert!(bc_api.store_hash(&id, &cid2, &hash2).await?);
+    assert!(bc_api.store_hash(&String::from("1000"), &cid1, &hash1).await?);
 
-    let result = bc_api.get_hash_list(&id)?;
+    let result = bc_api.get_hash_list(&id).await?;
 
     assert_eq!(result.len(), 2);
+    assert_eq!(result[1].hash, hash2);
 
     Ok(())
-}
\ No newline at end of file
+}
@@ -4,10 +4,13 @@
 // Before running the tests make sure that there's a valid token in auth/mod.rs
 // Also note: Clean up will not work if a test fails.
 // !!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!
-use core_lib::api::ApiClient;
-use core_lib::constants::DOCUMENT_API_URL;
+use core_lib::api::{ApiClient, DocumentQuery, SortField, SortOrder, verify_receipt};
+use core_lib::constants::{DOCUMENT_API_URL, PAYLOAD_PART};
+use core_lib::model::document::{Document, DocumentPart};
+use std::io::{Cursor, Read};
 use core_lib::util;
 use core_lib::errors::*;
+use core_lib::errors::ErrorKind::IntegrityViolation;
 use core_lib::api::client::document_api::DocumentApiClient;
 use crate::{TOKEN, create_test_document, delete_test_doc_type_from_keyring, insert_test_doc_type_into_keyring, TEST_CONFIG};
 
@@ -28,7 +31,8 @@ fn test_store_first_document() -> Result<()> {
 
     // run the test
     let result = doc_api.create_document(&TOKEN.to_string(), &expected_doc)?;
-    assert_eq!(result.chain_hash, String::from("0"));
+    assert_eq!(result.receipt.tc, 0);
+    assert_eq!(result.receipt.chain_hash, String::from("0"));
 
     // clean up
     assert!(doc_api.delete_document(&TOKEN.to_string(), &expected_doc.pid, &expected_doc.id)?);
@@ -59,7 +63,8 @@ fn test_store_chained_document() -> Result<()> {
 
     // run the test
     let result = doc_api.create_document(&TOKEN.to_string(), &second_doc)?;
-    assert_ne!(result.chain_hash, String::from("0"));
+    assert_eq!(result.receipt.tc, 1);
+    assert_ne!(result.receipt.chain_hash, String::from("0"));
 
     // clean up
     assert!(doc_api.delete_document(&TOKEN.to_string(), &first_doc.pid, &first_doc.id)?);
@@ -71,6 +76,133 @@ fn test_store_chained_document() -> Result<()> {
     Ok(())
 }
 
+/// Testcase: Batch of documents is chained in order, invalid documents are reported per item
+#[test]
+fn test_store_batch_of_documents() -> Result<()> {
+    // configure client_api
+    let api_url = util::load_from_test_config(DOCUMENT_API_URL, TEST_CONFIG);
+    let doc_api = DocumentApiClient::new(&api_url);
+
+    // prepare test data
+    let dt_id = String::from("test_store_batch_of_documents_dt");
+    let pid = String::from("test_store_batch_of_documents_pid");
+    let first_doc = create_test_document(&pid, &dt_id, 0);
+    let second_doc = create_test_document(&pid, &dt_id, 1);
+    let mut invalid_doc = create_test_document(&pid, &dt_id, 2);
+    invalid_doc.parts.retain(|p| p.name != PAYLOAD_PART);
+    let mut empty_doc = create_test_document(&pid, &dt_id, 3);
+    empty_doc.parts.iter_mut().filter(|p| p.name == PAYLOAD_PART).for_each(|p| p.content = None);
+    // clean up doc type (in case of previous test failure)
+    delete_test_doc_type_from_keyring(&TOKEN.to_string(), &pid, &dt_id)?;
+    insert_test_doc_type_into_keyring(&TOKEN.to_string(), &pid, &dt_id)?;
+
+    // run the test
+    let result = doc_api.create_documents(&TOKEN.to_string(), &pid, &[first_doc.clone(), invalid_doc.clone(), second_doc.clone()])?;
+    assert_eq!(result.len(), 3);
+    let first = result[0].receipt.as_ref().unwrap();
+    let second = result[2].receipt.as_ref().unwrap();
+    assert_eq!(first.receipt.tc, 0);
+    assert_eq!(second.receipt.tc, 1);
+    assert_eq!(result[1].id, invalid_doc.id);
+    assert!(result[1].receipt.is_none());
+    assert_eq!(result[1].error, Some(String::from("Document contains no payload!")));
+
+    // the documents are chained in the order of the batch
+    let report = doc_api.verify_chain(&TOKEN.to_string(), &pid)?;
+    assert!(report.valid);
+    assert_eq!(report.length, 2);
+
+    // a batch without valid documents is rejected, the errors are still reported per document
+    let result = doc_api.create_documents(&TOKEN.to_string(), &pid, &[invalid_doc.clone(), empty_doc.clone()])?;
+    assert_eq!(result.len(), 2);
+    assert_eq!(result[0].error, Some(String::from("Document contains no payload!")));
+    assert_eq!(result[1].error, Some(String::from("Payload of document has no content!")));
+
+    // clean up
+    assert!(doc_api.delete_document(&TOKEN.to_string(), &first_doc.pid, &first_doc.id)?);
+    assert!(doc_api.delete_document(&TOKEN.to_string(), &second_doc.pid, &second_doc.id)?);
+
+    // tear down
+    delete_test_doc_type_from_keyring(&TOKEN.to_string(), &pid, &dt_id)?;
+
+    Ok(())
+}
+
+/// Testcase: Retried requests return the original receipt, reusing the idempotency key for other content fails
+#[test]
+fn test_retry_document_creation() -> Result<()> {
+    // configure client_api
+    let api_url = util::load_from_test_config(DOCUMENT_API_URL, TEST_CONFIG);
+    let doc_api = DocumentApiClient::new(&api_url);
+
+    // prepare test data
+    let dt_id = String::from("test_retry_document_creation_dt");
+    let pid = String::from("test_retry_document_creation_pid");
+    let doc = create_test_document(&pid, &dt_id, 0);
+    let other_doc = create_test_document(&pid, &dt_id, 1);
+    let key = "test_retry_document_creation_key";
+    // clean up doc type (in case of previous test failure)
+    delete_test_doc_type_from_keyring(&TOKEN.to_string(), &pid, &dt_id)?;
+    insert_test_doc_type_into_keyring(&TOKEN.to_string(), &pid, &dt_id)?;
+
+    // run the test
+    let receipt = doc_api.create_document_with_idempotency_key(&TOKEN.to_string(), &doc, key)?;
+    let retried = doc_api.create_document_with_idempotency_key(&TOKEN.to_string(), &doc, key)?;
+    assert_eq!(receipt.receipt.doc_id, retried.receipt.doc_id);
+    assert_eq!(receipt.receipt.tc, retried.receipt.tc);
+    assert_eq!(receipt.receipt.chain_hash, retried.receipt.chain_hash);
+
+    // a retry without idempotency key is recognized by the id of the document
+    let resent = doc_api.create_document(&TOKEN.to_string(), &doc)?;
+    assert_eq!(receipt.receipt.chain_hash, resent.receipt.chain_hash);
+
+    // the idempotency key can not be used for another document
+    assert!(doc_api.create_document_with_idempotency_key(&TOKEN.to_string(), &other_doc, key).is_err());
+
+    // only the first request stored a document
+    let report = doc_api.verify_chain(&TOKEN.to_string(), &pid)?;
+    assert_eq!(report.length, 1);
+
+    // clean up
+    assert!(doc_api.delete_document(&TOKEN.to_string(), &doc.pid, &doc.id)?);
+
+    // tear down
+    delete_test_doc_type_from_keyring(&TOKEN.to_string(), &pid, &dt_id)?;
+
+    Ok(())
+}
+
+/// Testcase: Receipt of a stored document is signed with the key published by the document api
+#[test]
+fn test_verify_signed_receipt() -> Result<()> {
+    // configure client_api
+    let api_url = util::load_from_test_config(DOCUMENT_API_URL, TEST_CONFIG);
+    let doc_api = DocumentApiClient::new(&api_url);
+
+    // prepare test data
+    let dt_id = String::from("test_verify_signed_receipt_dt");
+    let pid = String::from("test_verify_signed_receipt_pid");
+    let doc = create_test_document(&pid, &dt_id, 0);
+    // clean up doc type (in case of previous test failure)
+    delete_test_doc_type_from_keyring(&TOKEN.to_string(), &pid, &dt_id)?;
+    insert_test_doc_type_into_keyring(&TOKEN.to_string(), &pid, &dt_id)?;
+    let result = doc_api.create_document(&TOKEN.to_string(), &doc)?;
+
+    // run the test
+    let jwks = doc_api.get_jwks()?;
+    let receipt = result.verify(&jwks)?;
+    assert_eq!(receipt.doc_id, doc.id);
+    assert_eq!(verify_receipt(&result.signature, &jwks)?, result.receipt);
+
+    // clean up
+    assert!(doc_api.delete_document(&TOKEN.to_string(), &doc.pid, &doc.id)?);
+
+    // tear down
+    delete_test_doc_type_from_keyring(&TOKEN.to_string(), &pid, &dt_id)?;
+
+    Ok(())
+}
+
 /// Testcase: Standard case: retrieve document.
 #[test]
 fn test_get_document() -> Result<()>{
@@ -115,6 +247,82 @@ fn test_get_document() -> Result<()>{
     Ok(())
 }
 
+/// Testcase: Only the selected parts of documents are decrypted
+#[test]
+fn test_get_selected_parts() -> Result<()>{
+    // configure client_api
+    let api_url = util::load_from_test_config(DOCUMENT_API_URL, TEST_CONFIG);
+    let doc_api = DocumentApiClient::new(&api_url);
+
+    // prepare test data
+    let dt_id = String::from("test_get_selected_parts_type");
+    let pid = String::from("test_get_selected_parts_process");
+    let expected_doc = create_test_document(&pid, &dt_id, 0);
+    let parts = vec!(String::from(PAYLOAD_PART), String::from("connector"));
+    // clean up doc type (in case of previous test failure)
+    delete_test_doc_type_from_keyring(&TOKEN.to_string(), &pid, &dt_id)?;
+    insert_test_doc_type_into_keyring(&TOKEN.to_string(), &pid, &dt_id)?;
+
+    // create test data in db
+    doc_api.create_document(&TOKEN.to_string(), &expected_doc)?;
+
+    // run test
+    let result = doc_api.get_document_parts(&TOKEN.to_string(), &pid, &expected_doc.id, &parts)?.unwrap();
+    let query = DocumentQuery{ parts: Some(parts.join(",")), ..Default::default() };
+    let page = doc_api.get_documents_for_pid(&TOKEN.to_string(), &pid, &query)?;
+
+    // only the payload and the connector are decrypted
+    for doc in [result, page.documents[0].clone()] {
+        let names: Vec<String> = doc.parts.iter().map(|p| p.name.clone()).collect();
+        assert_eq!(names, parts);
+        assert_eq!(doc.parts[0].content, expected_doc.parts[1].content);
+    }
+
+    // clean up
+    assert!(doc_api.delete_document(&TOKEN.to_string(), &expected_doc.pid, &expected_doc.id)?);
+
+    // tear down
+    delete_test_doc_type_from_keyring(&TOKEN.to_string(), &pid, &dt_id)?;
+
+    Ok(())
+}
+
+/// Testcase: Standard case: store a document with a large payload and retrieve the payload.
+#[test]
+fn test_store_and_get_large_payload() -> Result<()>{
+    // configure client_api
+    let api_url = util::load_from_test_config(DOCUMENT_API_URL, TEST_CONFIG);
+    let doc_api = DocumentApiClient::new(&api_url);
+
+    // prepare test data: a payload of several chunks that is not valid UTF-8
+    let dt_id = String::from("test_store_and_get_large_payload_type");
+    let pid = String::from("test_store_and_get_large_payload_process");
+    let doc = create_test_document(&pid, &dt_id, 0);
+    let parts: Vec<DocumentPart> = doc.parts.into_iter().filter(|p| p.name != PAYLOAD_PART).collect();
+    let payload: Vec<u8> = (0..1000000).map(|i| (i % 256) as u8).collect();
+    // clean up doc type (in case of previous test failure)
+    delete_test_doc_type_from_keyring(&TOKEN.to_string(), &pid, &dt_id)?;
+    insert_test_doc_type_into_keyring(&TOKEN.to_string(), &pid, &dt_id)?;
+
+    // run test
+    let receipt = doc_api.create_document_with_large_payload(&TOKEN.to_string(), &pid, &dt_id, &parts, Cursor::new(payload.clone()))?;
+    let mut result = vec!();
+    doc_api.get_large_payload(&TOKEN.to_string(), &pid, &receipt.receipt.doc_id)?.read_to_end(&mut result)?;
+
+    // checks
+    assert_eq!(result, payload);
+    let stored_doc = doc_api.get_document(&TOKEN.to_string(), &pid, &receipt.receipt.doc_id)?.unwrap();
+    assert_eq!(stored_doc.parts.len(), parts.len());
+
+    // clean up
+    assert!(doc_api.delete_document(&TOKEN.to_string(), &pid, &receipt.receipt.doc_id)?);
+
+    // tear down
+    delete_test_doc_type_from_keyring(&TOKEN.to_string(), &pid, &dt_id)?;
+
+    Ok(())
+}
+
 /// Testcase: Retrieve all documents for pid, but there are no documents
 #[test]
 fn test_get_no_documents_for_pid() -> Result<()>{
@@ -135,11 +343,12 @@ fn test_get_no_documents_for_pid() -> Result<()>{
     doc_api.create_document(&TOKEN.to_string(), &expected_doc)?;
 
     // run test
-    let result = doc_api.get_documents_for_pid(&TOKEN.to_string(), &pid_without_doc)?;
+    let result = doc_api.get_documents_for_pid(&TOKEN.to_string(), &pid_without_doc, &DocumentQuery::default())?;
     println!("Result: {:?}", result);
 
     // check that there are no documents found
-    assert_eq!(result.len(), 0);
+    assert_eq!(result.documents.len(), 0);
+    assert_eq!(result.next_after_tc, None);
 
     // clean up
     assert!(doc_api.delete_document(&TOKEN.to_string(), &expected_doc.pid, &expected_doc.id)?);
@@ -174,11 +383,34 @@ fn test_get_documents_for_pid() -> Result<()>{
     doc_api.create_document(&TOKEN.to_string(), &doc3)?;
 
     // run test
-    let result = doc_api.get_documents_for_pid(&TOKEN.to_string(), &pid)?;
+    let result = doc_api.get_documents_for_pid(&TOKEN.to_string(), &pid, &DocumentQuery::default())?;
     println!("Result: {:?}", result);
 
     // check that we got three documents back
-    assert_eq!(result.len(), 3);
+    assert_eq!(result.documents.len(), 3);
+
+    // pages of two documents, the last page has no cursor
+    let mut query = DocumentQuery{ limit: Some(2), ..Default::default() };
+    let first = doc_api.get_documents_for_pid(&TOKEN.to_string(), &pid, &query)?;
+    assert_eq!(first.documents.len(), 2);
+    assert_eq!(first.next_after_tc, Some(1));
+    query.after_tc = first.next_after_tc;
+    let second = doc_api.get_documents_for_pid(&TOKEN.to_string(), &pid, &query)?;
+    assert_eq!(second.documents.len(), 1);
+    assert_eq!(second.documents[0].id, doc3.id);
+    assert_eq!(second.next_after_tc, None);
+
+    // iterating through the pages in descending order returns all documents
+    let query = DocumentQuery{ order: Some(SortOrder::Desc), sort: Some(SortField::Ts), ..Default::default() };
+    let all = doc_api.get_all_documents_for_pid(&TOKEN.to_string(), &pid, &query, 2)?;
+    assert!(all.failures.is_empty());
+    let ids: Vec<String> = all.documents.iter().map(|d| d.id.clone()).collect();
+    assert_eq!(ids, vec!(doc3.id.clone(), doc2.id.clone(), doc1.id.clone()));
+
+    // the streamed documents are the same
+    let streamed = doc_api.stream_documents_for_pid(&TOKEN.to_string(), &pid, &query)?.collect::<Result<Vec<Document>>>()?;
+    let streamed_ids: Vec<String> = streamed.iter().map(|d| d.id.clone()).collect();
+    assert_eq!(streamed_ids, ids);
 
     // tear down
     delete_test_doc_type_from_keyring(&TOKEN.to_string(), &pid, &dt_id)?;
@@ -190,6 +422,49 @@ fn test_get_documents_for_pid() -> Result<()>{
     Ok(())
 }
 
+/// Testcase: Documents of several document types in one process are all decrypted and returned in tc order
+#[test]
+fn test_get_documents_of_several_types_for_pid() -> Result<()>{
+    // configure client_api
+    let api_url = util::load_from_test_config(DOCUMENT_API_URL, TEST_CONFIG);
+    let doc_api = DocumentApiClient::new(&api_url);
+
+    // prepare test data
+    let dt_id1 = String::from("test_get_documents_of_several_types_for_pid_type_1");
+    let dt_id2 = String::from("test_get_documents_of_several_types_for_pid_type_2");
+    let pid = String::from("test_get_documents_of_several_types_for_pid_pid");
+    let doc1 = create_test_document(&pid, &dt_id1, 0);
+    let doc2 = create_test_document(&pid, &dt_id2, 1);
+    let doc3 = create_test_document(&pid, &dt_id1, 2);
+    // clean up doc types (in case of previous test failure)
+    for dt_id in [&dt_id1, &dt_id2].iter() {
+        delete_test_doc_type_from_keyring(&TOKEN.to_string(), &pid, dt_id)?;
+        insert_test_doc_type_into_keyring(&TOKEN.to_string(), &pid, dt_id)?;
+    }
+
+    // create test data in db
+    doc_api.create_document(&TOKEN.to_string(), &doc1)?;
+    doc_api.create_document(&TOKEN.to_string(), &doc2)?;
+    doc_api.create_document(&TOKEN.to_string(), &doc3)?;
+
+    // run test
+    let result = doc_api.get_documents_for_pid(&TOKEN.to_string(), &pid, &DocumentQuery::default())?;
+    let ids: Vec<String> = result.documents.iter().map(|d| d.id.clone()).collect();
+    assert_eq!(ids, vec!(doc1.id.clone(), doc2.id.clone(), doc3.id.clone()));
+    assert!(result.documents[1].same_content(&doc2));
+    assert!(result.failures.is_empty());
+
+    // tear down
+    for dt_id in [&dt_id1, &dt_id2].iter() {
+        delete_test_doc_type_from_keyring(&TOKEN.to_string(), &pid, dt_id)?;
+    }
+    assert!(doc_api.delete_document(&TOKEN.to_string(), &pid, &doc1.id)?);
+    assert!(doc_api.delete_document(&TOKEN.to_string(), &pid, &doc2.id)?);
+    assert!(doc_api.delete_document(&TOKEN.to_string(), &pid, &doc3.id)?);
+
+    Ok(())
+}
+
 /// Testcase: Ensure that IDS ids can be used if they are url_encoded
 #[test]
 fn test_create_document_url_encoded_id() -> Result<()>{
@@ -220,5 +495,81 @@ fn test_create_document_url_encoded_id() -> Result<()>{
     // tear down
     delete_test_doc_type_from_keyring(&TOKEN.to_string(), &pid, &dt_id)?;
 
+    Ok(())
+}
+
+/// Testcase: Standard case: Verify the chain of all documents for pid
+#[test]
+fn test_verify_chain() -> Result<()>{
+    // configure client_api
+    let api_url = util::load_from_test_config(DOCUMENT_API_URL, TEST_CONFIG);
+    let doc_api = DocumentApiClient::new(&api_url);
+
+    // prepare test data
+    let dt_id = String::from("test_verify_chain_type");
+    let pid = String::from("test_verify_chain_pid");
+    let doc1 = create_test_document(&pid, &dt_id, 0);
+    let doc2 = create_test_document(&pid, &dt_id, 1);
+    // clean up doc type (in case of previous test failure)
+    delete_test_doc_type_from_keyring(&TOKEN.to_string(), &pid, &dt_id)?;
+    insert_test_doc_type_into_keyring(&TOKEN.to_string(), &pid, &dt_id)?;
+
+    // create test data in db
+    doc_api.create_document(&TOKEN.to_string(), &doc1)?;
+    doc_api.create_document(&TOKEN.to_string(), &doc2)?;
+
+    // run test
+    let result = doc_api.verify_chain(&TOKEN.to_string(), &pid)?;
+    println!("Result: {:?}", result);
+
+    // check that the chain of both documents is intact
+    assert!(result.valid);
+    assert_eq!(result.length, 2);
+    assert!(result.head_hash.is_some());
+
+    // tear down
+    delete_test_doc_type_from_keyring(&TOKEN.to_string(), &pid, &dt_id)?;
+    assert!(doc_api.delete_document(&TOKEN.to_string(), &pid, &doc1.id)?);
+    assert!(doc_api.delete_document(&TOKEN.to_string(), &pid, &doc2.id)?);
+
+    Ok(())
+}
+
+/// Testcase: Retrieve document with integrity check using the chain hash of the receipt
+#[test]
+fn test_get_document_with_integrity_check() -> Result<()>{
+    // configure client_api
+    let api_url = util::load_from_test_config(DOCUMENT_API_URL, TEST_CONFIG);
+    let doc_api = DocumentApiClient::new(&api_url);
+
+    // prepare test data
+    let dt_id = String::from("test_get_document_with_integrity_check_type");
+    let pid = String::from("test_get_document_with_integrity_check_pid");
+    let doc1 = create_test_document(&pid, &dt_id, 0);
+    let doc2 = create_test_document(&pid, &dt_id, 1);
+    // clean up doc type (in case of previous test failure)
+    delete_test_doc_type_from_keyring(&TOKEN.to_string(), &pid, &dt_id)?;
+    insert_test_doc_type_into_keyring(&TOKEN.to_string(), &pid, &dt_id)?;
+
+    // create test data in db
+    doc_api.create_document(&TOKEN.to_string(), &doc1)?;
+    let receipt = doc_api.create_document(&TOKEN.to_string(), &doc2)?;
+
+    // run test: correct chain hash
+    let result = doc_api.get_document_with_integrity_check(&TOKEN.to_string(), &pid, &doc2.id, &receipt.receipt.chain_hash)?;
+    assert_eq!(result.id, doc2.id);
+
+    // run test: wrong chain hash
+    let result = doc_api.get_document_with_integrity_check(&TOKEN.to_string(), &pid, &doc2.id, &String::from("0"));
+    match result {
+        Err(Error(IntegrityViolation(id), _)) => assert_eq!(id, doc2.id),
+        _ => panic!("Expected integrity violation")
+    }
+
+    // tear down
+    delete_test_doc_type_from_keyring(&TOKEN.to_string(), &pid, &dt_id)?;
+    assert!(doc_api.delete_document(&TOKEN.to_string(), &pid, &doc1.id)?);
+    assert!(doc_api.delete_document(&TOKEN.to_string(), &pid, &doc2.id)?);
+
     Ok(())
 }
\ No newline at end of file
@@ -31,7 +31,7 @@ fn test_generate_keys() -> Result<()> {
     println!("key_ct: {}", hex::encode_upper(keys.keys_enc.as_ref().unwrap()));
 
     // check that KeyMap is meant for encryption
-    assert_eq!(keys.enc, true);
+    assert!(keys.enc);
 
     // check that there's a key_ct
     assert!(keys.keys_enc.is_some());
@@ -66,7 +66,7 @@ fn test_decrypt_keys() -> Result<()> {
     let dec_keys = key_api.decrypt_keys(&TOKEN.to_string(), &pid, &dt_id, keys.keys_enc.as_ref().unwrap())?;
 
     // check that KeyMap is meant for decryption
-    assert_eq!(dec_keys.enc, false);
+    assert!(!dec_keys.enc);
 
     // check that there's no key_ct
     assert!(dec_keys.keys_enc.is_none());
@@ -84,4 +84,28 @@ fn test_decrypt_keys() -> Result<()> {
     delete_test_doc_type_from_keyring(&TOKEN.to_string(), &pid, &dt_id)?;
 
     Ok(())
-}
\ No newline at end of file
+}
+/// Testcase: Get the document type, unknown document types are not found
+#[test]
+fn test_get_document_type() -> Result<()> {
+    // configure client_api
+    let api_url = util::load_from_test_config(KEYRING_API_URL, TEST_CONFIG);
+    let key_api = KeyringApiClient::new(&api_url);
+
+    // prepare test data
+    let dt_id = String::from("test_get_document_type_dt");
+    let pid = String::from("test_get_document_type_pid");
+    // clean up doc type (in case of previous test failure)
+    delete_test_doc_type_from_keyring(&TOKEN.to_string(), &pid, &dt_id)?;
+    insert_test_doc_type_into_keyring(&TOKEN.to_string(), &pid, &dt_id)?;
+
+    // run the test
+    let dt = key_api.get_document_type(&TOKEN.to_string(), &pid, &dt_id)?;
+    assert_eq!(dt.unwrap().id, dt_id);
+    assert!(key_api.get_document_type(&TOKEN.to_string(), &pid, "unknown")?.is_none());
+
+    // tear down
+    delete_test_doc_type_from_keyring(&TOKEN.to_string(), &pid, &dt_id)?;
+
+    Ok(())
+}
@@ -0,0 +1,50 @@
+use std::net::TcpStream;
+use std::sync::Mutex;
+use std::time::Duration;
+use rocket::{Config, State};
+use rocket::config::LogLevel;
+use rocket::http::Status;
+use rocket::serde::json::Json;
+use rocket::tokio;
+use core_lib::api::BlockchainMessage;
+
+/// In memory ledger that offers the api of the blockchain api
+#[derive(Default)]
+struct MockLedger {
+    hashes: Mutex<Vec<BlockchainMessage>>,
+}
+
+#[rocket::post("/hash", format = "json", data = "<message>")]
+fn store_hash(ledger: &State<MockLedger>, message: Json<BlockchainMessage>) -> Status {
+    ledger.hashes.lock().unwrap().push(message.into_inner());
+    Status::Created
+}
+
+#[rocket::get("/hash/<id>")]
+fn get_hash_list(ledger: &State<MockLedger>, id: String) -> Json<Vec<BlockchainMessage>> {
+    let hashes = ledger.hashes.lock().unwrap();
+    Json(hashes.iter().filter(|m| m.id == id).cloned().collect())
+}
+
+/// Starts a mock ledger on the given port that runs until the runtime of the test is dropped.
+/// Returns the url of the ledger.
+pub async fn launch_mock_ledger(port: u16) -> String {
+    let config = Config {
+        port,
+        log_level: LogLevel::Off,
+        ..Config::debug_default()
+    };
+    let ledger = rocket::custom(config)
+        .manage(MockLedger::default())
+        .mount("/", rocket::routes![store_hash, get_hash_list]);
+    tokio::spawn(ledger.launch());
+
+    // wait until the ledger accepts connections
+    for _ in 0..50 {
+        if TcpStream::connect(("127.0.0.1", port)).is_ok() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    format!("http://127.0.0.1:{}", port)
+}
@@ -0,0 +1,61 @@
+use std::fs;
+use std::net::TcpStream;
+use std::path::Path;
+use std::process::Command;
+use std::time::Duration;
+use rocket::Config;
+use rocket::config::LogLevel;
+use rocket::http::{ContentType, Status};
+use rocket::tokio;
+use core_lib::model::new_uuid;
+
+pub const TSA_CERTIFICATE: &str = "tests/tsa/tsa_cert.der";
+const TSA_CERTIFICATE_PEM: &str = "tests/tsa/tsa_cert.pem";
+const TSA_KEY: &str = "tests/tsa/tsa_key.pem";
+const TSA_CONFIG: &str = "tests/tsa/tsa.cnf";
+
+/// Answers time-stamp requests using the `openssl ts` command and the test TSA
+#[rocket::post("/", format = "application/timestamp-query", data = "<request>")]
+fn timestamp(request: Vec<u8>) -> Result<(ContentType, Vec<u8>), Status> {
+    let dir = std::env::temp_dir().join(new_uuid());
+    fs::create_dir(&dir).map_err(|_| Status::InternalServerError)?;
+    fs::write(dir.join("tsa_serial"), "01").map_err(|_| Status::InternalServerError)?;
+    fs::write(dir.join("request.tsq"), request).map_err(|_| Status::InternalServerError)?;
+
+    let absolute = |file: &str| fs::canonicalize(Path::new(file)).unwrap();
+    let result = Command::new("openssl")
+        .current_dir(&dir)
+        .args(["ts", "-reply", "-queryfile", "request.tsq", "-out", "response.tsr"])
+        .arg("-signer").arg(absolute(TSA_CERTIFICATE_PEM))
+        .arg("-inkey").arg(absolute(TSA_KEY))
+        .arg("-config").arg(absolute(TSA_CONFIG))
+        .output();
+    let response = fs::read(dir.join("response.tsr"));
+    let _ = fs::remove_dir_all(&dir);
+    match (result, response) {
+        (Ok(output), Ok(response)) if output.status.success() => Ok((ContentType::new("application", "timestamp-reply"), response)),
+        _ => Err(Status::InternalServerError)
+    }
+}
+
+/// Starts a local TSA on the given port that runs until the runtime of the test is dropped.
+/// Returns the url of the TSA.
+pub async fn launch_mock_tsa(port: u16) -> String {
+    let config = Config {
+        port,
+        log_level: LogLevel::Off,
+        ..Config::debug_default()
+    };
+    let tsa = rocket::custom(config)
+        .mount("/", rocket::routes![timestamp]);
+    tokio::spawn(tsa.launch());
+
+    // wait until the TSA accepts connections
+    for _ in 0..50 {
+        if TcpStream::connect(("127.0.0.1", port)).is_ok() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    format!("http://127.0.0.1:{}", port)
+}
@@ -0,0 +1,28 @@
+use core_lib::api::ApiClient;
+use core_lib::errors::*;
+use core_lib::api::client::tsa_api::TsaApiClient;
+use core_lib::timestamp::{verify_timestamp_token, TimestampToken};
+use crate::mock_tsa::{launch_mock_tsa, TSA_CERTIFICATE};
+
+/// Testcase: Standard case: obtain a time-stamp token over a chain hash
+#[rocket::async_test]
+async fn test_get_timestamp_token() -> Result<()>{
+    // configure client_api
+    let api_url = launch_mock_tsa(8012).await;
+    let tsa_api = TsaApiClient::new(&api_url);
+    let tsa_certificate = std::fs::read(TSA_CERTIFICATE)?;
+
+    // run the test
+    let (token, nonce) = tsa_api.get_timestamp_token("test_get_timestamp_token_hash").await?;
+
+    // check
+    let info = verify_timestamp_token(&token, "test_get_timestamp_token_hash", &tsa_certificate)?;
+    assert_eq!(info.nonce, Some(nonce));
+    let timestamp_token = TimestampToken::new(String::from("test_get_timestamp_token_hash"), &token);
+    assert_eq!(timestamp_token.verify(&tsa_certificate)?, info);
+
+    // the token can not be used for another hash
+    assert!(verify_timestamp_token(&token, "test_get_timestamp_token_other_hash", &tsa_certificate).is_err());
+
+    Ok(())
+}
@@ -0,0 +1,110 @@
+use biscuit::Empty;
+use std::time::Duration;
+use core_lib::api::{ApiClient, ApiResponse};
+use core_lib::api::auth::ApiKey;
+use core_lib::api::claims::IdsClaims;
+use core_lib::api::client::{ApiClientConfigurator, ApiClientEnum};
+use core_lib::api::client::blockchain_api::BlockchainApiClient;
+use core_lib::constants::{ANCHOR_INTERVAL, BLOCKCHAIN_API_URL, ROCKET_DOC_API};
+use core_lib::errors::*;
+use core_lib::model::anchor::compare_anchors;
+use rocket::fairing::AdHoc;
+use rocket::serde::json::json;
+use rocket::{State, tokio};
+use crate::db::DataStore;
+use crate::model::anchor::AnchoredHead;
+
+/// default number of seconds between two anchoring runs
+const DEFAULT_ANCHOR_INTERVAL: u64 = 3600;
+
+/// Anchors the latest chain hash of each process periodically in the ledger of the blockchain api
+/// and mounts the endpoint to compare the anchored hashes with the chain.
+/// Anchoring is disabled if no blockchain api is configured.
+pub fn add_anchoring() -> AdHoc {
+    AdHoc::try_on_ignite("Configuring Blockchain Anchoring", |rocket| async {
+        let api_url: String = rocket.figment().extract_inner(&BlockchainApiClient::get_conf_param()).unwrap_or(String::new());
+        if api_url.is_empty(){
+            info!("No {} configured. Blockchain anchoring disabled.", BLOCKCHAIN_API_URL);
+            return Ok(rocket)
+        }
+        let interval: u64 = rocket.figment().extract_inner(ANCHOR_INTERVAL).unwrap_or(DEFAULT_ANCHOR_INTERVAL);
+        if interval == 0 {
+            error!("{} has to be at least one second", ANCHOR_INTERVAL);
+            return Err(rocket)
+        }
+        debug!("Anchoring chain hashes every {} seconds", interval);
+
+        Ok(rocket
+            .mount(ROCKET_DOC_API, routes![compare_anchored_hashes])
+            .attach(ApiClientConfigurator::new(ApiClientEnum::Blockchain))
+            .attach(AdHoc::on_liftoff("Starting Blockchain Anchoring", move |rocket| Box::pin(async move {
+                match (rocket.state::<DataStore>(), rocket.state::<BlockchainApiClient>()){
+                    (Some(db), Some(bc_api)) => {
+                        tokio::spawn(anchor_chains(db.clone(), bc_api.clone(), interval));
+                    },
+                    _ => error!("Database or blockchain api not available. Blockchain anchoring disabled.")
+                }
+            }))))
+    })
+}
+
+/// Compare the chain hashes anchored in the ledger with the current chain of the process with pid
+#[get("/<pid>/anchors", format = "json")]
+async fn compare_anchored_hashes(api_key: ApiKey<IdsClaims, Empty>, db: &State<DataStore>, bc_api: &State<BlockchainApiClient>, pid: String) -> ApiResponse {
+    debug!("user '{:?}' with claims {:?}", api_key.sub(), api_key.claims());
+    debug!("trying to compare anchored hashes for pid '{}'", &pid);
+    let anchors = match bc_api.get_hash_list(&pid).await{
+        Ok(anchors) => anchors,
+        Err(e) => {
+            error!("Error while retrieving anchored hashes: {:?}", e);
+            return ApiResponse::InternalError(format!("Error while retrieving anchored hashes for {}", &pid))
+        }
+    };
+    match db.get_documents_for_pid(&pid).await{
+        Ok((docs, unreadable)) => {
+            let report = compare_anchors(&pid, &anchors, &docs).with_unreadable_documents(unreadable);
+            if !report.valid{
+                warn!("Anchored hashes for pid '{}' do not match the chain!", &pid);
+            }
+            ApiResponse::SuccessOk(json!(report))
+        },
+        Err(e) => {
+            error!("Error while retrieving documents: {:?}", e);
+            ApiResponse::InternalError(format!("Error while retrieving documents for {}", &pid))
+        }
+    }
+}
+
+async fn anchor_chains(db: DataStore, bc_api: BlockchainApiClient, interval: u64) {
+    let mut timer = tokio::time::interval(Duration::from_secs(interval));
+    loop {
+        timer.tick().await;
+        if let Err(e) = anchor_latest_hashes(&db, &bc_api).await{
+            error!("Error while anchoring chain hashes: {:?}", e);
+        }
+    }
+}
+
+/// anchors the chain hash of the latest document of each process that changed since it was last anchored
+async fn anchor_latest_hashes(db: &DataStore, bc_api: &BlockchainApiClient) -> Result<()> {
+    debug!("Anchoring chain hashes...");
+    for pid in db.get_pids().await?{
+        let latest = match db.get_latest_document_for_pid(&pid).await?{
+            Some(latest) => latest,
+            None => continue
+        };
+        let head = AnchoredHead::new(pid.clone(), latest.tc + 1);
+        if db.get_anchored_tree_size(&pid).await? == Some(head.tree_size){
+            continue
+        }
+        match bc_api.store_hash(&pid, &latest.tc.to_string(), &latest.hash()).await{
+            Ok(true) => {
+                debug!("... anchored pid {} at tc {}", &pid, latest.tc);
+                db.set_anchored_tree_size(&head).await?;
+            },
+            Ok(false) => warn!("Blockchain api did not anchor pid {} at tc {}", &pid, latest.tc),
+            Err(e) => error!("Error while anchoring pid {}: {:?}", &pid, e)
+        }
+    }
+    Ok(())
+}
@@ -1,19 +1,57 @@
-use mongodb::{Client, Database, IndexModel};
-use mongodb::bson::doc;
-use mongodb::options::{CreateCollectionOptions, IndexOptions, WriteConcern};
+use mongodb::{Client, Cursor, Database, IndexModel};
+use mongodb::bson::{doc, from_document, oid::ObjectId, to_bson, Bson};
+use mongodb::error::{ErrorKind, WriteFailure};
+use mongodb::gridfs::{GridFsBucket, GridFsDownloadStream, GridFsUploadStream};
+use mongodb::options::{CreateCollectionOptions, FindOneOptions, FindOptions, GridFsBucketOptions, IndexOptions, UpdateOptions, WriteConcern};
 use rocket::{Build, Rocket};
 use rocket::fairing::{self, Fairing, Info, Kind};
 use rocket::futures::TryStreamExt;
 use rocket::serde::json::json;
 
-use core_lib::constants::{DATABASE_URL, DOCUMENT_DB, CLEAR_DB, MONGO_COLL_DOCUMENTS, MONGO_DT_ID, MONGO_ID, MONGO_PID, DOCUMENT_DB_CLIENT, MONGO_TC};
+use core_lib::constants::{DATABASE_URL, DOCUMENT_DB, CLEAR_DB, MONGO_BUCKET_PAYLOADS, MONGO_COLL_ALERTS, MONGO_COLL_ANCHORS, MONGO_COLL_DOCUMENTS, MONGO_COLL_MERKLE_LEAVES, MONGO_COLL_TRANSACTIONS, MONGO_DESCRIPTION, MONGO_DOC_ID, MONGO_DT_ID, MONGO_FIRST_SEEN, MONGO_HASH, MONGO_ID, MONGO_IDEMPOTENCY_KEY, MONGO_KIND, MONGO_LAST_SEEN, MONGO_LEAF, MONGO_PID, DOCUMENT_DB_CLIENT, MONGO_TC, MONGO_TREE_SIZE, MONGO_TS};
+use core_lib::api::{DocumentQuery, SortField, SortOrder};
 use core_lib::db::{DataStoreApi, init_database_client};
 use core_lib::errors::*;
-use core_lib::model::document::{Document, EncryptedDocument};
+use core_lib::model::alert::Alert;
+use core_lib::model::chain::UnreadableDocument;
+use core_lib::model::document::EncryptedDocument;
+use crate::model::anchor::AnchoredHead;
+use crate::model::merkle::MerkleLeaf;
+use crate::model::transaction::TransactionCounter;
 
 
 #[cfg(test)] mod tests;
 
+/// name of the unique index on tc that chained the documents of all processes together
+const LEGACY_TC_INDEX: &str = "tc_1";
+
+/// name of the unique index on the idempotency keys of the documents of a process
+const IDEMPOTENCY_INDEX: &str = "pid_1_idempotency_key_1";
+
+/// Checks if storing a document failed because another document of its process already has its idempotency key,
+/// e.g. because a concurrent retry of the same request was stored first
+pub fn is_duplicate_idempotency_key(e: &Error) -> bool {
+    let is_duplicate = |code: i32, message: &String| code == 11000 && message.contains(IDEMPOTENCY_INDEX);
+    match e.kind() {
+        core_lib::errors::ErrorKind::Mongodb(e) => match *e.kind {
+            ErrorKind::BulkWrite(ref failure) => failure.write_errors.iter().flatten().any(|we| is_duplicate(we.code, &we.message)),
+            ErrorKind::Write(WriteFailure::WriteError(ref we)) => is_duplicate(we.code, &we.message),
+            _ => false
+        },
+        _ => false
+    }
+}
+
+/// index over the given keys that is unique if `unique` is set
+fn index(keys: mongodb::bson::Document, unique: bool) -> IndexModel {
+    let mut index_options = IndexOptions::default();
+    index_options.unique = Some(unique);
+    let mut index_model = IndexModel::default();
+    index_model.keys = keys;
+    index_model.options = Some(index_options);
+    index_model
+}
+
 #[derive(Clone, Debug)]
 pub struct DatastoreConfigurator;
 
@@ -38,7 +76,7 @@ impl Fairing for DatastoreConfigurator {
         };
         debug!("Using database url: '{:#?}'", &db_url);
 
-        match init_database_client::<DataStore>(&db_url.as_str(), Some(DOCUMENT_DB_CLIENT.to_string())).await{
+        match init_database_client::<DataStore>(db_url.as_str(), Some(DOCUMENT_DB_CLIENT.to_string())).await{
             Ok(datastore) => {
                 debug!("Check if database is empty...");
                 match datastore.client.database(DOCUMENT_DB)
@@ -46,7 +84,7 @@ impl Fairing for DatastoreConfigurator {
                     .await{
                     Ok(colls) => {
                         debug!("... found collections: {:#?}", &colls);
-                        if colls.len() > 0 && clear_db{
+                        if !colls.is_empty() && clear_db{
                             debug!("Database not empty and clear_db == true. Dropping database...");
                             match datastore.client.database(DOCUMENT_DB).drop(None).await{
                                 Ok(_) => {
@@ -58,7 +96,7 @@ impl Fairing for DatastoreConfigurator {
                                 }
                             };
                         }
-                        if colls.len() == 0 || clear_db{
+                        if colls.is_empty() || clear_db{
                             debug!("Database empty. Need to initialize...");
                             let mut write_concern = WriteConcern::default();
                             write_concern.journal = Some(true);
@@ -74,22 +112,12 @@ impl Fairing for DatastoreConfigurator {
                                     return Err(rocket);
                                 }
                             };
-                            let mut index_options = IndexOptions::default();
-                            index_options.unique = Some(true);
-                            let mut index_model = IndexModel::default();
-                            index_model.keys =  doc!{MONGO_TC: 1};
-                            index_model.options = Some(index_options);
-
-                            debug!("Create unique index for {} ...", MONGO_COLL_DOCUMENTS);
-                            match datastore.client.database(DOCUMENT_DB).collection::<Document>(MONGO_COLL_DOCUMENTS).create_index(index_model, None).await{
-                                Ok(result) => {
-                                    debug!("... index {} created", result.index_name);
-                                }
-                                Err(_) => {
-                                    debug!("... failed.");
-                                    return Err(rocket);
-                                }
-                            }
+                        }
+                        // the indexes are brought up to date on every start, also for existing databases
+                        debug!("Migrating database...");
+                        if let Err(e) = datastore.migrate().await{
+                            error!("Error while migrating database: {:?}", e);
+                            return Err(rocket);
                         }
                         debug!("... database initialized.");
                         Ok(rocket.manage(datastore))
@@ -121,7 +149,77 @@ impl DataStoreApi for DataStore {
 
 
 impl DataStore {
+    /// Creates the indexes of all collections and drops indexes that are no longer used.
+    /// Runs on every start, so every step has to be idempotent.
+    pub async fn migrate(&self) -> Result<()> {
+        let documents = self.database.collection::<EncryptedDocument>(MONGO_COLL_DOCUMENTS);
+
+        // retries of a request must not create a second document
+        let mut idempotency_index = index(doc!{MONGO_PID: 1, MONGO_IDEMPOTENCY_KEY: 1}, true);
+        if let Some(options) = idempotency_index.options.as_mut() {
+            options.name = Some(String::from(IDEMPOTENCY_INDEX));
+            options.partial_filter_expression = Some(doc!{MONGO_IDEMPOTENCY_KEY: {"$exists": true}});
+        }
+        debug!("Create indexes for {} ...", MONGO_COLL_DOCUMENTS);
+        let result = documents.create_indexes(vec!(
+            index(doc!{MONGO_PID: 1, MONGO_TC: 1}, true),
+            // indexes for the queries of the documents of a process sorted by ts or of a single document type
+            index(doc!{MONGO_PID: 1, MONGO_TS: 1, MONGO_TC: 1}, false),
+            index(doc!{MONGO_PID: 1, MONGO_DT_ID: 1, MONGO_TC: 1}, false),
+            idempotency_index
+        ), None).await?;
+        debug!("... indexes {:?} created", result.index_names);
+
+        // documents used to be chained across all processes
+        if documents.list_index_names().await?.iter().any(|name| name == LEGACY_TC_INDEX) {
+            info!("Dropping legacy index {} of {} ...", LEGACY_TC_INDEX, MONGO_COLL_DOCUMENTS);
+            documents.drop_index(LEGACY_TC_INDEX, None).await?;
+        }
+
+        debug!("Create unique index for {} ...", MONGO_COLL_TRANSACTIONS);
+        let transactions = self.database.collection::<TransactionCounter>(MONGO_COLL_TRANSACTIONS);
+        transactions.create_index(index(doc!{MONGO_PID: 1}, true), None).await?;
+
+        // earlier versions advanced the counter before the documents were stored,
+        // so a failed store left the counter ahead of the stored documents of its process
+        let mut counters = transactions.find(None, None).await?;
+        while let Some(counter) = counters.try_next().await? {
+            let stored = self.derive_transaction_counter(&counter.pid).await?;
+            if counter.tc > stored.tc {
+                warn!("Resetting transaction counter of pid {} from tc {} to tc {}", &counter.pid, counter.tc, stored.tc);
+                transactions.replace_one(doc!{MONGO_PID: counter.pid.clone(), MONGO_TC: counter.tc}, stored, None).await?;
+            }
+        }
+
+        // the leaves of the merkle trees used to be stored in their own collection
+        let merkle_leaves = self.database.collection::<MerkleLeaf>(MONGO_COLL_MERKLE_LEAVES);
+        let mut legacy = documents.clone_with_type::<mongodb::bson::Document>()
+            .find(Some(doc!{MONGO_LEAF: {"$exists": false}}), None).await?;
+        while let Some(legacy_doc) = legacy.try_next().await? {
+            let doc: EncryptedDocument = match from_document(legacy_doc){
+                Ok(doc) => doc,
+                Err(e) => {
+                    warn!("Could not add merkle leaf to document: {:?}", e);
+                    continue
+                }
+            };
+            let filter = doc!{MONGO_PID: doc.pid.clone(), MONGO_TC: doc.tc, MONGO_ID: doc.id.clone()};
+            let leaf = match merkle_leaves.find_one(Some(filter.clone()), None).await? {
+                Some(leaf) => leaf.hash,
+                None => doc.hash()
+            };
+            documents.update_one(filter, doc!{"$set": {MONGO_LEAF: leaf}}, None).await?;
+        }
+        merkle_leaves.drop(None).await?;
+
+        debug!("Create unique index for {} ...", MONGO_COLL_ANCHORS);
+        self.database.collection::<AnchoredHead>(MONGO_COLL_ANCHORS)
+            .create_index(index(doc!{MONGO_PID: 1}, true), None).await?;
+        Ok(())
+    }
+
     // DOCUMENT
+    #[cfg(test)]
     pub async fn add_document(&self, doc: EncryptedDocument) -> Result<bool> {
         debug!("add_document({:#?})", json!(doc));
         let coll = self.database.collection::<EncryptedDocument>(MONGO_COLL_DOCUMENTS);
@@ -137,6 +235,56 @@ impl DataStore {
         }
     }
 
+    /// stores the documents in the given order and stops at the first document that can not be stored.
+    /// Returns the number of stored documents and the error that prevented storing the others, if any.
+    pub async fn add_documents(&self, docs: Vec<EncryptedDocument>) -> (usize, Option<Error>) {
+        debug!("add_documents() for {} documents", docs.len());
+        let coll = self.database.collection::<EncryptedDocument>(MONGO_COLL_DOCUMENTS);
+        match coll.insert_many(docs, None).await {
+            Ok(r) => {
+                debug!("added {} new documents", r.inserted_ids.len());
+                (r.inserted_ids.len(), None)
+            },
+            Err(e) => {
+                error!("failed to store documents: {:#?}", &e);
+                // the insert is ordered, so the documents before the first failing one are stored
+                let stored = match *e.kind {
+                    ErrorKind::BulkWrite(ref failure) => failure.write_errors.as_ref()
+                        .and_then(|errors| errors.iter().map(|we| we.index).min())
+                        .unwrap_or(0),
+                    _ => 0
+                };
+                (stored, Some(Error::from(e)))
+            }
+        }
+    }
+
+    // PAYLOAD
+    fn payload_bucket(&self) -> GridFsBucket {
+        let mut options = GridFsBucketOptions::default();
+        options.bucket_name = Some(String::from(MONGO_BUCKET_PAYLOADS));
+        self.database.gridfs_bucket(options)
+    }
+
+    /// opens a stream to store the encrypted chunks of the payload of the document with id
+    pub fn open_payload_upload(&self, id: &String) -> GridFsUploadStream {
+        debug!("Opening upload of payload for document '{}'", id);
+        self.payload_bucket().open_upload_stream(id, None)
+    }
+
+    /// opens a stream to read the encrypted chunks of a payload
+    pub async fn open_payload_download(&self, file_id: &String) -> Result<GridFsDownloadStream> {
+        debug!("Opening download of payload '{}'", file_id);
+        let file_id = ObjectId::parse_str(file_id).chain_err(|| format!("Invalid payload id '{}'", file_id))?;
+        Ok(self.payload_bucket().open_download_stream(Bson::ObjectId(file_id)).await?)
+    }
+
+    pub async fn delete_payload(&self, file_id: &String) -> Result<()> {
+        debug!("Trying to delete payload '{}'", file_id);
+        let file_id = ObjectId::parse_str(file_id).chain_err(|| format!("Invalid payload id '{}'", file_id))?;
+        Ok(self.payload_bucket().delete(Bson::ObjectId(file_id)).await?)
+    }
+
     /// deletes model from db
     pub async fn delete_document(&self, id: &String) -> Result<bool> {
         debug!("Trying to delete entry with id '{}'...", id);
@@ -186,26 +334,120 @@ impl DataStore {
         }
     }
 
-    /// gets documents for a single process from the db
-    pub async fn get_documents_for_pid(&self, pid: &String) -> Result<Vec<EncryptedDocument>> {
-        debug!("Trying to get all documents for pid {}...", pid);
+    /// gets the document of the process that was created by the request with the idempotency key
+    pub async fn get_document_by_idempotency_key(&self, pid: &String, key: &String) -> Result<Option<EncryptedDocument>> {
+        debug!("Trying to get doc with idempotency key {} for pid {}...", key, pid);
         let coll = self.database.collection::<EncryptedDocument>(MONGO_COLL_DOCUMENTS);
-        let result = coll.find(Some(doc! { MONGO_PID: pid.clone() }), None).await?
-            .try_collect().await.unwrap_or_else(|_| vec![]);
+        match coll.find_one(Some(doc! { MONGO_PID: pid.clone(), MONGO_IDEMPOTENCY_KEY: key.clone()}), None).await{
+            Ok(doc) => Ok(doc),
+            Err(e) => {
+                error!("Error while getting document with idempotency key {}!", key);
+                Err(Error::from(e))
+            }
+        }
+    }
+
+    /// gets documents for a single process from the db, sorted by tc,
+    /// and the documents of the process that could not be read, e.g. because they were modified
+    pub async fn get_documents_for_pid(&self, pid: &String) -> Result<(Vec<EncryptedDocument>, Vec<UnreadableDocument>)> {
+        debug!("Trying to get all documents for pid {}...", pid);
+        let coll = self.database.collection::<mongodb::bson::Document>(MONGO_COLL_DOCUMENTS);
+        let find_options = FindOptions::builder().sort(doc! { MONGO_TC: 1 }).build();
+        let mut cursor = coll.find(Some(doc! { MONGO_PID: pid.clone() }), find_options).await?;
+        let mut docs = vec!();
+        let mut unreadable = vec!();
+        while let Some(raw) = cursor.try_next().await? {
+            let id = raw.get_str(MONGO_ID).ok().map(String::from);
+            let tc = raw.get_i64(MONGO_TC).ok();
+            match from_document::<EncryptedDocument>(raw){
+                Ok(doc) => docs.push(doc),
+                Err(e) => {
+                    warn!("Could not read document {:?} with tc {:?} of pid {}: {}", &id, tc, pid, e);
+                    unreadable.push(UnreadableDocument::new(id, tc, e.to_string()));
+                }
+            }
+        }
+        Ok((docs, unreadable))
+    }
+
+    /// gets the documents of a single process that match the query, sorted as requested.
+    /// At most `limit` documents are returned, starting after the document with tc `after_tc`.
+    pub async fn get_documents_page(&self, pid: &String, query: &DocumentQuery, limit: Option<i64>) -> Result<Vec<EncryptedDocument>> {
+        let result = self.find_documents(pid, query, limit).await?
+            .try_collect().await?;
         Ok(result)
     }
 
-    /// gets documents for a single process from the db
-    pub async fn get_document_with_previous_tc(&self, tc: i64) -> Result<Option<EncryptedDocument>> {
+    /// opens a cursor over the documents of a single process that match the query, see `get_documents_page`.
+    /// The documents are only loaded from the db as the cursor is advanced.
+    pub async fn find_documents(&self, pid: &String, query: &DocumentQuery, limit: Option<i64>) -> Result<Cursor<EncryptedDocument>> {
+        debug!("Trying to get documents for pid {} with query {:?}...", pid, query);
+        let coll = self.database.collection::<EncryptedDocument>(MONGO_COLL_DOCUMENTS);
+        let (direction, after) = match query.sort_order() {
+            SortOrder::Asc => (1, "$gt"),
+            SortOrder::Desc => (-1, "$lt")
+        };
+
+        let mut filter = doc! { MONGO_PID: pid.clone() };
+        if let Some(dt_id) = &query.doc_type {
+            filter.insert(MONGO_DT_ID, dt_id.clone());
+        }
+        let mut ts_range = doc! {};
+        if let Some(from) = query.from {
+            ts_range.insert("$gte", from);
+        }
+        if let Some(to) = query.to {
+            ts_range.insert("$lt", to);
+        }
+        if !ts_range.is_empty() {
+            filter.insert(MONGO_TS, ts_range);
+        }
+        let sort = match query.sort_field() {
+            SortField::Tc => {
+                if let Some(after_tc) = query.after_tc {
+                    filter.insert(MONGO_TC, doc! { after: after_tc });
+                }
+                doc! { MONGO_TC: direction }
+            },
+            SortField::Ts => {
+                if let Some(after_tc) = query.after_tc {
+                    // the cursor is the position of the document with tc `after_tc` in the order of ts and tc
+                    let cursor = match coll.find_one(Some(doc! { MONGO_PID: pid.clone(), MONGO_TC: after_tc }), None).await? {
+                        Some(cursor) => cursor,
+                        None => return Err(Error::from(format!("Document with tc {} of pid {} not found", after_tc, pid)))
+                    };
+                    filter.insert("$or", vec!(
+                        doc! { MONGO_TS: { after: cursor.ts } },
+                        doc! { MONGO_TS: cursor.ts, MONGO_TC: { after: after_tc } }
+                    ));
+                }
+                doc! { MONGO_TS: direction, MONGO_TC: direction }
+            }
+        };
+
+        let find_options = FindOptions::builder().sort(sort).limit(limit).build();
+        Ok(coll.find(Some(filter), find_options).await?)
+    }
+
+    /// gets the pids of all processes that have documents
+    pub async fn get_pids(&self) -> Result<Vec<String>> {
+        debug!("Trying to get all pids...");
+        let coll = self.database.collection::<EncryptedDocument>(MONGO_COLL_DOCUMENTS);
+        let pids = coll.distinct(MONGO_PID, None, None).await?;
+        Ok(pids.iter().filter_map(|pid| pid.as_str().map(String::from)).collect())
+    }
+
+    /// gets the document of the process with pid that precedes tc in the process' chain
+    pub async fn get_document_with_previous_tc(&self, pid: &String, tc: i64) -> Result<Option<EncryptedDocument>> {
         let previous_tc = tc - 1;
-        debug!("Trying to get document for tc {} ...", previous_tc);
+        debug!("Trying to get document for pid {} and tc {} ...", pid, previous_tc);
         if previous_tc < 0 {
             info!("... not entry exists.");
             Ok(None)
         }
         else{
             let coll = self.database.collection::<EncryptedDocument>(MONGO_COLL_DOCUMENTS);
-            match coll.find_one(Some(doc! {MONGO_TC: previous_tc}), None).await{
+            match coll.find_one(Some(doc! {MONGO_PID: pid.clone(), MONGO_TC: previous_tc}), None).await{
                 Ok(doc) => {
                     debug!("... found it.");
                     Ok(doc)
@@ -218,21 +460,180 @@ impl DataStore {
         }
     }
 
-    /// gets documents of a specific document type for a single process from the db
-    pub async fn get_documents_of_dt_for_pid(&self, dt_id: &String, pid: &String) -> Result<Vec<EncryptedDocument>> {
-        debug!("Trying to get all documents for pid {} of dt {}...", pid, dt_id);
+    /// gets the document with the highest tc of a single process from the db
+    pub async fn get_latest_document_for_pid(&self, pid: &String) -> Result<Option<EncryptedDocument>> {
+        debug!("Trying to get latest document for pid {} ...", pid);
         let coll = self.database.collection::<EncryptedDocument>(MONGO_COLL_DOCUMENTS);
-        let result = coll.find(Some(doc! { MONGO_PID: pid.clone(), MONGO_DT_ID: dt_id.clone() }), None).await?
-            .try_collect().await.unwrap_or_else(|_| vec![]);
-        Ok(result)
+        let find_options = FindOneOptions::builder().sort(doc! { MONGO_TC: -1 }).build();
+        match coll.find_one(Some(doc! { MONGO_PID: pid.clone() }), find_options).await{
+            Ok(doc) => {
+                debug!("... done.");
+                Ok(doc)
+            },
+            Err(e) => {
+                error!("Error while getting latest document!");
+                Err(Error::from(e))
+            }
+        }
+    }
+
+    // MERKLE TREE
+    /// gets the leaves of the Merkle tree of a single process from its documents, sorted by tc.
+    /// Each document carries its leaf, so a document and its leaf are always stored together.
+    /// Fails if a tc has no document or a document has no leaf, since the tree would be incomplete.
+    pub async fn get_merkle_leaves(&self, pid: &String) -> Result<Vec<MerkleLeaf>> {
+        debug!("Trying to get merkle leaves for pid {}...", pid);
+        let coll = self.database.collection::<mongodb::bson::Document>(MONGO_COLL_DOCUMENTS);
+        let find_options = FindOptions::builder()
+            .sort(doc! { MONGO_TC: 1 })
+            .projection(doc! { MONGO_ID: 1, MONGO_TC: 1, MONGO_LEAF: 1 })
+            .build();
+        let mut cursor = coll.find(Some(doc! { MONGO_PID: pid.clone() }), find_options).await?;
+        let mut leaves = vec!();
+        while let Some(leaf) = cursor.try_next().await? {
+            let tc = leaf.get_i64(MONGO_TC).chain_err(|| format!("Invalid tc in merkle tree of pid {}", pid))?;
+            if tc != leaves.len() as i64 {
+                error!("Merkle tree of pid {} has no leaf for tc {}", pid, leaves.len());
+                return Err(Error::from(format!("Merkle tree of pid {} has no leaf for tc {}", pid, leaves.len())))
+            }
+            let id = leaf.get_str(MONGO_ID).chain_err(|| format!("Invalid id of tc {} in merkle tree of pid {}", tc, pid))?;
+            let hash = leaf.get_str(MONGO_LEAF).chain_err(|| format!("Document {} of pid {} has no merkle leaf", id, pid))?;
+            leaves.push(MerkleLeaf::new(pid.clone(), tc, id.to_string(), hash.to_string()));
+        }
+        Ok(leaves)
+    }
+
+    // TRANSACTION COUNTER
+    /// gets the head of the chain of a single process.
+    /// If the process has no counter yet, it is derived from the latest document of the process.
+    pub async fn get_transaction_counter(&self, pid: &String) -> Result<TransactionCounter> {
+        debug!("Trying to get transaction counter for pid {} ...", pid);
+        let coll = self.database.collection::<TransactionCounter>(MONGO_COLL_TRANSACTIONS);
+        match coll.find_one(Some(doc! { MONGO_PID: pid.clone() }), None).await?{
+            Some(counter) => {
+                debug!("... found it.");
+                Ok(counter)
+            },
+            None => {
+                debug!("... not found. Looking for existing documents ...");
+                self.derive_transaction_counter(pid).await
+            }
+        }
     }
 
-    /// gets all documents from the db
-    pub async fn get_all_documents(&self) -> Result<Vec<EncryptedDocument>> {
-        debug!("Trying to get all documents...");
+    /// moves the transaction counter of the process forward to the tc and head hash of `counter`.
+    /// Returns false if the counter is already at or beyond that tc.
+    pub async fn move_transaction_counter(&self, counter: &TransactionCounter) -> Result<bool> {
+        debug!("Trying to move transaction counter for pid {} to tc {} ...", &counter.pid, counter.tc);
+        let coll = self.database.collection::<TransactionCounter>(MONGO_COLL_TRANSACTIONS);
+        let update_options = UpdateOptions::builder().upsert(true).build();
+        let filter = doc! { MONGO_PID: counter.pid.clone(), MONGO_TC: { "$lt": counter.tc } };
+        let update = doc! { "$set": { MONGO_TC: counter.tc, MONGO_HASH: counter.hash.clone() } };
+        match coll.update_one(filter, update, update_options).await{
+            Ok(r) => {
+                let moved = r.matched_count == 1 || r.upserted_id.is_some();
+                debug!("... moved: {}", moved);
+                Ok(moved)
+            },
+            Err(e) => {
+                // the upsert collides with the unique pid index if the counter is already at or beyond the tc
+                match *e.kind {
+                    ErrorKind::Write(WriteFailure::WriteError(ref we)) if we.code == 11000 => {
+                        debug!("... counter is already at or beyond tc {}.", counter.tc);
+                        Ok(false)
+                    },
+                    _ => {
+                        error!("Error while moving transaction counter!");
+                        Err(Error::from(e))
+                    }
+                }
+            }
+        }
+    }
+
+    /// moves the transaction counter of the process to the latest stored document,
+    /// e.g. if the counter lags behind because it could not be moved after a document was stored
+    pub async fn sync_transaction_counter(&self, pid: &String) -> Result<TransactionCounter> {
+        let counter = self.derive_transaction_counter(pid).await?;
+        self.move_transaction_counter(&counter).await?;
+        Ok(counter)
+    }
+
+    /// transaction counter that continues after the latest stored document of the process
+    async fn derive_transaction_counter(&self, pid: &String) -> Result<TransactionCounter> {
+        match self.get_latest_document_for_pid(pid).await?{
+            Some(latest) => Ok(TransactionCounter::new(pid.clone(), latest.tc + 1, latest.hash())),
+            None => Ok(TransactionCounter::new_chain(pid.clone()))
+        }
+    }
+
+    /// gets the document of the process with pid that succeeds tc in the process' chain
+    pub async fn get_document_with_next_tc(&self, pid: &String, tc: i64) -> Result<Option<EncryptedDocument>> {
+        let next_tc = tc + 1;
+        debug!("Trying to get document for pid {} and tc {} ...", pid, next_tc);
         let coll = self.database.collection::<EncryptedDocument>(MONGO_COLL_DOCUMENTS);
-        let result = coll.find(None, None).await?
-            .try_collect().await.unwrap_or_else(|_| vec![]);
+        match coll.find_one(Some(doc! {MONGO_PID: pid.clone(), MONGO_TC: next_tc}), None).await{
+            Ok(doc) => {
+                debug!("... done.");
+                Ok(doc)
+            },
+            Err(e) => {
+                error!("Error while getting next document!");
+                Err(Error::from(e))
+            }
+        }
+    }
+
+    // ANCHORS
+    /// gets the size of the tree of the process when its chain hash was last anchored in the ledger
+    pub async fn get_anchored_tree_size(&self, pid: &String) -> Result<Option<i64>> {
+        debug!("Trying to get anchored tree size for pid {} ...", pid);
+        let coll = self.database.collection::<AnchoredHead>(MONGO_COLL_ANCHORS);
+        let result = coll.find_one(Some(doc! { MONGO_PID: pid.clone() }), None).await?;
+        Ok(result.map(|head| head.tree_size))
+    }
+
+    /// records that the chain hash of the process was anchored at the tree size
+    pub async fn set_anchored_tree_size(&self, head: &AnchoredHead) -> Result<()> {
+        debug!("Setting anchored tree size for pid {} to {} ...", &head.pid, head.tree_size);
+        let coll = self.database.collection::<AnchoredHead>(MONGO_COLL_ANCHORS);
+        let update_options = UpdateOptions::builder().upsert(true).build();
+        let update = doc! { "$set": { MONGO_TREE_SIZE: head.tree_size } };
+        coll.update_one(doc! { MONGO_PID: head.pid.clone() }, update, update_options).await?;
+        Ok(())
+    }
+
+    // ALERTS
+    /// records the alert. If the same violation was recorded before, only its `last_seen` is updated.
+    pub async fn add_alert(&self, alert: &Alert) -> Result<bool> {
+        debug!("add_alert({:#?})", json!(alert));
+        let coll = self.database.collection::<Alert>(MONGO_COLL_ALERTS);
+        let update_options = UpdateOptions::builder().upsert(true).build();
+        let filter = doc! {
+            MONGO_PID: alert.pid.clone(),
+            MONGO_DOC_ID: alert.doc_id.clone(),
+            MONGO_KIND: to_bson(&alert.kind).chain_err(|| "Error while serializing alert")?,
+            MONGO_DESCRIPTION: alert.description.clone()
+        };
+        let update = doc! {
+            "$set": { MONGO_LAST_SEEN: alert.last_seen },
+            "$setOnInsert": { MONGO_ID: alert.id.clone(), MONGO_FIRST_SEEN: alert.first_seen }
+        };
+        let result = coll.update_one(filter, update, update_options).await?;
+        if result.upserted_id.is_some(){
+            warn!("recorded new alert for pid {}: {}", &alert.pid, &alert.description);
+        }
+        Ok(true)
+    }
+
+    /// gets the recorded alerts, optionally only for a single process, the most recent first
+    pub async fn get_alerts(&self, pid: Option<String>) -> Result<Vec<Alert>> {
+        debug!("Trying to get alerts for pid {:?}...", pid);
+        let coll = self.database.collection::<Alert>(MONGO_COLL_ALERTS);
+        let filter = pid.map(|pid| doc! { MONGO_PID: pid });
+        let find_options = FindOptions::builder().sort(doc! { MONGO_LAST_SEEN: -1 }).build();
+        let result = coll.find(filter, find_options).await?
+            .try_collect().await?;
         Ok(result)
     }
 }
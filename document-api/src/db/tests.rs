@@ -3,19 +3,29 @@
 // cargo test -- --test-threads=1
 // otherwise they will interfere with each other
 // !!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!
+use core_lib::api::{DocumentQuery, SortField, SortOrder};
 use core_lib::db::DataStoreApi;
 use core_lib::errors::*;
+use core_lib::model::alert::{Alert, AlertKind};
 use core_lib::model::document::EncryptedDocument;
 use mongodb::Client;
-use crate::db::DataStore;
+use rocket::futures::io::{AsyncReadExt, AsyncWriteExt};
+use crate::db::{index, is_duplicate_idempotency_key, DataStore, LEGACY_TC_INDEX};
+use core_lib::constants::{MONGO_COLL_DOCUMENTS, MONGO_COLL_MERKLE_LEAVES, MONGO_ID, MONGO_PID, MONGO_TC};
+use mongodb::bson::doc;
+use crate::scanner::scan;
+use crate::model::anchor::AnchoredHead;
+use crate::model::merkle::MerkleLeaf;
+use crate::model::transaction::TransactionCounter;
 use chrono::Utc;
 
-const DATABASE_URL: &'static str = "mongodb://127.0.0.1:27017";
+const DATABASE_URL: &str = "mongodb://127.0.0.1:27017";
 
 async fn db_setup() -> DataStore {
     let client = Client::with_uri_str(DATABASE_URL).await.unwrap();
     let db = DataStore::new(client);
     db.database.drop(None).await.expect("Database Error");
+    db.migrate().await.expect("Migration Error");
     db
 }
 
@@ -24,9 +34,10 @@ async fn tear_down(db: DataStore){
 }
 
 fn create_test_enc_document(id: &String, pid: &String, dt_id: &String) -> EncryptedDocument{
-    let mut cts = vec!();
-    cts.push(String::from("1::4EBC3F1C2B8CB16C52E41424502FD112015D9C25919C2401514B5DD5B4233B65593CF0A4"));
-    cts.push(String::from("2::FE2195305E95B9F931660CBA20B4707A1D92123022371CEDD2E70A538A8771EE7540D9F34845BBAEECEC"));
+    let cts = vec!(
+        String::from("1::4EBC3F1C2B8CB16C52E41424502FD112015D9C25919C2401514B5DD5B4233B65593CF0A4"),
+        String::from("2::FE2195305E95B9F931660CBA20B4707A1D92123022371CEDD2E70A538A8771EE7540D9F34845BBAEECEC")
+    );
     let key_ct = String::from("very secure key ct");
     let ts = Utc::now().timestamp();
     EncryptedDocument::new(id.clone(), pid.clone(), dt_id.clone(), ts, 3241, key_ct, cts)
@@ -47,7 +58,7 @@ async fn test_document_exists() -> Result<()>{
     db.add_document(doc.clone()).await?;
 
     // run the test
-    assert_eq!(db.exists_document(&id).await?, true);
+    assert!(db.exists_document(&id).await?);
 
     // clean up
     tear_down(db).await;
@@ -70,7 +81,7 @@ async fn test_document_does_not_exist() -> Result<()>{
     db.add_document(doc.clone()).await?;
 
     // run the test
-    assert_eq!(db.exists_document(&id2).await?, false);
+    assert!(!db.exists_document(&id2).await?);
 
     // clean up
     tear_down(db).await;
@@ -92,13 +103,13 @@ async fn test_delete_document_doc_is_deleted() -> Result<()>{
     db.add_document(doc.clone()).await?;
 
     // db should be able to find the document
-    assert_eq!(db.exists_document(&id).await?, true);
+    assert!(db.exists_document(&id).await?);
 
     // run the test
     assert!(db.delete_document(&id).await?);
 
     // db should not find document anymore
-    assert_eq!(db.exists_document(&id).await?, false);
+    assert!(!db.exists_document(&id).await?);
 
     // clean up
     tear_down(db).await;
@@ -123,14 +134,14 @@ async fn test_delete_document_check_others() -> Result<()>{
     db.add_document(doc2.clone()).await?;
 
     // db should be able to find both documents
-    assert_eq!(db.exists_document(&id1).await?, true);
-    assert_eq!(db.exists_document(&id2).await?, true);
+    assert!(db.exists_document(&id1).await?);
+    assert!(db.exists_document(&id2).await?);
 
     // run the test
     assert!(db.delete_document(&id1).await?);
 
     // db should still find the other document
-    assert_eq!(db.exists_document(&id2).await?, true);
+    assert!(db.exists_document(&id2).await?);
 
     // clean up
     tear_down(db).await;
@@ -153,7 +164,7 @@ async fn test_delete_document_on_not_existing_doc() -> Result<()>{
     db.add_document(doc.clone()).await?;
 
     // run the test
-    assert_eq!(db.delete_document(&id2).await?, false);
+    assert!(!db.delete_document(&id2).await?);
 
     // clean up
     tear_down(db).await;
@@ -178,16 +189,575 @@ async fn test_get_document() -> Result<()>{
     db.add_document(doc2.clone()).await?;
 
     // db should be able to find both documents
-    assert_eq!(db.exists_document(&id1).await?, true);
-    assert_eq!(db.exists_document(&id2).await?, true);
+    assert!(db.exists_document(&id1).await?);
+    assert!(db.exists_document(&id2).await?);
 
     // the test
     let result = db.get_document(&id1, &pid).await?;
-    assert_eq!(result.is_some(), true);
+    assert!(result.is_some());
     assert_eq!(result.unwrap().id, id1);
 
     // clean up
     tear_down(db).await;
 
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Testcase: Document is found by the idempotency key of the request that created it, within its process only
+#[tokio::test]
+async fn test_get_document_by_idempotency_key() -> Result<()>{
+    // empty db and create tables
+    let db = db_setup().await;
+
+    // prepare test data
+    let pid = String::from("test_get_document_by_idempotency_key_pid");
+    let other_pid = String::from("test_get_document_by_idempotency_key_other_pid");
+    let dt_id = String::from("test_get_document_by_idempotency_key_dt");
+    let id = String::from("test_get_document_by_idempotency_key_id");
+    let key = String::from("test_get_document_by_idempotency_key_key");
+    let mut doc = create_test_enc_document(&id, &pid, &dt_id);
+    doc.idempotency_key = Some(key.clone());
+    db.add_document(doc).await?;
+
+    // the test
+    let result = db.get_document_by_idempotency_key(&pid, &key).await?;
+    assert_eq!(result.unwrap().id, id);
+    assert!(db.get_document_by_idempotency_key(&other_pid, &key).await?.is_none());
+    assert!(db.get_document_by_idempotency_key(&pid, &String::from("unknown")).await?.is_none());
+
+    // clean up
+    tear_down(db).await;
+
+    Ok(())
+}
+
+/// Testcase: Storing a second document with the idempotency key of a stored document is recognized as duplicate
+#[tokio::test]
+async fn test_duplicate_idempotency_key() -> Result<()>{
+    // empty db and create tables
+    let db = db_setup().await;
+
+    // prepare test data
+    let pid = String::from("test_duplicate_idempotency_key_pid");
+    let dt_id = String::from("test_duplicate_idempotency_key_dt");
+    let key = String::from("test_duplicate_idempotency_key_key");
+    let mut doc = create_test_enc_document(&String::from("test_duplicate_idempotency_key_id"), &pid, &dt_id);
+    doc.tc = 0;
+    doc.idempotency_key = Some(key.clone());
+    db.add_document(doc.clone()).await?;
+
+    // the test: the retry of the request is stored with the next tc
+    let mut retry = create_test_enc_document(&String::from("test_duplicate_idempotency_key_retry"), &pid, &dt_id);
+    retry.tc = 1;
+    retry.idempotency_key = Some(key);
+    let (stored, error) = db.add_documents(vec!(retry)).await;
+    assert_eq!(stored, 0);
+    assert!(is_duplicate_idempotency_key(&error.unwrap()));
+
+    // other collisions are no duplicates of the idempotency key
+    let mut other = create_test_enc_document(&String::from("test_duplicate_idempotency_key_other"), &pid, &dt_id);
+    other.tc = 0;
+    let (stored, error) = db.add_documents(vec!(other)).await;
+    assert_eq!(stored, 0);
+    assert!(!is_duplicate_idempotency_key(&error.unwrap()));
+
+    // clean up
+    tear_down(db).await;
+
+    Ok(())
+}
+
+/// Testcase: Documents of a process that can not be read are returned separately
+#[tokio::test]
+async fn test_get_documents_for_pid_with_unreadable_document() -> Result<()>{
+    // empty db and create tables
+    let db = db_setup().await;
+
+    // prepare test data: the cts of the second document are modified in the db
+    let pid = String::from("test_get_documents_for_pid_with_unreadable_document_pid");
+    let dt_id = String::from("test_get_documents_for_pid_with_unreadable_document_dt");
+    let id = String::from("test_get_documents_for_pid_with_unreadable_document_id");
+    let mut doc = create_test_enc_document(&id, &pid, &dt_id);
+    doc.tc = 0;
+    db.add_document(doc).await?;
+    let modified = doc!{MONGO_ID: "test_get_documents_for_pid_with_unreadable_document_modified", MONGO_PID: pid.clone(), MONGO_TC: 1_i64, "cts": 42};
+    db.database.collection::<mongodb::bson::Document>(MONGO_COLL_DOCUMENTS).insert_one(modified, None).await?;
+
+    // run the test
+    let (docs, unreadable) = db.get_documents_for_pid(&pid).await?;
+
+    // check
+    assert_eq!(docs.len(), 1);
+    assert_eq!(docs[0].id, id);
+    assert_eq!(unreadable.len(), 1);
+    assert_eq!(unreadable[0].id, Some(String::from("test_get_documents_for_pid_with_unreadable_document_modified")));
+    assert_eq!(unreadable[0].tc, Some(1));
+
+    // clean up
+    tear_down(db).await;
+
+    Ok(())
+}
+
+/// Testcase: Migration drops the legacy index on tc and can run again on a migrated db
+#[tokio::test]
+async fn test_migrate_drops_legacy_tc_index() -> Result<()>{
+    // empty db and create tables
+    let db = db_setup().await;
+
+    // prepare test data: documents of two processes with the same tc violate the legacy index
+    let coll = db.database.collection::<EncryptedDocument>(MONGO_COLL_DOCUMENTS);
+    coll.create_index(index(doc!{MONGO_TC: 1}, true), None).await?;
+    let dt_id = String::from("test_migrate_drops_legacy_tc_index_dt");
+    let doc1 = create_test_enc_document(&String::from("test_migrate_drops_legacy_tc_index_id1"), &String::from("test_migrate_drops_legacy_tc_index_pid1"), &dt_id);
+    let doc2 = create_test_enc_document(&String::from("test_migrate_drops_legacy_tc_index_id2"), &String::from("test_migrate_drops_legacy_tc_index_pid2"), &dt_id);
+    db.add_document(doc1).await?;
+    assert!(db.add_document(doc2.clone()).await.is_err());
+
+    // run the test
+    db.migrate().await?;
+    db.migrate().await?;
+    assert!(!coll.list_index_names().await?.contains(&String::from(LEGACY_TC_INDEX)));
+    db.add_document(doc2).await?;
+
+    // clean up
+    tear_down(db).await;
+
+    Ok(())
+}
+
+/// Testcase: Previous document is looked up in the chain of the same process only
+#[tokio::test]
+async fn test_get_document_with_previous_tc_per_process() -> Result<()>{
+    // empty db and create tables
+    let db = db_setup().await;
+
+    // prepare test data
+    let pid1 = String::from("test_get_document_with_previous_tc_per_process_pid1");
+    let pid2 = String::from("test_get_document_with_previous_tc_per_process_pid2");
+    let dt_id = String::from("test_get_document_with_previous_tc_per_process_dt");
+    let id1 = String::from("test_get_document_with_previous_tc_per_process_id1");
+    let id2 = String::from("test_get_document_with_previous_tc_per_process_id2");
+    let mut doc1 = create_test_enc_document(&id1, &pid1, &dt_id);
+    doc1.tc = 0;
+    let mut doc2 = create_test_enc_document(&id2, &pid2, &dt_id);
+    doc2.tc = 0;
+    db.add_document(doc1.clone()).await?;
+    db.add_document(doc2.clone()).await?;
+
+    // run the test: each process has its own chain
+    let result = db.get_document_with_previous_tc(&pid1, 1).await?;
+    assert_eq!(result.unwrap().id, id1);
+    let result = db.get_document_with_previous_tc(&pid2, 1).await?;
+    assert_eq!(result.unwrap().id, id2);
+
+    // the chain of another process is not used
+    let pid3 = String::from("test_get_document_with_previous_tc_per_process_pid3");
+    assert!(db.get_document_with_previous_tc(&pid3, 1).await?.is_none());
+
+    // clean up
+    tear_down(db).await;
+
+    Ok(())
+}
+
+/// Testcase: Each process with documents is listed once
+#[tokio::test]
+async fn test_get_pids() -> Result<()>{
+    // empty db and create tables
+    let db = db_setup().await;
+
+    // prepare test data
+    let pid1 = String::from("test_get_pids_pid1");
+    let pid2 = String::from("test_get_pids_pid2");
+    let dt_id = String::from("test_get_pids_dt");
+    let mut doc1 = create_test_enc_document(&String::from("test_get_pids_id1"), &pid1, &dt_id);
+    doc1.tc = 0;
+    let mut doc2 = create_test_enc_document(&String::from("test_get_pids_id2"), &pid1, &dt_id);
+    doc2.tc = 1;
+    let doc3 = create_test_enc_document(&String::from("test_get_pids_id3"), &pid2, &dt_id);
+    db.add_document(doc1).await?;
+    db.add_document(doc2).await?;
+    db.add_document(doc3).await?;
+
+    // run the test
+    let mut pids = db.get_pids().await?;
+    pids.sort();
+    assert_eq!(pids, vec!(pid1, pid2));
+
+    // clean up
+    tear_down(db).await;
+
+    Ok(())
+}
+
+/// Testcase: Payload stored in GridFS is read back unchanged and can be deleted
+#[tokio::test]
+async fn test_store_and_delete_payload() -> Result<()>{
+    // empty db and create tables
+    let db = db_setup().await;
+
+    // prepare test data: larger than one GridFS chunk
+    let id = String::from("test_store_and_delete_payload_id");
+    let payload: Vec<u8> = (0..1000000).map(|i| (i % 256) as u8).collect();
+    let mut upload = db.open_payload_upload(&id);
+    let file_id = upload.id().as_object_id().unwrap().to_hex();
+    upload.write_all(&payload).await?;
+    upload.close().await?;
+
+    // run the test
+    let mut result = vec!();
+    db.open_payload_download(&file_id).await?.read_to_end(&mut result).await?;
+    assert_eq!(result, payload);
+    db.delete_payload(&file_id).await?;
+
+    // check
+    assert!(db.open_payload_download(&file_id).await.is_err());
+
+    // clean up
+    tear_down(db).await;
+
+    Ok(())
+}
+
+/// Testcase: Transaction counter of a new process starts a new chain and is only moved forward
+#[tokio::test]
+async fn test_move_transaction_counter() -> Result<()>{
+    // empty db and create tables
+    let db = db_setup().await;
+
+    // prepare test data
+    let pid = String::from("test_move_transaction_counter_pid");
+    let head = String::from("test_move_transaction_counter_hash");
+
+    // a new process starts with tc 0 and chain hash "0"
+    let counter = db.get_transaction_counter(&pid).await?;
+    assert_eq!(counter.tc, 0);
+    assert_eq!(counter.hash, String::from("0"));
+
+    // run the test
+    let next = TransactionCounter::new(pid.clone(), 2, head.clone());
+    assert!(db.move_transaction_counter(&next).await?);
+
+    // the counter now points to the next tc and the new head
+    let result = db.get_transaction_counter(&pid).await?;
+    assert_eq!(result.tc, 2);
+    assert_eq!(result.hash, head);
+
+    // the counter is not moved back
+    assert!(!db.move_transaction_counter(&next).await?);
+    assert!(!db.move_transaction_counter(&TransactionCounter::new(pid.clone(), 1, head.clone())).await?);
+    assert_eq!(db.get_transaction_counter(&pid).await?.tc, 2);
+
+    // clean up
+    tear_down(db).await;
+
+    Ok(())
+}
+
+/// Testcase: Batch of documents is stored in order up to the first document whose tc is taken
+#[tokio::test]
+async fn test_add_batch_of_documents() -> Result<()>{
+    // empty db and create tables
+    let db = db_setup().await;
+
+    // prepare test data
+    let pid = String::from("test_add_batch_of_documents_pid");
+    let dt_id = String::from("test_add_batch_of_documents_dt");
+    let mut docs = vec!();
+    for i in 0..3 {
+        let mut doc = create_test_enc_document(&format!("test_add_batch_of_documents_id_{}", i), &pid, &dt_id);
+        doc.tc = i;
+        docs.push(doc);
+    }
+    let mut taken = create_test_enc_document(&String::from("test_add_batch_of_documents_taken"), &pid, &dt_id);
+    taken.tc = 4;
+    db.add_document(taken).await?;
+
+    // run the test
+    let (stored, error) = db.add_documents(docs).await;
+    assert_eq!(stored, 3);
+    assert!(error.is_none());
+
+    // the documents before the document with the taken tc are stored
+    let mut more = vec!();
+    for i in 3..6 {
+        let mut doc = create_test_enc_document(&format!("test_add_batch_of_documents_id_{}", i), &pid, &dt_id);
+        doc.tc = i;
+        more.push(doc);
+    }
+    let (stored, error) = db.add_documents(more).await;
+    assert_eq!(stored, 1);
+    assert!(error.is_some());
+    assert_eq!(db.get_documents_for_pid(&pid).await?.0.len(), 5);
+
+    // a counter that lags behind is synced with the latest document
+    let counter = db.sync_transaction_counter(&pid).await?;
+    assert_eq!(counter.tc, 5);
+    assert_eq!(db.get_transaction_counter(&pid).await?.tc, 5);
+
+    // clean up
+    tear_down(db).await;
+
+    Ok(())
+}
+
+/// Testcase: Pages of documents are filtered by ts, sorted and continue after the cursor
+#[tokio::test]
+async fn test_get_documents_page() -> Result<()>{
+    // empty db and create tables
+    let db = db_setup().await;
+
+    // prepare test data: documents with tc 0..5, the ts of the last two documents precede the others
+    let pid = String::from("test_get_documents_page_pid");
+    let dt_id = String::from("test_get_documents_page_dt");
+    let mut docs = vec!();
+    for i in 0..5 {
+        let mut doc = create_test_enc_document(&format!("test_get_documents_page_id_{}", i), &pid, &dt_id);
+        doc.tc = i;
+        doc.ts = if i < 3 { 100 + i } else { i };
+        docs.push(doc);
+    }
+    db.add_documents(docs).await;
+    let tcs = |docs: Vec<EncryptedDocument>| docs.iter().map(|d| d.tc).collect::<Vec<i64>>();
+
+    // sorted by tc
+    let mut query = DocumentQuery::default();
+    assert_eq!(tcs(db.get_documents_page(&pid, &query, Some(2)).await?), vec!(0, 1));
+    query.after_tc = Some(1);
+    assert_eq!(tcs(db.get_documents_page(&pid, &query, Some(2)).await?), vec!(2, 3));
+    query.order = Some(SortOrder::Desc);
+    assert_eq!(tcs(db.get_documents_page(&pid, &query, None).await?), vec!(0));
+
+    // sorted by ts, the cursor is the position of the document in that order
+    let mut query = DocumentQuery{ sort: Some(SortField::Ts), ..Default::default() };
+    assert_eq!(tcs(db.get_documents_page(&pid, &query, None).await?), vec!(3, 4, 0, 1, 2));
+    query.after_tc = Some(4);
+    assert_eq!(tcs(db.get_documents_page(&pid, &query, Some(2)).await?), vec!(0, 1));
+
+    // filtered by ts
+    let query = DocumentQuery{ from: Some(4), to: Some(101), ..Default::default() };
+    assert_eq!(tcs(db.get_documents_page(&pid, &query, None).await?), vec!(0, 4));
+
+    // clean up
+    tear_down(db).await;
+
+    Ok(())
+}
+
+/// Testcase: Transaction counter of a process without counter continues after the latest document
+#[tokio::test]
+async fn test_transaction_counter_from_existing_documents() -> Result<()>{
+    // empty db and create tables
+    let db = db_setup().await;
+
+    // prepare test data
+    let pid = String::from("test_transaction_counter_from_existing_documents_pid");
+    let dt_id = String::from("test_transaction_counter_from_existing_documents_dt");
+    let id = String::from("test_transaction_counter_from_existing_documents_id");
+    let doc = create_test_enc_document(&id, &pid, &dt_id);
+    db.add_document(doc.clone()).await?;
+
+    // run the test
+    let counter = db.get_transaction_counter(&pid).await?;
+    assert_eq!(counter.tc, doc.tc + 1);
+    assert_eq!(counter.hash, doc.hash());
+
+    // a counter for another process is not affected
+    let other = db.get_transaction_counter(&String::from("test_transaction_counter_from_existing_documents_pid2")).await?;
+    assert_eq!(other.tc, TransactionCounter::new_chain(pid).tc);
+
+    // clean up
+    tear_down(db).await;
+
+    Ok(())
+}
+
+/// Testcase: Merkle tree is read from the leaves of the documents and fails if a tc has no document
+#[tokio::test]
+async fn test_get_merkle_leaves_fails_at_gap() -> Result<()>{
+    // empty db and create tables
+    let db = db_setup().await;
+
+    // prepare test data: document with tc 2 is missing
+    let pid = String::from("test_get_merkle_leaves_fails_at_gap_pid");
+    let dt_id = String::from("test_get_merkle_leaves_fails_at_gap_dt");
+    for tc in [0, 1]{
+        let mut doc = create_test_enc_document(&format!("test_get_merkle_leaves_fails_at_gap_id{}", tc), &pid, &dt_id);
+        doc.tc = tc;
+        doc.leaf = Some(format!("hash{}", tc));
+        db.add_document(doc).await?;
+    }
+
+    // run the test
+    let leaves = db.get_merkle_leaves(&pid).await?;
+    assert_eq!(leaves.len(), 2);
+    assert_eq!(leaves[1].tc, 1);
+    assert_eq!(leaves[1].hash, String::from("hash1"));
+
+    // the tree can not skip the missing document
+    let mut doc = create_test_enc_document(&String::from("test_get_merkle_leaves_fails_at_gap_id3"), &pid, &dt_id);
+    doc.tc = 3;
+    doc.leaf = Some(String::from("hash3"));
+    db.add_document(doc).await?;
+    assert!(db.get_merkle_leaves(&pid).await.is_err());
+
+    // clean up
+    tear_down(db).await;
+
+    Ok(())
+}
+
+/// Testcase: Migration adds the merkle leaves to documents stored without them
+#[tokio::test]
+async fn test_migrate_adds_merkle_leaves() -> Result<()>{
+    // empty db and create tables
+    let db = db_setup().await;
+
+    // prepare test data: the leaf of the first document is in the legacy collection of leaves
+    let pid = String::from("test_migrate_adds_merkle_leaves_pid");
+    let dt_id = String::from("test_migrate_adds_merkle_leaves_dt");
+    let mut docs = vec!();
+    for tc in [0, 1]{
+        let mut doc = create_test_enc_document(&format!("test_migrate_adds_merkle_leaves_id{}", tc), &pid, &dt_id);
+        doc.tc = tc;
+        db.add_document(doc.clone()).await?;
+        docs.push(doc);
+    }
+    let legacy_leaf = MerkleLeaf::new(pid.clone(), 0, docs[0].id.clone(), String::from("legacy_hash"));
+    db.database.collection::<MerkleLeaf>(MONGO_COLL_MERKLE_LEAVES).insert_one(legacy_leaf, None).await?;
+    assert!(db.get_merkle_leaves(&pid).await.is_err());
+
+    // run the test
+    db.migrate().await?;
+
+    // check
+    let leaves = db.get_merkle_leaves(&pid).await?;
+    assert_eq!(leaves.len(), 2);
+    assert_eq!(leaves[0].hash, String::from("legacy_hash"));
+    assert_eq!(leaves[1].hash, docs[1].hash());
+
+    // clean up
+    tear_down(db).await;
+
+    Ok(())
+}
+
+/// Testcase: Anchored tree size of a process is stored and replaced by the next anchoring
+#[tokio::test]
+async fn test_set_anchored_tree_size() -> Result<()>{
+    // empty db and create tables
+    let db = db_setup().await;
+
+    // prepare test data
+    let pid = String::from("test_set_anchored_tree_size_pid");
+    assert_eq!(db.get_anchored_tree_size(&pid).await?, None);
+
+    // run the test
+    db.set_anchored_tree_size(&AnchoredHead::new(pid.clone(), 3)).await?;
+    assert_eq!(db.get_anchored_tree_size(&pid).await?, Some(3));
+    db.set_anchored_tree_size(&AnchoredHead::new(pid.clone(), 5)).await?;
+
+    // check
+    assert_eq!(db.get_anchored_tree_size(&pid).await?, Some(5));
+    assert_eq!(db.get_anchored_tree_size(&String::from("test_set_anchored_tree_size_other_pid")).await?, None);
+
+    // clean up
+    tear_down(db).await;
+
+    Ok(())
+}
+
+/// Testcase: The same violation found in different scans is recorded only once
+#[tokio::test]
+async fn test_add_alert_records_violation_once() -> Result<()>{
+    // empty db and create tables
+    let db = db_setup().await;
+
+    // prepare test data
+    let pid = String::from("test_add_alert_records_violation_once_pid");
+    let doc_id = Some(String::from("test_add_alert_records_violation_once_id"));
+    let first = Alert::new(pid.clone(), doc_id.clone(), AlertKind::MalformedDocument, String::from("keys_ct is not hex encoded"), 1000);
+    let second = Alert::new(pid.clone(), doc_id.clone(), AlertKind::MalformedDocument, String::from("keys_ct is not hex encoded"), 2000);
+    let other = Alert::new(pid.clone(), doc_id.clone(), AlertKind::BrokenChain, String::from("chain is broken"), 2000);
+
+    // run the test
+    db.add_alert(&first).await?;
+    db.add_alert(&second).await?;
+    db.add_alert(&other).await?;
+
+    // check
+    let alerts = db.get_alerts(Some(pid.clone())).await?;
+    assert_eq!(alerts.len(), 2);
+    let recorded = alerts.iter().find(|a| a.kind == AlertKind::MalformedDocument).unwrap();
+    assert_eq!(recorded.id, first.id);
+    assert_eq!(recorded.first_seen, 1000);
+    assert_eq!(recorded.last_seen, 2000);
+    assert!(db.get_alerts(Some(String::from("test_add_alert_records_violation_once_pid2"))).await?.is_empty());
+
+    // clean up
+    tear_down(db).await;
+
+    Ok(())
+}
+
+/// Testcase: Scan records documents with malformed keys_ct
+#[tokio::test]
+async fn test_scan_detects_malformed_document() -> Result<()>{
+    // empty db and create tables
+    let db = db_setup().await;
+
+    // prepare test data: keys_ct of the test documents is not hex encoded
+    let pid = String::from("test_scan_detects_malformed_document_pid");
+    let dt_id = String::from("test_scan_detects_malformed_document_dt");
+    let id = String::from("test_scan_detects_malformed_document_id");
+    let mut doc = create_test_enc_document(&id, &pid, &dt_id);
+    doc.tc = 0;
+    db.add_document(doc).await?;
+
+    // run the test
+    assert_eq!(scan(&db).await?, 1);
+    assert_eq!(scan(&db).await?, 1);
+
+    // check
+    let alerts = db.get_alerts(None).await?;
+    assert_eq!(alerts.len(), 1);
+    assert_eq!(alerts[0].doc_id, Some(id));
+
+    // clean up
+    tear_down(db).await;
+
+    Ok(())
+}
+
+/// Testcase: Scan records documents that can not be read
+#[tokio::test]
+async fn test_scan_records_unreadable_document() -> Result<()>{
+    // empty db and create tables
+    let db = db_setup().await;
+
+    // prepare test data: the cts of the second document are modified in the db
+    let pid = String::from("test_scan_records_unreadable_document_pid");
+    let dt_id = String::from("test_scan_records_unreadable_document_dt");
+    let id = String::from("test_scan_records_unreadable_document_id");
+    let mut doc = create_test_enc_document(&id, &pid, &dt_id);
+    doc.tc = 0;
+    doc.keys_ct = hex::encode(&doc.keys_ct);
+    db.add_document(doc).await?;
+    let modified = doc!{MONGO_ID: "test_scan_records_unreadable_document_modified", MONGO_PID: pid.clone(), MONGO_TC: 1_i64, "cts": 42};
+    db.database.collection::<mongodb::bson::Document>(MONGO_COLL_DOCUMENTS).insert_one(modified, None).await?;
+
+    // run the test
+    assert_eq!(scan(&db).await?, 1);
+
+    // check
+    let alerts = db.get_alerts(Some(pid)).await?;
+    assert_eq!(alerts.len(), 1);
+    assert_eq!(alerts[0].kind, AlertKind::UnreadableDocument);
+    assert_eq!(alerts[0].doc_id, Some(String::from("test_scan_records_unreadable_document_modified")));
+
+    // clean up
+    tear_down(db).await;
+
+    Ok(())
+}
@@ -1,45 +1,318 @@
 use biscuit::Empty;
 use rocket::State;
-use chrono::Local;
+use chrono::{Local, Utc};
 use core_lib::{
     api::{
         ApiResponse,
         auth::ApiKey,
+        DocumentBatchItem,
+        DocumentFailure,
+        DocumentPage,
+        DocumentQuery,
+        SortField,
         claims::IdsClaims,
         client::keyring_api::KeyringApiClient,
-        DocumentReceipt
+        DocumentReceipt,
+        SignedDocumentReceipt
     },
-    constants::ROCKET_DOC_API,
+    constants::{LIMIT_LARGE_PAYLOAD, PAYLOAD_CHUNK_SIZE, ROCKET_DOC_API, ROCKET_JWKS},
+    errors::*,
     model::{
-        crypto::{KeyCt, KeyCtList},
-        document::Document
-    }
+        chain::verify_chain,
+        chunked::{ChunkDecryptor, ChunkEncryptor, ChunkedPayload},
+        crypto::{KeyCt, KeyCtList, KeyEntry, KeyMap},
+        doc_type::DocumentType,
+        document::{Document, DocumentPart, EncryptedDocument, HashVersion},
+        ids::{derive_pid, document_from_ids_message, message_processed_notification, rejection_message, RejectionReason},
+        merkle::{audit_path, consistency_proof, encode_hash, leaf_hash, root_hash, ConsistencyProof, InclusionProof, TreeHead}
+    },
+    signing::SigningKey,
+    util::split_list
 };
+use bytes::Bytes;
+use mongodb::Cursor;
+use mongodb::gridfs::GridFsDownloadStream;
+use rocket::data::{ByteUnit, Data, DataStream, Limits, ToByteUnit};
 use rocket::fairing::AdHoc;
-use rocket::serde::json::{json, Json};
-use crate::db::DataStore;
+use rocket::futures::{future::join_all, io::AsyncWriteExt, stream::{self, Stream}, TryStreamExt};
+use rocket::form::{self, Form};
+use rocket::http::{ContentType, Status};
+use rocket::response::stream::{One, ReaderStream};
+use rocket::serde::json::{json, Json, Value};
+use rocket::tokio::io::{AsyncRead, AsyncReadExt};
+use std::collections::HashMap;
+use std::io;
+use std::pin::Pin;
+use tokio_util::compat::{Compat, FuturesAsyncReadCompatExt};
+use tokio_util::io::StreamReader;
+use crate::db::{is_duplicate_idempotency_key, DataStore};
+use crate::model::idempotency::IdempotencyKey;
+use crate::model::ids::{IdsConfig, IdsMultipartMessage, IdsMultipartResponse};
+use crate::model::transaction::TransactionCounter;
+use crate::timestamping::Timestamping;
 use core_lib::constants::PAYLOAD_PART;
 
+/// number of times a document tries to get the next tc of its process before giving up
+const MAX_TC_ATTEMPTS: i32 = 10;
+/// limit of payloads stored in chunks if `large_payload` is not configured in the limits
+const DEFAULT_LARGE_PAYLOAD_LIMIT: u64 = 1 << 30;
+
+/// number of documents whose keys are requested from the keyring at once when documents are streamed
+const KEY_BATCH_SIZE: usize = 100;
+
+/// decrypted data streamed to the client, e.g. the chunks of a payload. An error aborts the response.
+type DecryptedReader = StreamReader<Pin<Box<dyn Stream<Item = io::Result<Bytes>> + Send>>, Bytes>;
+
 #[post("/", format = "json", data = "<document>")]
 async fn create_enc_document(
     api_key: ApiKey<IdsClaims, Empty>,
     db: &State<DataStore>,
     key_api: &State<KeyringApiClient>,
+    signing_key: &State<SigningKey>,
+    timestamping: &State<Timestamping>,
+    hash_version: &State<HashVersion>,
+    idempotency_key: IdempotencyKey,
     document: Json<Document>
 ) -> ApiResponse {
     debug!("user '{:?}' with claims {:?}", api_key.sub(), api_key.claims());
     let doc: Document = document.into_inner();
     trace!("requested document is: '{:#?}'", json!(doc));
+    let idempotency_key = idempotency_key.0;
+    create_document(&api_key, db, key_api, signing_key, timestamping, *hash_version.inner(), idempotency_key, doc).await
+}
+
+#[post("/ids?<pid>", format = "multipart", data = "<message>")]
+async fn create_enc_document_from_ids_message(
+    api_key: ApiKey<IdsClaims, Empty>,
+    db: &State<DataStore>,
+    key_api: &State<KeyringApiClient>,
+    signing_key: &State<SigningKey>,
+    timestamping: &State<Timestamping>,
+    hash_version: &State<HashVersion>,
+    ids_config: &State<IdsConfig>,
+    pid: Option<String>,
+    message: std::result::Result<Form<IdsMultipartMessage>, form::Errors<'_>>
+) -> IdsMultipartResponse {
+    debug!("user '{:?}' with claims {:?}", api_key.sub(), api_key.claims());
+    let connector_id = &ids_config.connector_id;
+    let reject = |request: &Value, status: Status, reason: RejectionReason, message: String| {
+        warn!("Rejecting IDS message: {}", &message);
+        IdsMultipartResponse::new(status, rejection_message(request, reason, connector_id), Some((ContentType::Plain, message)))
+    };
+    let message = match message {
+        Ok(message) => message.into_inner(),
+        Err(e) => return reject(&Value::Null, Status::BadRequest, RejectionReason::MalformedMessage, format!("Invalid multipart message: {}", e))
+    };
+    let header: Value = match serde_json::from_slice(&message.header.content) {
+        Ok(header) => header,
+        Err(e) => return reject(&Value::Null, Status::BadRequest, RejectionReason::MalformedMessage, format!("Invalid message header: {}", e))
+    };
+
+    let pid = match pid.map(Ok).unwrap_or_else(|| derive_pid(&header)) {
+        Ok(pid) => pid,
+        Err(e) => return reject(&header, Status::BadRequest, RejectionReason::BadParameters, e.to_string())
+    };
+    let payload_type = message.payload.as_ref().and_then(|p| p.content_type.as_ref()).map(|c| c.to_string());
+    let payload = message.payload.as_ref().map(|p| p.content.as_slice());
+    let doc = match document_from_ids_message(pid, &header, payload, payload_type) {
+        Ok(doc) => doc,
+        Err(e) => return reject(&header, Status::BadRequest, RejectionReason::MalformedMessage, e.to_string())
+    };
+    trace!("requested document is: '{:#?}'", json!(doc));
+
+    // a resent message is recognized by its id
+    let idempotency_key = header.get("@id").and_then(|id| id.as_str()).map(String::from);
+    match create_document(&api_key, db, key_api, signing_key, timestamping, *hash_version.inner(), idempotency_key, doc).await {
+        ApiResponse::SuccessCreate(receipt) => {
+            let notification = message_processed_notification(&header, connector_id);
+            IdsMultipartResponse::new(Status::Created, notification, Some((ContentType::JSON, receipt.to_string())))
+        },
+        ApiResponse::SuccessOk(receipt) => {
+            let notification = message_processed_notification(&header, connector_id);
+            IdsMultipartResponse::new(Status::Ok, notification, Some((ContentType::JSON, receipt.to_string())))
+        },
+        ApiResponse::BadRequest(e) => reject(&header, Status::BadRequest, RejectionReason::BadParameters, e),
+        ApiResponse::Unauthorized(e) => reject(&header, Status::Unauthorized, RejectionReason::NotAuthenticated, e),
+        ApiResponse::NotFound(e) => reject(&header, Status::NotFound, RejectionReason::NotFound, e),
+        ApiResponse::IntegrityViolation(e) | ApiResponse::InternalError(e) => reject(&header, Status::InternalServerError, RejectionReason::InternalRecipientError, e),
+        _ => reject(&header, Status::InternalServerError, RejectionReason::InternalRecipientError, String::from("Unexpected result"))
+    }
+}
+
+#[post("/<pid>/batch", format = "json", data = "<documents>")]
+async fn create_enc_documents(
+    api_key: ApiKey<IdsClaims, Empty>,
+    db: &State<DataStore>,
+    key_api: &State<KeyringApiClient>,
+    signing_key: &State<SigningKey>,
+    timestamping: &State<Timestamping>,
+    hash_version: &State<HashVersion>,
+    pid: String,
+    documents: Json<Vec<Document>>
+) -> ApiResponse {
+    debug!("user '{:?}' with claims {:?}", api_key.sub(), api_key.claims());
+    let docs = documents.into_inner();
+    if docs.is_empty() {
+        return ApiResponse::BadRequest(String::from("Batch contains no documents!"));
+    }
+    debug!("creating batch of {} documents for pid {}", docs.len(), &pid);
+    let mut results: Vec<Option<DocumentBatchItem>> = vec![None; docs.len()];
+    // documents that were rejected because of an error of the clearing house, not because they are invalid
+    let mut internal_errors = 0;
+    let mut fail = |i: usize, id: &String, e: String, internal: bool| {
+        warn!("Rejecting document {} of batch: {}", id, &e);
+        results[i] = Some(DocumentBatchItem::failure(id.clone(), e));
+        if internal {
+            internal_errors += 1;
+        }
+    };
+
+    // data validation, each document type is only retrieved once
+    let mut doc_types: HashMap<String, Option<DocumentType>> = HashMap::new();
+    let mut accepted: Vec<(usize, Document)> = vec!();
+    for (i, doc) in docs.into_iter().enumerate() {
+        if doc.pid != pid {
+            fail(i, &doc.id, format!("Document belongs to process '{}'!", &doc.pid), false);
+            continue;
+        }
+        if let Err(e) = check_document(&doc) {
+            fail(i, &doc.id, e, false);
+            continue;
+        }
+        if !doc_types.contains_key(&doc.dt_id) {
+            match key_api.get_document_type(&api_key.raw(), &pid, &doc.dt_id) {
+                Ok(dt) => {
+                    doc_types.insert(doc.dt_id.clone(), dt);
+                },
+                Err(e) => {
+                    error!("Error while retrieving document type: {:?}", e);
+                    fail(i, &doc.id, String::from("Error while retrieving document type!"), true);
+                    continue;
+                }
+            }
+        }
+        let checked = match &doc_types[&doc.dt_id] {
+            Some(dt) => check_document_type(dt, &doc, &[]),
+            None => Err(format!("Document type '{}' does not exist!", &doc.dt_id))
+        };
+        if let Err(e) = checked {
+            fail(i, &doc.id, e, false);
+            continue;
+        }
+        if accepted.iter().any(|(_, d)| d.id == doc.id) {
+            fail(i, &doc.id, String::from("Document occurs more than once in batch!"), false);
+            continue;
+        }
+        match db.exists_document(&doc.id).await {
+            Ok(false) => accepted.push((i, doc)),
+            Ok(true) => fail(i, &doc.id, String::from("Document exists already!"), false),
+            Err(e) => {
+                error!("Error while checking database: {:?}", e);
+                fail(i, &doc.id, String::from("Error while checking database!"), true)
+            }
+        }
+    }
+
+    // get the keys of all documents with a single call
+    if !accepted.is_empty() {
+        debug!("getting keys");
+        let dt_ids: Vec<String> = accepted.iter().map(|(_, d)| d.dt_id.clone()).collect();
+        match key_api.generate_multiple_keys(&api_key.raw(), &pid, &dt_ids) {
+            Ok(key_maps) => {
+                debug!("start encryption");
+                let mut enc_docs = vec!();
+                let mut stored = vec!();
+                for ((i, doc), keys) in accepted.into_iter().zip(key_maps) {
+                    match doc.encrypt(keys) {
+                        Ok(mut enc_doc) => {
+                            enc_doc.hash_version = *hash_version.inner();
+                            enc_doc.content_hash = Some(signing_key.content_hash(&doc));
+                            enc_docs.push(enc_doc);
+                            stored.push(i);
+                        },
+                        Err(e) => {
+                            error!("Error while encrypting: {:?}", e);
+                            fail(i, &doc.id, String::from("Error while encrypting!"), true)
+                        }
+                    }
+                }
+                if !enc_docs.is_empty() {
+                    let ids: Vec<String> = enc_docs.iter().map(|d| d.id.clone()).collect();
+                    // the documents are stored in order, so the receipts belong to the first documents
+                    let (receipts, error) = store_enc_documents(db, signing_key, timestamping, enc_docs).await;
+                    let error = error.unwrap_or_else(|| String::from("Error while storing document!"));
+                    for (i, id) in stored.iter().zip(ids.iter()).skip(receipts.len()) {
+                        fail(*i, id, error.clone(), true);
+                    }
+                    for ((i, id), receipt) in stored.into_iter().zip(ids).zip(receipts) {
+                        results[i] = Some(DocumentBatchItem::success(id, receipt));
+                    }
+                }
+            },
+            Err(e) => {
+                error!("Error while retrieving keys: {:?}", e);
+                for (i, doc) in accepted.iter() {
+                    fail(*i, &doc.id, String::from("Error while retrieving keys!"), true);
+                }
+            }
+        }
+    }
+
+    let results: Vec<DocumentBatchItem> = results.into_iter().flatten().collect();
+    let created = results.iter().filter(|item| item.receipt.is_some()).count();
+    if created == results.len() {
+        ApiResponse::SuccessCreate(json!(results))
+    }
+    else if created > 0 {
+        ApiResponse::MultiStatus(json!(results))
+    }
+    else if internal_errors > 0 {
+        ApiResponse::InternalError(json!(results).to_string())
+    }
+    else {
+        ApiResponse::BadRequest(json!(results).to_string())
+    }
+}
+
+/// Validates, encrypts and stores a new document. Returns the signed receipt of the document.
+/// A retry of a request that already created the document, recognized by its idempotency key or by the id
+/// of the document, returns the receipt of the stored document instead.
+async fn create_document(
+    api_key: &ApiKey<IdsClaims, Empty>,
+    db: &DataStore,
+    key_api: &KeyringApiClient,
+    signing_key: &SigningKey,
+    timestamping: &Timestamping,
+    hash_version: HashVersion,
+    idempotency_key: Option<String>,
+    doc: Document
+) -> ApiResponse {
+    // check if the request was already processed, a retry is answered even if the document type changed since
+    let previous = match &idempotency_key {
+        Some(key) => db.get_document_by_idempotency_key(&doc.pid, key).await,
+        None => db.get_document(&doc.id, &doc.pid).await
+    };
+    match previous {
+        Ok(Some(prev)) => {
+            let conflict = match &idempotency_key {
+                Some(_) => String::from("Idempotency key was already used for a different document!"),
+                None => format!("Document {} was already created with different content!", &doc.id)
+            };
+            return replay_document(signing_key, prev, &doc, conflict)
+        },
+        Ok(None) => debug!("Request was not processed before"),
+        Err(e) => {
+            error!("Error while checking database: {:?}", e);
+            return ApiResponse::InternalError(String::from("Error while checking database!"))
+        }
+    }
 
     // data validation
-    let payload: Vec<String> = doc.parts.iter()
-        .filter(|p| String::from(PAYLOAD_PART) == p.name)
-        .map(|p| p.content.as_ref().unwrap().clone()).collect();
-    if payload.len() > 1 {
-        return ApiResponse::BadRequest(String::from("Document contains two payloads!"));
+    if let Err(e) = check_document(&doc) {
+        return ApiResponse::BadRequest(e);
     }
-    else if payload.len() == 0 {
-        return ApiResponse::BadRequest(String::from("Document contains no payload!"));
+    if let Err(response) = validate_document(key_api, &api_key.raw(), &doc, &[]) {
+        return response;
     }
 
     // check if doc id already exists
@@ -70,7 +343,10 @@ async fn create_enc_document(
             match doc.encrypt(keys) {
                 Ok(ct) => {
                     debug!("got ct");
-                    enc_doc = ct
+                    enc_doc = ct;
+                    enc_doc.hash_version = hash_version;
+                    enc_doc.idempotency_key = idempotency_key.clone();
+                    enc_doc.content_hash = Some(signing_key.content_hash(&doc));
                 },
                 Err(e) => {
                     error!("Error while encrypting: {:?}", e);
@@ -78,56 +354,425 @@ async fn create_enc_document(
                 },
             };
 
-            // chain the document to previous documents
-            debug!("add the chain hash...");
-            // get the document with the previous tc
-            match db.get_document_with_previous_tc(doc.tc).await{
-                Ok(Some(previous_doc)) => {
-                    enc_doc.hash = previous_doc.hash();
-                },
-                Ok(None) => {
-                    if doc.tc == 0{
-                        info!("No entries found for pid {}. Beginning new chain!", &doc.pid);
-                    }
-                    else{
-                        // If this happens, db didn't find a tc entry that should exist.
-                        return ApiResponse::InternalError(String::from("Error while creating the chain hash!"))
-                    }
+            match (store_enc_document(db, signing_key, timestamping, enc_doc).await, &idempotency_key) {
+                // a concurrent retry of the request may have stored the document first
+                (ApiResponse::InternalError(e), Some(key)) => match db.get_document_by_idempotency_key(&doc.pid, key).await {
+                    Ok(Some(prev)) => replay_document(signing_key, prev, &doc, String::from("Idempotency key was already used for a different document!")),
+                    _ => ApiResponse::InternalError(e)
                 },
+                (response, _) => response
+            }
+        }
+    }
+}
+
+/// Answers the retry of a request that already stored `prev`. If the retried document has the same content hash,
+/// the receipt of the stored document is returned again, otherwise the request is rejected with `conflict`.
+fn replay_document(signing_key: &SigningKey, prev: EncryptedDocument, doc: &Document, conflict: String) -> ApiResponse {
+    debug!("Document {} was already stored, comparing content hashes...", &prev.id);
+    if prev.content_hash.as_ref() != Some(&signing_key.content_hash(doc)) {
+        warn!("{}", &conflict);
+        return ApiResponse::BadRequest(conflict)
+    }
+    let receipt = DocumentReceipt::new(prev.ts, &prev.pid, &prev.id, prev.tc, &prev.hash, &prev.hash());
+    match signing_key.sign(receipt.clone()) {
+        Ok(signature) => ApiResponse::SuccessOk(json!(SignedDocumentReceipt::new(receipt, signature, prev.timestamp_token))),
+        Err(e) => {
+            error!("Error while signing receipt: {:?}", e);
+            ApiResponse::InternalError(String::from("Error while signing receipt!"))
+        }
+    }
+}
+
+/// Validates the parts of the document against its document type, apart from the `ignored` parts.
+/// Returns a bad request that lists all violations if the document is invalid.
+fn validate_document(key_api: &KeyringApiClient, token: &String, doc: &Document, ignored: &[&str]) -> std::result::Result<(), ApiResponse> {
+    debug!("getting document type");
+    let dt = match key_api.get_document_type(token, &doc.pid, &doc.dt_id) {
+        Ok(Some(dt)) => dt,
+        Ok(None) => return Err(ApiResponse::BadRequest(format!("Document type '{}' does not exist!", &doc.dt_id))),
+        Err(e) => {
+            error!("Error while retrieving document type: {:?}", e);
+            return Err(ApiResponse::InternalError(String::from("Error while retrieving document type!")))
+        }
+    };
+    check_document_type(&dt, doc, ignored).map_err(ApiResponse::BadRequest)
+}
+
+/// Checks that the document contains exactly one payload and that the content of its binary parts is base64 encoded
+fn check_document(doc: &Document) -> std::result::Result<(), String> {
+    let payload: Vec<&DocumentPart> = doc.parts.iter()
+        .filter(|p| PAYLOAD_PART == p.name)
+        .collect();
+    if payload.len() > 1 {
+        return Err(String::from("Document contains two payloads!"));
+    }
+    else if payload.is_empty() {
+        return Err(String::from("Document contains no payload!"));
+    }
+    else if payload[0].content.is_none() {
+        return Err(String::from("Payload of document has no content!"));
+    }
+    if let Some(part) = doc.parts.iter().find(|p| p.bytes().is_err()) {
+        return Err(format!("Content of part '{}' is not base64 encoded!", &part.name));
+    }
+    Ok(())
+}
+
+/// Returns the violations of the document type by the document, apart from violations of the `ignored` parts
+fn check_document_type(dt: &DocumentType, doc: &Document, ignored: &[&str]) -> std::result::Result<(), String> {
+    let violations: Vec<String> = dt.validate(doc).iter()
+        .filter(|v| !ignored.contains(&v.part.as_str()))
+        .map(|v| v.to_string())
+        .collect();
+    if violations.is_empty() {
+        Ok(())
+    }
+    else {
+        warn!("Document violates document type '{}': {:?}", &dt.id, &violations);
+        Err(format!("Document violates document type '{}': {}", &dt.id, violations.join("; ")))
+    }
+}
+
+/// Assigns the next tc of the process to the encrypted document, chains it to its predecessor,
+/// adds it to the merkle tree and stores it. Returns the signed receipt of the document.
+async fn store_enc_document(db: &DataStore, signing_key: &SigningKey, timestamping: &Timestamping, enc_doc: EncryptedDocument) -> ApiResponse {
+    match store_enc_documents(db, signing_key, timestamping, vec!(enc_doc)).await {
+        (mut receipts, _) if !receipts.is_empty() => ApiResponse::SuccessCreate(json!(receipts.remove(0))),
+        (_, e) => ApiResponse::InternalError(e.unwrap_or_else(|| String::from("Error while storing document!")))
+    }
+}
+
+/// Assigns consecutive tcs of their process to the encrypted documents, chains them in the given order
+/// and stores them together with their leaves of the merkle tree. All documents have to belong to the same process.
+/// Returns the signed receipts of the stored documents, which are the first documents in the given order,
+/// and the error that prevented storing the others, if any.
+///
+/// Storing a document claims its tc, since the (pid, tc) index is unique. The transaction counter is only
+/// moved afterwards, so a document that can not be stored never leaves a gap in the chain. The time-stamp
+/// tokens are obtained concurrently before the documents are stored.
+async fn store_enc_documents(db: &DataStore, signing_key: &SigningKey, timestamping: &Timestamping, mut enc_docs: Vec<EncryptedDocument>) -> (Vec<SignedDocumentReceipt>, Option<String>) {
+    let pid = enc_docs[0].pid.clone();
+    let mut receipts = vec!();
+    let mut attempts = 0;
+    while !enc_docs.is_empty() {
+        // chain the documents to the head of the process
+        debug!("assign tcs and add the chain hashes...");
+        let counter = match db.get_transaction_counter(&pid).await{
+            Ok(counter) => counter,
+            Err(e) => {
+                error!("Error while creating the chain hash: {:?}", e);
+                return (receipts, Some(String::from("Error while creating the chain hash!")))
+            }
+        };
+        if counter.tc == 0{
+            info!("No entries found for pid {}. Beginning new chain!", &pid);
+        }
+        let mut head = counter.hash.clone();
+        for (i, enc_doc) in enc_docs.iter_mut().enumerate(){
+            enc_doc.tc = counter.tc + i as i64;
+            enc_doc.hash = head;
+            head = enc_doc.hash();
+            // the document is added to the merkle tree of the process when it is stored
+            enc_doc.leaf = Some(head.clone());
+        }
+
+        // obtain trusted timestamps for the new chain hashes
+        let heads: Vec<String> = enc_docs.iter().map(|enc_doc| enc_doc.hash()).collect();
+        let tokens = join_all(heads.iter().map(|head| timestamping.timestamp(head))).await;
+        for (enc_doc, token) in enc_docs.iter_mut().zip(tokens){
+            match token{
+                Ok(token) => enc_doc.timestamp_token = token,
+                Err(e) => warn!("Could not obtain time-stamp token for document {}: {:?}", &enc_doc.id, e)
+            }
+        }
+
+        let mut signed = vec!();
+        for enc_doc in enc_docs.iter(){
+            // prepare the success result message
+            let receipt = DocumentReceipt::new(enc_doc.ts, &enc_doc.pid, &enc_doc.id, enc_doc.tc, &enc_doc.hash, &enc_doc.hash());
+            match signing_key.sign(receipt.clone()){
+                Ok(signature) => signed.push(SignedDocumentReceipt::new(receipt, signature, enc_doc.timestamp_token.clone())),
                 Err(e) => {
-                    error!("Error while creating the chain hash: {:?}", e);
-                    return ApiResponse::InternalError(String::from("Error while creating the chain hash!"))
+                    error!("Error while signing receipt: {:?}", e);
+                    return (receipts, Some(String::from("Error while signing receipt!")))
                 }
+            };
+        }
+
+        debug!("storing {} documents ....", enc_docs.len());
+        //TODO store encrypted keys
+        let (stored, error) = db.add_documents(enc_docs.clone()).await;
+        if stored > 0 {
+            let latest = &enc_docs[stored - 1];
+            let counter = TransactionCounter::new(pid.clone(), latest.tc + 1, latest.hash());
+            // a counter that lags behind is moved when the next document collides with the stored ones
+            if let Err(e) = db.move_transaction_counter(&counter).await{
+                warn!("Could not move transaction counter of pid {} to tc {}: {:?}", &pid, counter.tc, e);
             }
+        }
+        receipts.extend(signed.drain(..stored));
+        enc_docs.drain(..stored);
 
-            // prepare the success result message
+        if let Some(e) = error {
+            // the request was stored by a concurrent retry, which is answered by the caller
+            if is_duplicate_idempotency_key(&e) {
+                warn!("Idempotency key of document {} is already used", &enc_docs[0].id);
+                return (receipts, Some(String::from("Idempotency key was already used!")))
+            }
+            // retry the remaining documents if another document took the tc of the first one
+            let taken = match db.get_document_with_previous_tc(&pid, enc_docs[0].tc + 1).await{
+                Ok(Some(other)) => other.id != enc_docs[0].id,
+                _ => false
+            };
+            attempts += 1;
+            if !taken || attempts >= MAX_TC_ATTEMPTS {
+                error!("Error while adding: {:?}", e);
+                return (receipts, Some(String::from("Error while storing document!")))
+            }
+            debug!("tc {} for pid {} already taken. Retrying...", enc_docs[0].tc, &pid);
+            if let Err(e) = db.sync_transaction_counter(&pid).await{
+                error!("Error while creating the chain hash: {:?}", e);
+                return (receipts, Some(String::from("Error while creating the chain hash!")))
+            }
+        }
+    }
+    (receipts, None)
+}
 
+/// Reads from the reader until the chunk is full or the reader is exhausted
+async fn read_chunk<R: AsyncRead + Unpin>(reader: &mut R, chunk_size: usize) -> io::Result<Vec<u8>> {
+    let mut chunk = vec![0u8; chunk_size];
+    let mut filled = 0;
+    while filled < chunk_size {
+        match reader.read(&mut chunk[filled..]).await? {
+            0 => break,
+            n => filled += n
+        }
+    }
+    chunk.truncate(filled);
+    Ok(chunk)
+}
+
+/// Logs the error that occurred while storing the payload. Such errors are not caused by the client
+fn payload_storage_error<E: std::fmt::Debug>(e: E) -> ApiResponse {
+    error!("Error while storing payload: {:?}", e);
+    ApiResponse::InternalError(String::from("Error while storing payload!"))
+}
 
-            let receipt = DocumentReceipt::new(enc_doc.ts, &enc_doc.pid, &enc_doc.id, &enc_doc.hash);
+/// Encrypts the streamed payload chunk by chunk with the key of the payload part and stores the chunks in GridFS.
+/// Only one chunk is kept in memory, since the last chunk is only known once the stream is exhausted.
+/// Payloads that exceed the limit or can not be read from the request are answered with `BadRequest`.
+async fn store_payload(db: &DataStore, id: &String, key: &KeyEntry, mut data: DataStream<'_>, limit: ByteUnit) -> std::result::Result<ChunkedPayload, ApiResponse> {
+    let mut encryptor = ChunkEncryptor::new(&key.key, &key.nonce, PAYLOAD_CHUNK_SIZE).map_err(payload_storage_error)?;
+    let mut upload = db.open_payload_upload(id);
+    let file_id = match upload.id().as_object_id(){
+        Some(file_id) => file_id.to_hex(),
+        None => return Err(payload_storage_error("GridFS did not assign an ObjectId to the payload"))
+    };
 
-            debug!("storing document ....");
-            // store document
-            //TODO store encrypted keys
-            match db.add_document(enc_doc).await {
-                Ok(_b) => ApiResponse::SuccessCreate(json!(receipt)),
+    let result: std::result::Result<(), ApiResponse> = async {
+        let mut length = 0;
+        let mut pending: Option<Vec<u8>> = None;
+        loop {
+            let chunk = match read_chunk(&mut data, PAYLOAD_CHUNK_SIZE).await {
+                Ok(chunk) => chunk,
                 Err(e) => {
-                    error!("Error while adding: {:?}", e);
-                    ApiResponse::InternalError(String::from("Error while storing document!"))
+                    warn!("Error while reading payload: {:?}", e);
+                    return Err(ApiResponse::BadRequest(String::from("Error while reading payload!")))
                 }
+            };
+            length += chunk.len() as u64;
+            if length > limit.as_u64() {
+                return Err(ApiResponse::BadRequest(format!("Payload exceeds the limit of {}!", limit)))
+            }
+            if chunk.is_empty() {
+                break;
+            }
+            if let Some(previous) = pending.replace(chunk) {
+                let ct = encryptor.encrypt_chunk(&previous, false).map_err(payload_storage_error)?;
+                upload.write_all(&ct).await.map_err(payload_storage_error)?;
+            }
+        }
+        let ct = encryptor.encrypt_chunk(&pending.unwrap_or_default(), true).map_err(payload_storage_error)?;
+        upload.write_all(&ct).await.map_err(payload_storage_error)?;
+        upload.close().await.map_err(payload_storage_error)?;
+        Ok(())
+    }.await;
+
+    match result {
+        Ok(()) => encryptor.finish(file_id, key.id.clone()).map_err(payload_storage_error),
+        Err(response) => {
+            if let Err(abort_error) = upload.abort().await {
+                warn!("Could not abort upload of payload for document '{}': {:?}", id, abort_error);
             }
+            Err(response)
         }
     }
 }
 
+/// Stores a document whose payload is streamed in the request body. The payload is encrypted in chunks
+/// and stored in GridFS, the other parts of the document are given as query parameters.
+#[post("/<pid>/large?<dt_id>&<id>&<parts..>", data = "<payload>")]
+async fn create_enc_document_with_large_payload(
+    api_key: ApiKey<IdsClaims, Empty>,
+    db: &State<DataStore>,
+    key_api: &State<KeyringApiClient>,
+    signing_key: &State<SigningKey>,
+    timestamping: &State<Timestamping>,
+    hash_version: &State<HashVersion>,
+    limits: &Limits,
+    pid: String,
+    dt_id: String,
+    id: Option<String>,
+    parts: HashMap<String, String>,
+    payload: Data<'_>
+) -> ApiResponse {
+    debug!("user '{:?}' with claims {:?}", api_key.sub(), api_key.claims());
+    if parts.contains_key(PAYLOAD_PART) {
+        return ApiResponse::BadRequest(String::from("Payload has to be sent in the request body!"));
+    }
+    let parts = parts.into_iter().map(|(name, content)| DocumentPart::new(name, Some(content))).collect();
+    let mut doc = Document::new(pid, dt_id, 0, parts);
+    if let Some(id) = id {
+        doc.id = id;
+    }
+    // the payload is streamed and can not be validated before it is stored
+    if let Err(response) = validate_document(key_api, &api_key.raw(), &doc, &[PAYLOAD_PART]) {
+        return response;
+    }
+
+    // check if doc id already exists
+    match db.exists_document(&doc.id).await {
+        Ok(false) => debug!("Document does not exists!"),
+        _ => {
+            warn!("Document exists already!");
+            return ApiResponse::BadRequest(String::from("Document exists already!"))
+        }
+    }
+
+    debug!("getting keys");
+    let keys = match key_api.generate_keys(&api_key.raw(), &doc.pid, &doc.dt_id) {
+        Ok(key_map) => key_map,
+        Err(e) => {
+            error!("Error while retrieving keys: {:?}", e);
+            return ApiResponse::InternalError(String::from("Error while retrieving keys!"))
+        }
+    };
+    let payload_key = match keys.keys.get(PAYLOAD_PART) {
+        Some(key) => key.clone(),
+        None => return ApiResponse::BadRequest(format!("Document type '{}' has no payload!", &doc.dt_id))
+    };
+
+    debug!("start encryption");
+    let mut enc_doc = match doc.encrypt(keys) {
+        Ok(enc_doc) => enc_doc,
+        Err(e) => {
+            error!("Error while encrypting: {:?}", e);
+            return ApiResponse::InternalError(String::from("Error while encrypting!"))
+        }
+    };
+    enc_doc.hash_version = *hash_version.inner();
+
+    debug!("storing payload in chunks...");
+    let limit = limits.get(LIMIT_LARGE_PAYLOAD).unwrap_or(DEFAULT_LARGE_PAYLOAD_LIMIT.bytes());
+    let reference = match store_payload(db, &doc.id, &payload_key, payload.open(limit + 1), limit).await {
+        Ok(reference) => reference,
+        Err(response) => return response
+    };
+    let file_id = reference.file_id.clone();
+    enc_doc.payload = Some(reference);
+
+    match store_enc_document(db, signing_key, timestamping, enc_doc).await {
+        ApiResponse::SuccessCreate(receipt) => ApiResponse::SuccessCreate(receipt),
+        response => {
+            // the document may be stored even if storing it reported an error
+            if let Ok(true) = db.exists_document(&doc.id).await {
+                return response
+            }
+            if let Err(e) = db.delete_payload(&file_id).await {
+                warn!("Could not delete payload of document '{}': {:?}", &doc.id, e);
+            }
+            response
+        }
+    }
+}
+
+/// Streams the decrypted payload of a document whose payload is stored in chunks
+#[get("/<pid>/<id>/payload")]
+async fn get_large_payload(api_key: ApiKey<IdsClaims, Empty>, key_api: &State<KeyringApiClient>, db: &State<DataStore>, pid: String, id: String) -> std::result::Result<(ContentType, ReaderStream<One<DecryptedReader>>), ApiResponse> {
+    debug!("user '{:?}' with claims {:?}", api_key.sub(), api_key.claims());
+    debug!("trying to retrieve payload of document with id '{}' for pid '{}'", &id, &pid);
+    let ct = match db.get_document(&id, &pid).await {
+        Ok(Some(ct)) => ct,
+        Ok(None) => return Err(ApiResponse::NotFound(format!("Document {} not found!", &id))),
+        Err(e) => {
+            error!("Error while retrieving document: {:?}", e);
+            return Err(ApiResponse::InternalError(format!("Error while retrieving document {}", &id)))
+        }
+    };
+    let reference = match ct.payload.clone() {
+        Some(reference) => reference,
+        None => return Err(ApiResponse::NotFound(format!("Payload of document {} is not stored in chunks!", &id)))
+    };
+
+    let key = match hex::decode(&ct.keys_ct).chain_err(|| "Invalid key ct")
+        .and_then(|key_ct| key_api.decrypt_keys(&api_key.raw(), &pid, &ct.dt_id, &key_ct)) {
+        Ok(key_map) => key_map.keys.get(&reference.key_id).cloned(),
+        Err(e) => {
+            error!("Error while retrieving keys from keyring: {:?}", e);
+            return Err(ApiResponse::InternalError(String::from("Error while retrieving keys")))
+        }
+    };
+    let decryptor = match key.map(|key| ChunkDecryptor::new(&key.key, &key.nonce, &reference)) {
+        Some(Ok(decryptor)) => decryptor,
+        _ => {
+            error!("Key for payload of document '{}' not available", &id);
+            return Err(ApiResponse::InternalError(String::from("Error while retrieving keys")))
+        }
+    };
+    let download = match db.open_payload_download(&reference.file_id).await {
+        Ok(download) => download.compat(),
+        Err(e) => {
+            error!("Error while opening payload: {:?}", e);
+            return Err(ApiResponse::InternalError(format!("Error while retrieving payload of document {}", &id)))
+        }
+    };
+
+    let chunk_size = reference.stored_chunk_size();
+    let chunks = stream::try_unfold((download, decryptor), move |(mut download, mut decryptor): (Compat<GridFsDownloadStream>, ChunkDecryptor)| async move {
+        if decryptor.is_finished() {
+            return Ok(None)
+        }
+        let chunk = read_chunk(&mut download, chunk_size).await?;
+        match decryptor.decrypt_chunk(&chunk) {
+            Ok(pt) => Ok(Some((Bytes::from(pt), (download, decryptor)))),
+            Err(e) => {
+                error!("Error while decrypting payload: {:?}", e);
+                Err(io::Error::new(io::ErrorKind::InvalidData, e.to_string()))
+            }
+        }
+    });
+    let reader: DecryptedReader = StreamReader::new(Box::pin(chunks));
+    Ok((ContentType::Binary, ReaderStream::one(reader)))
+}
+
 #[delete("/<pid>/<id>", format = "json")]
 async fn delete_document(api_key: ApiKey<IdsClaims, Empty>, db: &State<DataStore>, pid: String, id: String) -> ApiResponse {
     debug!("delete called...");
     debug!("user '{:?}' with claims {:?}", api_key.sub(), api_key.claims());
     // this is only a sanity check, i.e. we make sure id/pid pair exists
     match db.get_document(&id, &pid).await{
-        Ok(Some(_enc_doc)) => {
+        Ok(Some(enc_doc)) => {
             match db.delete_document(&id).await{
-                Ok(true) => ApiResponse::SuccessNoContent(String::from("Document deleted!")),
+                Ok(true) => {
+                    if let Some(payload) = enc_doc.payload{
+                        if let Err(e) = db.delete_payload(&payload.file_id).await{
+                            warn!("Could not delete payload of document '{}': {:?}", &id, e);
+                        }
+                    }
+                    ApiResponse::SuccessNoContent(String::from("Document deleted!"))
+                },
                 Ok(false) => ApiResponse::NotFound(String::from("Document does not exist!")),
                 Err(e) => {
                     error!("Error while deleting document: {:?}", e);
@@ -142,131 +787,434 @@ async fn delete_document(api_key: ApiKey<IdsClaims, Empty>, db: &State<DataStore
     }
 }
 
-#[get("/<pid>?<doc_type>", format = "json")]
-async fn get_enc_documents_for_pid(api_key: ApiKey<IdsClaims, Empty>, key_api: &State<KeyringApiClient>, db: &State<DataStore>, doc_type: Option<String>, pid: String) -> ApiResponse {
+/// Returns the decrypted documents of the process that match the query, see `DocumentQuery`
+#[get("/<pid>?<query..>", format = "json")]
+async fn get_enc_documents_for_pid(api_key: ApiKey<IdsClaims, Empty>, key_api: &State<KeyringApiClient>, db: &State<DataStore>, pid: String, query: DocumentQuery) -> ApiResponse {
     debug!("trying to retrieve documents for pid '{}'", &pid);
     debug!("user '{:?}' with claims {:?}", api_key.sub(), api_key.claims());
-    // either call db with type filter or without to get cts
-    let cts;
+    if let Err(response) = check_document_query(db, &pid, &query).await {
+        return response;
+    }
     let start = Local::now();
-    if doc_type.is_some(){
-        debug!("but only of document type: '{}'", doc_type.as_ref().unwrap());
-        match db.get_documents_of_dt_for_pid(doc_type.as_ref().unwrap(), &pid).await{
-            Ok(cts_type_filter) => cts = cts_type_filter,
-            Err(e) => {
-                error!("Error while retrieving document: {:?}", e);
-                return ApiResponse::InternalError(format!("Error while retrieving document for {}", &pid))
-            }
+    // one more document than requested shows if there is a next page
+    let mut cts = match db.get_documents_page(&pid, &query, query.limit.map(|l| l as i64 + 1)).await{
+        Ok(cts) => cts,
+        Err(e) => {
+            error!("Error while retrieving document: {:?}", e);
+            return ApiResponse::InternalError(format!("Error while retrieving document for {}", &pid))
         }
+    };
+    let next_after_tc = match query.limit {
+        Some(limit) if cts.len() > limit as usize => {
+            cts.truncate(limit as usize);
+            cts.last().map(|ct| ct.tc)
+        },
+        _ => None
+    };
+    // The db might contain no documents in which case we get an empty vector
+    let (pts_bulk, failures) = if cts.is_empty(){
+        debug!("Queried empty pid: {}", &pid);
+        (vec!(), vec!())
     }
     else{
-        debug!("no type filter applied");
-        match db.get_documents_for_pid(&pid).await{
-            //TODO: would like to send "{}" instead of "null" when dt is not found
-            Ok(cts_unfiltered) => cts = cts_unfiltered,
+        // Documents found for pid, now decrypting them
+        match decrypt_documents(key_api, &api_key.raw(), &pid, &cts, query.part_names().as_deref()) {
+            Ok(result) => result,
+            Err(e) => return ApiResponse::InternalError(e)
+        }
+    };
+    let end = Local::now();
+    let diff = end - start;
+    info!("Total time taken to run in ms: {}", diff.num_milliseconds());
+    if !failures.is_empty() {
+        warn!("{} documents of pid {} could not be decrypted", failures.len(), &pid);
+    }
+    ApiResponse::SuccessOk(json!(DocumentPage::new(pts_bulk, failures, next_after_tc)))
+}
+
+/// Streams the decrypted documents of the process that match the query as NDJSON, one document per line.
+/// The documents are decrypted in batches as they are read from the db, so the whole process is never held in memory.
+/// Documents of a batch that could not be decrypted follow the batch as `{"failure": <DocumentFailure>}` lines.
+#[get("/<pid>?<query..>", format = "application/x-ndjson", rank = 2)]
+async fn stream_enc_documents_for_pid(api_key: ApiKey<IdsClaims, Empty>, key_api: &State<KeyringApiClient>, db: &State<DataStore>, pid: String, query: DocumentQuery) -> std::result::Result<(ContentType, ReaderStream<One<DecryptedReader>>), ApiResponse> {
+    debug!("trying to stream documents for pid '{}'", &pid);
+    debug!("user '{:?}' with claims {:?}", api_key.sub(), api_key.claims());
+    check_document_query(db, &pid, &query).await?;
+    let cursor = match db.find_documents(&pid, &query, query.limit.map(|l| l as i64)).await {
+        Ok(cursor) => cursor,
+        Err(e) => {
+            error!("Error while retrieving document: {:?}", e);
+            return Err(ApiResponse::InternalError(format!("Error while retrieving document for {}", &pid)))
+        }
+    };
+
+    let key_api = key_api.inner().clone();
+    let token = api_key.raw();
+    let parts = query.part_names();
+    let lines = stream::try_unfold((cursor, key_api, token, pid, parts), |(mut cursor, key_api, token, pid, parts): (Cursor<EncryptedDocument>, KeyringApiClient, String, String, Option<Vec<String>>)| async move {
+        // the keys of a batch are requested with a single call to the keyring
+        let mut cts = vec!();
+        while cts.len() < KEY_BATCH_SIZE {
+            match cursor.try_next().await {
+                Ok(Some(ct)) => cts.push(ct),
+                Ok(None) => break,
+                Err(e) => {
+                    error!("Error while retrieving document: {:?}", e);
+                    return Err(io::Error::other(e.to_string()))
+                }
+            }
+        }
+        if cts.is_empty() {
+            return Ok(None)
+        }
+        let (pts, failures) = decrypt_documents(&key_api, &token, &pid, &cts, parts.as_deref())
+            .map_err(io::Error::other)?;
+        let mut lines = Vec::new();
+        for pt in pts.iter() {
+            serde_json::to_writer(&mut lines, pt)?;
+            lines.push(b'\n');
+        }
+        // documents that could not be decrypted are reported in lines of their own
+        for failure in failures.iter() {
+            serde_json::to_writer(&mut lines, &json!({ "failure": failure }))?;
+            lines.push(b'\n');
+        }
+        Ok(Some((Bytes::from(lines), (cursor, key_api, token, pid, parts))))
+    });
+    let reader: DecryptedReader = StreamReader::new(Box::pin(lines));
+    Ok((ContentType::new("application", "x-ndjson"), ReaderStream::one(reader)))
+}
+
+/// Checks the parameters of a query for the documents of a process
+async fn check_document_query(db: &DataStore, pid: &String, query: &DocumentQuery) -> std::result::Result<(), ApiResponse> {
+    if query.limit == Some(0) {
+        return Err(ApiResponse::BadRequest(String::from("Limit has to be positive!")));
+    }
+    check_part_names(query.part_names().as_deref())?;
+    if let (SortField::Ts, Some(after_tc)) = (query.sort_field(), query.after_tc) {
+        // the cursor has to exist to find its position in the order of ts
+        match db.get_document_with_previous_tc(pid, after_tc + 1).await {
+            Ok(Some(_)) => (),
+            Ok(None) => return Err(ApiResponse::BadRequest(format!("Document with tc {} not found!", after_tc))),
             Err(e) => {
                 error!("Error while retrieving document: {:?}", e);
-                return ApiResponse::InternalError(format!("Error while retrieving document for {}", &pid))
+                return Err(ApiResponse::InternalError(format!("Error while retrieving document for {}", pid)))
             }
         }
-    };
-    // The db might contain no documents in which case we get an empty vector
-    if cts.is_empty(){
-        debug!("Queried empty pid: {}", &pid);
-        ApiResponse::SuccessOk(json!(cts))
     }
-    else{
-        // Documents found for pid, now decrypting them
-        debug!("Found {} documents. Getting keys from keyring...", cts.len());
-        let key_cts: Vec<KeyCt> = cts.iter()
-            .map(|e| KeyCt::new(e.id.clone(), e.keys_ct.clone())).collect();
-        // caution! we currently only support a single dt per call, so we use the first dt we found
-        let key_cts_list = KeyCtList::new(cts[0].dt_id.clone(), key_cts);
+    if let Some(doc_type) = query.doc_type.as_ref(){
+        debug!("but only of document type: '{}'", doc_type);
+    }
+    Ok(())
+}
+
+/// Checks that a selection of parts names at least one part
+fn check_part_names(parts: Option<&[String]>) -> std::result::Result<(), ApiResponse> {
+    match parts {
+        Some([]) => Err(ApiResponse::BadRequest(String::from("No parts selected!"))),
+        _ => Ok(())
+    }
+}
+
+/// Decrypts the documents of the process with keys requested from the keyring in a single call per document type.
+/// The decrypted documents keep the order of `cts`, documents that can not be decrypted are returned as failures.
+/// If `parts` is given, only the keys of these parts are requested and only these parts are decrypted.
+fn decrypt_documents(key_api: &KeyringApiClient, token: &String, pid: &String, cts: &[EncryptedDocument], parts: Option<&[String]>) -> std::result::Result<(Vec<Document>, Vec<DocumentFailure>), String> {
+    debug!("Found {} documents. Getting keys from keyring...", cts.len());
+    // the keyring restores the keys of a document with its document type, so the documents are grouped by type
+    let mut dt_ids: Vec<&String> = vec!();
+    let mut groups: HashMap<&String, Vec<&EncryptedDocument>> = HashMap::new();
+    for ct in cts.iter() {
+        groups.entry(&ct.dt_id).or_insert_with(|| {
+            dt_ids.push(&ct.dt_id);
+            vec!()
+        }).push(ct);
+    }
+
+    // the keyring leaves out key maps it can not restore, so they are matched with the documents by id
+    let mut key_maps: HashMap<String, KeyMap> = HashMap::new();
+    for dt_id in dt_ids {
+        let group = &groups[dt_id];
+        let key_cts: Vec<KeyCt> = group.iter()
+            .map(|ct| KeyCt::new(ct.id.clone(), ct.keys_ct.clone())).collect();
+        let mut key_cts_list = KeyCtList::new(dt_id.clone(), key_cts);
+        key_cts_list.parts = parts.map(|parts| parts.to_vec());
         // decrypt cts
-        let key_maps = match key_api.decrypt_multiple_keys(&api_key.raw(), &pid,&key_cts_list){
-            Ok(key_map) => {
-                key_map
+        match key_api.decrypt_multiple_keys(token, pid, &key_cts_list){
+            Ok(items) => {
+                debug!("... keys of {} of {} documents of type {} received", items.len(), group.len(), dt_id);
+                key_maps.extend(items.into_iter().map(|item| (item.id, item.map)));
             }
             Err(e) => {
-                error!("Error while retrieving keys from keyring: {:?}", e);
-                return ApiResponse::InternalError(format!("Error while retrieving keys from keyring"))
+                error!("Error while retrieving keys of document type {} from keyring: {:?}", dt_id, e);
+                return Err("Error while retrieving keys from keyring".to_string())
             }
         };
-        debug!("... keys received. Starting decryption...");
-        let pts_bulk : Vec<Document> = cts.iter().zip(key_maps.iter())
-            .filter_map(|(ct,key_map)|{
-                if ct.id != key_map.id{
-                    error!("Document and map don't match");
+    }
+
+    debug!("Starting decryption...");
+    let mut pts = vec!();
+    let mut failures = vec!();
+    for ct in cts.iter() {
+        let decrypted = match key_maps.remove(&ct.id) {
+            Some(key_map) => {
+                let decrypted = match parts {
+                    Some(_) => ct.decrypt_parts(key_map.keys),
+                    None => ct.decrypt(key_map.keys)
                 };
-                match ct.decrypt(key_map.map.keys.clone()){
-                    Ok(d) => Some(d),
-                    Err(e) => {
-                        warn!("Got empty document from decryption! {:?}", e);
-                        None
-                    }
+                decrypted.map_err(|e| format!("Decryption failed: {}", e))
+            },
+            None => Err(String::from("Keys could not be restored by the keyring"))
+        };
+        match decrypted {
+            Ok(d) => pts.push(d),
+            Err(reason) => {
+                warn!("Could not decrypt document {} with tc {}: {}", &ct.id, ct.tc, &reason);
+                failures.push(DocumentFailure::new(ct.id.clone(), ct.tc, reason));
+            }
+        }
+    }
+    debug!("...done.");
+    Ok((pts, failures))
+}
+
+/// Checks the integrity of the document using the hash given by the client.
+/// The hash is either the chain hash of the document (as found in the receipt), which has to match
+/// the hash of its predecessor, or the hash of the document itself, which has to match the chain hash
+/// of its successor. The head of the chain has no successor, so the recomputed hash suffices there.
+async fn check_integrity(db: &DataStore, ct: &EncryptedDocument, hash: &String) -> Result<bool> {
+    if hash == &ct.hash {
+        match db.get_document_with_previous_tc(&ct.pid, ct.tc).await?{
+            Some(previous_doc) => Ok(previous_doc.hash() == ct.hash),
+            None => {
+                if ct.tc == 0{
+                    Ok(ct.hash == "0")
+                }
+                else{
+                    warn!("Predecessor of document '{}' not found. The chain is broken", &ct.id);
+                    Ok(false)
                 }
-            }).collect();
-        debug!("...done.");
-        let end = Local::now();
-        let diff = end - start;
-        info!("Total time taken to run in ms: {}", diff.num_milliseconds());
-        ApiResponse::SuccessOk(json!(pts_bulk))
+            }
+        }
+    }
+    else if hash == &ct.hash() {
+        match db.get_document_with_next_tc(&ct.pid, ct.tc).await?{
+            Some(next_doc) => Ok(&next_doc.hash == hash),
+            None => {
+                debug!("Document '{}' is the head of the chain. Only the recomputed hash was checked", &ct.id);
+                Ok(true)
+            }
+        }
+    }
+    else{
+        Ok(false)
     }
 }
 
-/// Retrieve document with id for process with pid
-#[get("/<pid>/<id>?<hash>", format = "json")]
-async fn get_enc_document(api_key: ApiKey<IdsClaims, Empty>, key_api: &State<KeyringApiClient>, db: &State<DataStore>, pid: String, id: String, hash: Option<String>) -> ApiResponse {
+/// Retrieve document with id for process with pid. If `parts` is given, only the listed parts are decrypted.
+#[get("/<pid>/<id>?<hash>&<parts>", format = "json")]
+async fn get_enc_document(api_key: ApiKey<IdsClaims, Empty>, key_api: &State<KeyringApiClient>, db: &State<DataStore>, pid: String, id: String, hash: Option<String>, parts: Option<String>) -> ApiResponse {
     debug!("user '{:?}' with claims {:?}", api_key.sub(), api_key.claims());
     debug!("trying to retrieve document with id '{}' for pid '{}'", &id, &pid);
-    if hash.is_some(){
-        debug!("integrity check with hash: {}", hash.as_ref().unwrap());
+    if let Some(hash) = hash.as_ref(){
+        debug!("integrity check with hash: {}", hash);
+    }
+    let parts = parts.map(|parts| split_list(&parts));
+    if let Err(response) = check_part_names(parts.as_deref()) {
+        return response;
     }
 
     match db.get_document(&id, &pid).await{
         //TODO: would like to send "{}" instead of "null" when dt is not found
         Ok(Some(ct)) => {
+            // check the integrity before anything is decrypted
+            if let Some(hash) = hash.as_ref(){
+                match check_integrity(db, &ct, hash).await{
+                    Ok(true) => debug!("integrity check passed"),
+                    Ok(false) => {
+                        warn!("Integrity check for document '{}' with pid '{}' failed!", &id, &pid);
+                        return ApiResponse::IntegrityViolation(format!("Integrity violation for document {}!", &id))
+                    },
+                    Err(e) => {
+                        error!("Error while checking integrity: {:?}", e);
+                        return ApiResponse::InternalError(format!("Error while checking integrity of document {}", &id))
+                    }
+                }
+            }
             match hex::decode(&ct.keys_ct){
                 Ok(key_ct) => {
-                    match key_api.decrypt_keys(&api_key.raw(), &pid, &ct.dt_id, &key_ct){
+                    // only the keys of the requested parts are restored
+                    let keys = match parts.as_ref() {
+                        Some(parts) => key_api.decrypt_part_keys(&api_key.raw(), &pid, &ct.dt_id, &key_ct, parts),
+                        None => key_api.decrypt_keys(&api_key.raw(), &pid, &ct.dt_id, &key_ct)
+                    };
+                    match keys{
                         Ok(key_map) => {
-                            //TODO check the hash
-                            match ct.decrypt(key_map.keys){
+                            let decrypted = match parts {
+                                Some(_) => ct.decrypt_parts(key_map.keys),
+                                None => ct.decrypt(key_map.keys)
+                            };
+                            match decrypted{
                                 Ok(d) => ApiResponse::SuccessOk(json!(d)),
                                 Err(e) => {
                                     warn!("Got empty document from decryption! {:?}", e);
-                                    return ApiResponse::NotFound(format!("Document {} not found!", &id))
+                                    ApiResponse::NotFound(format!("Document {} not found!", &id))
                                 }
                             }
                         }
                         Err(e) => {
                             error!("Error while retrieving keys from keyring: {:?}", e);
-                            return ApiResponse::InternalError(format!("Error while retrieving keys"))
+                            ApiResponse::InternalError("Error while retrieving keys".to_string())
                         }
                     }
 
                 },
                 Err(e) => {
                     error!("Error while decoding ciphertext: {:?}", e);
-                    return ApiResponse::InternalError(format!("Key Ciphertext corrupted"))
+                    ApiResponse::InternalError("Key Ciphertext corrupted".to_string())
                 }
             }
         },
         Ok(None) => {
             debug!("Nothing found in db!");
-            return ApiResponse::NotFound(format!("Document {} not found!", &id))
+            ApiResponse::NotFound(format!("Document {} not found!", &id))
         }
         Err(e) => {
             error!("Error while retrieving document: {:?}", e);
-            return ApiResponse::InternalError(format!("Error while retrieving document {}", &id))
+            ApiResponse::InternalError(format!("Error while retrieving document {}", &id))
         }
     }
 }
 
+/// Creates the signed head of the merkle tree over the given leaf hashes of the process with pid
+fn sign_tree_head(signing_key: &SigningKey, pid: &String, leaf_hashes: &[Vec<u8>]) -> Result<String> {
+    let tree_head = TreeHead::new(pid.clone(), leaf_hashes.len(), encode_hash(&root_hash(leaf_hashes)), Utc::now().timestamp());
+    signing_key.sign(tree_head)
+}
+
+/// Retrieve the leaf hashes of the merkle tree of the process with pid
+async fn get_leaf_hashes(db: &DataStore, pid: &String) -> Result<Vec<(String, Vec<u8>)>> {
+    let leaves = db.get_merkle_leaves(pid).await?;
+    Ok(leaves.iter().map(|l| (l.id.clone(), leaf_hash(&l.hash))).collect())
+}
+
+/// Retrieve the signed head of the merkle tree of the process with pid
+#[get("/<pid>/tree_head", format = "json")]
+async fn get_tree_head(api_key: ApiKey<IdsClaims, Empty>, db: &State<DataStore>, signing_key: &State<SigningKey>, pid: String) -> ApiResponse {
+    debug!("user '{:?}' with claims {:?}", api_key.sub(), api_key.claims());
+    debug!("trying to create tree head for pid '{}'", &pid);
+    let leaf_hashes: Vec<Vec<u8>> = match get_leaf_hashes(db, &pid).await{
+        Ok(leaves) => leaves.into_iter().map(|(_, h)| h).collect(),
+        Err(e) => {
+            error!("Error while retrieving merkle leaves: {:?}", e);
+            return ApiResponse::InternalError(format!("Error while retrieving merkle tree for {}", &pid))
+        }
+    };
+    match sign_tree_head(signing_key, &pid, &leaf_hashes){
+        Ok(sth) => ApiResponse::SuccessOk(json!(sth)),
+        Err(e) => {
+            error!("Error while signing tree head: {:?}", e);
+            ApiResponse::InternalError(String::from("Error while signing tree head"))
+        }
+    }
+}
+
+/// Retrieve the proof that document with id is included in the merkle tree of the process with pid
+#[get("/<pid>/<id>/proof", format = "json")]
+async fn get_inclusion_proof(api_key: ApiKey<IdsClaims, Empty>, db: &State<DataStore>, signing_key: &State<SigningKey>, pid: String, id: String) -> ApiResponse {
+    debug!("user '{:?}' with claims {:?}", api_key.sub(), api_key.claims());
+    debug!("trying to create inclusion proof for document with id '{}' for pid '{}'", &id, &pid);
+    let leaves = match get_leaf_hashes(db, &pid).await{
+        Ok(leaves) => leaves,
+        Err(e) => {
+            error!("Error while retrieving merkle leaves: {:?}", e);
+            return ApiResponse::InternalError(format!("Error while retrieving merkle tree for {}", &pid))
+        }
+    };
+    let leaf_index = match leaves.iter().position(|(leaf_id, _)| leaf_id == &id){
+        Some(index) => index,
+        None => {
+            debug!("Document not found in merkle tree!");
+            return ApiResponse::NotFound(format!("Document {} not found!", &id))
+        }
+    };
+
+    let leaf_hashes: Vec<Vec<u8>> = leaves.into_iter().map(|(_, h)| h).collect();
+    let path = audit_path(leaf_index, &leaf_hashes).iter().map(|h| encode_hash(h)).collect();
+    match sign_tree_head(signing_key, &pid, &leaf_hashes){
+        Ok(sth) => {
+            let proof = InclusionProof::new(pid, id, leaf_index, leaf_hashes.len(), encode_hash(&leaf_hashes[leaf_index]), path, sth);
+            ApiResponse::SuccessOk(json!(proof))
+        },
+        Err(e) => {
+            error!("Error while signing tree head: {:?}", e);
+            ApiResponse::InternalError(String::from("Error while signing tree head"))
+        }
+    }
+}
+
+/// Retrieve the proof that the merkle tree of size first of the process with pid is a prefix of the
+/// tree of size second. If second is not given, the current tree is used.
+#[get("/<pid>/consistency?<first>&<second>", format = "json")]
+async fn get_consistency_proof(api_key: ApiKey<IdsClaims, Empty>, db: &State<DataStore>, signing_key: &State<SigningKey>, pid: String, first: usize, second: Option<usize>) -> ApiResponse {
+    debug!("user '{:?}' with claims {:?}", api_key.sub(), api_key.claims());
+    debug!("trying to create consistency proof for pid '{}' between {} and {:?}", &pid, first, second);
+    let mut leaf_hashes: Vec<Vec<u8>> = match get_leaf_hashes(db, &pid).await{
+        Ok(leaves) => leaves.into_iter().map(|(_, h)| h).collect(),
+        Err(e) => {
+            error!("Error while retrieving merkle leaves: {:?}", e);
+            return ApiResponse::InternalError(format!("Error while retrieving merkle tree for {}", &pid))
+        }
+    };
+    let second = second.unwrap_or(leaf_hashes.len());
+    if first > second || second > leaf_hashes.len(){
+        warn!("Invalid tree sizes {} and {} for tree of size {}", first, second, leaf_hashes.len());
+        return ApiResponse::BadRequest(format!("Invalid tree sizes for process {}", &pid))
+    }
+
+    leaf_hashes.truncate(second);
+    let proof = consistency_proof(first, &leaf_hashes).iter().map(|h| encode_hash(h)).collect();
+    match sign_tree_head(signing_key, &pid, &leaf_hashes){
+        Ok(sth) => ApiResponse::SuccessOk(json!(ConsistencyProof::new(pid, first, second, proof, sth))),
+        Err(e) => {
+            error!("Error while signing tree head: {:?}", e);
+            ApiResponse::InternalError(String::from("Error while signing tree head"))
+        }
+    }
+}
+
+/// Verify the hash chain of all documents of the process with pid
+#[get("/<pid>/verify", format = "json")]
+async fn verify_process_chain(api_key: ApiKey<IdsClaims, Empty>, db: &State<DataStore>, pid: String) -> ApiResponse {
+    debug!("user '{:?}' with claims {:?}", api_key.sub(), api_key.claims());
+    debug!("trying to verify chain for pid '{}'", &pid);
+    match db.get_documents_for_pid(&pid).await{
+        Ok((cts, unreadable)) => {
+            let report = verify_chain(&pid, &cts).with_unreadable_documents(unreadable);
+            if !report.valid{
+                warn!("Chain verification for pid '{}' failed!", &pid);
+            }
+            ApiResponse::SuccessOk(json!(report))
+        },
+        Err(e) => {
+            error!("Error while retrieving documents: {:?}", e);
+            ApiResponse::InternalError(format!("Error while retrieving documents for {}", &pid))
+        }
+    }
+}
+
+/// Public keys of the clearing house, used to verify signed receipts and tree heads
+#[get("/", format = "json")]
+async fn get_jwks(signing_key: &State<SigningKey>) -> ApiResponse {
+    ApiResponse::SuccessOk(json!(signing_key.jwks()))
+}
+
 pub fn mount_api() -> AdHoc {
     AdHoc::on_ignite("Mounting Document API", |rocket| async {
         rocket
-            .mount(ROCKET_DOC_API, routes![create_enc_document, delete_document,
-                                            get_enc_document, get_enc_documents_for_pid])
+            .mount(ROCKET_DOC_API, routes![create_enc_document, create_enc_documents, create_enc_document_from_ids_message, create_enc_document_with_large_payload, delete_document,
+                                            get_enc_document, get_large_payload, get_enc_documents_for_pid, stream_enc_documents_for_pid, get_consistency_proof, get_inclusion_proof,
+                                            get_tree_head, verify_process_chain])
+            .mount(ROCKET_JWKS, routes![get_jwks])
     })
 }
\ No newline at end of file
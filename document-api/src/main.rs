@@ -1,18 +1,40 @@
+// the errors of error_chain are large, the apis take `&String` throughout and the routes take a guard per managed state
+#![allow(clippy::result_large_err, clippy::ptr_arg, clippy::too_many_arguments)]
+
 #[macro_use] extern crate rocket;
+#[macro_use] extern crate serde_derive;
 
 use core_lib::api::client::{ApiClientConfigurator, ApiClientEnum};
+use core_lib::constants::{CONNECTOR_ID, HASH_ALGORITHM, SIGNING_KEY};
+use core_lib::model::document::HashVersion;
+use core_lib::signing::SigningKey;
 use core_lib::util::setup_logger;
 use rocket::fairing::AdHoc;
 use rocket::http::Method;
 use rocket::{Rocket, Build};
+use std::path::Path;
 use rocket_cors::{
     AllowedHeaders, AllowedOrigins,
     CorsOptions
 };
 use crate::db::DatastoreConfigurator;
+use crate::model::ids::IdsConfig;
 
+// each route of rocket 0.5.0-rc.1 re-exports its uri macro, which is never used
+#[allow(unused_imports)]
+mod anchoring;
+#[allow(unused_imports)]
 mod doc_api;
 mod db;
+mod model;
+#[allow(unused_imports)]
+mod scanner;
+mod timestamping;
+
+/// identity of the clearing house in IDS messages if `connector_id` is not configured
+const DEFAULT_CONNECTOR_ID: &str = "urn:ids:clearing-house";
+/// location of the signing key if `signing_key` is not configured
+const DEFAULT_SIGNING_KEY: &str = "keys/private_key.der";
 
 fn add_cors_options() ->  AdHoc {
     AdHoc::on_ignite("Adding CORS rules", |rocket| async {
@@ -45,6 +67,7 @@ fn add_cors_options() ->  AdHoc {
                 "Accept",
                 "Authorization",
                 "Content-Type",
+                "Idempotency-Key",
                 "Origin"
             ]),
             allow_credentials: true,
@@ -58,6 +81,53 @@ fn add_cors_options() ->  AdHoc {
     })
 }
 
+fn add_signing_key() -> AdHoc {
+    AdHoc::try_on_ignite("Loading Signing Key", |rocket| async {
+        let key_file: String = rocket.figment().extract_inner(SIGNING_KEY).unwrap_or(String::from(DEFAULT_SIGNING_KEY));
+        debug!("Using signing key: '{}'", &key_file);
+        let key = if Path::new(&key_file).exists() {
+            SigningKey::from_file(&key_file)
+        }
+        else {
+            warn!("Signing key '{}' does not exist. Generating a new signing key, its public key is published as JWKS.", &key_file);
+            SigningKey::generate(&key_file)
+        };
+        match key {
+            Ok(key) => Ok(rocket.manage(key)),
+            Err(e) => {
+                error!("Error while loading signing key: {:?}", e);
+                Err(rocket)
+            }
+        }
+    })
+}
+
+fn add_hash_version() -> AdHoc {
+    AdHoc::try_on_ignite("Configuring Hash Algorithm", |rocket| async {
+        let algorithm: Option<String> = rocket.figment().extract_inner(HASH_ALGORITHM).ok();
+        let hash_version = match algorithm {
+            Some(a) => match a.parse::<HashVersion>() {
+                Ok(version) => version,
+                Err(e) => {
+                    error!("Error while configuring hash algorithm: {:?}", e);
+                    return Err(rocket)
+                }
+            },
+            None => HashVersion::CURRENT
+        };
+        debug!("Hashing new documents with '{}'", hash_version);
+        Ok(rocket.manage(hash_version))
+    })
+}
+
+fn add_ids_config() -> AdHoc {
+    AdHoc::on_ignite("Configuring IDS Messages", |rocket| async {
+        let connector_id: String = rocket.figment().extract_inner(CONNECTOR_ID).unwrap_or(String::from(DEFAULT_CONNECTOR_ID));
+        debug!("Answering IDS messages as connector '{}'", &connector_id);
+        rocket.manage(IdsConfig::new(connector_id))
+    })
+}
+
 #[launch]
 fn rocket() -> Rocket<Build> {
     // setup logging
@@ -66,7 +136,13 @@ fn rocket() -> Rocket<Build> {
     rocket::build()
         .attach(doc_api::mount_api())
         .attach(add_cors_options())
+        .attach(add_signing_key())
+        .attach(add_hash_version())
+        .attach(add_ids_config())
+        .attach(timestamping::add_timestamping())
         .attach(DatastoreConfigurator)
         .attach(ApiClientConfigurator::new(ApiClientEnum::Daps))
         .attach(ApiClientConfigurator::new(ApiClientEnum::Keyring))
+        .attach(anchoring::add_anchoring())
+        .attach(scanner::add_scanner())
 }
\ No newline at end of file
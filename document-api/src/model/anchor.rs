@@ -0,0 +1,16 @@
+/// Latest chain hash of a process that was anchored in the ledger.
+/// `tree_size` is the number of documents of the process at that time, i.e. the tc of the anchored document + 1.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct AnchoredHead {
+    pub pid: String,
+    pub tree_size: i64,
+}
+
+impl AnchoredHead {
+    pub fn new(pid: String, tree_size: i64) -> AnchoredHead {
+        AnchoredHead {
+            pid,
+            tree_size,
+        }
+    }
+}
@@ -0,0 +1,26 @@
+use core_lib::constants::IDEMPOTENCY_KEY_HEADER;
+use rocket::http::Status;
+use rocket::outcome::Outcome;
+use rocket::request::{self, FromRequest, Request};
+
+/// maximum length of an idempotency key
+const MAX_KEY_LENGTH: usize = 255;
+
+/// Value of the `Idempotency-Key` header, which identifies the retries of a request.
+/// Requests without the header have no key, requests with an invalid key are rejected.
+pub struct IdempotencyKey(pub Option<String>);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for IdempotencyKey {
+    type Error = String;
+
+    async fn from_request(request: &'r Request<'_>) -> request::Outcome<Self, String> {
+        match request.headers().get_one(IDEMPOTENCY_KEY_HEADER) {
+            Some(key) if key.is_empty() || key.len() > MAX_KEY_LENGTH => {
+                Outcome::Failure((Status::BadRequest, format!("{} must have between 1 and {} characters", IDEMPOTENCY_KEY_HEADER, MAX_KEY_LENGTH)))
+            },
+            Some(key) => Outcome::Success(IdempotencyKey(Some(String::from(key)))),
+            None => Outcome::Success(IdempotencyKey(None))
+        }
+    }
+}
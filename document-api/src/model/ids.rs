@@ -0,0 +1,95 @@
+use core_lib::model::new_uuid;
+use rocket::data::Limits;
+use rocket::form::{self, DataField, FromFormField, ValueField};
+use rocket::http::{ContentType, Status};
+use rocket::request::Request;
+use rocket::response::{self, Responder, Response};
+use serde_json::Value;
+use std::io::Cursor;
+
+/// Identity of the clearing house in the IDS messages it answers with
+pub struct IdsConfig {
+    pub connector_id: String,
+}
+
+impl IdsConfig {
+    pub fn new(connector_id: String) -> IdsConfig {
+        IdsConfig {
+            connector_id,
+        }
+    }
+}
+
+/// Content of a part of a multipart message. The content type is only known for parts that were sent as file.
+pub struct MultipartPart {
+    pub content_type: Option<ContentType>,
+    pub content: Vec<u8>,
+}
+
+#[rocket::async_trait]
+impl<'r> FromFormField<'r> for MultipartPart {
+    fn from_value(field: ValueField<'r>) -> form::Result<'r, Self> {
+        Ok(MultipartPart {
+            content_type: None,
+            content: field.value.as_bytes().to_vec(),
+        })
+    }
+
+    async fn from_data(field: DataField<'r, '_>) -> form::Result<'r, Self> {
+        let limit = field.request.limits().get("data-form").unwrap_or(Limits::DATA_FORM);
+        let bytes = field.data.open(limit).into_bytes().await?;
+        if !bytes.is_complete() {
+            Err((None, Some(limit)))?;
+        }
+        Ok(MultipartPart {
+            content_type: Some(field.content_type),
+            content: bytes.into_inner(),
+        })
+    }
+}
+
+/// IDS message in the multipart format: the JSON-LD message header and an optional payload
+#[derive(FromForm)]
+pub struct IdsMultipartMessage {
+    pub header: MultipartPart,
+    pub payload: Option<MultipartPart>,
+}
+
+/// IDS message sent as answer in the multipart format
+pub struct IdsMultipartResponse {
+    pub status: Status,
+    pub header: Value,
+    pub payload: Option<(ContentType, String)>,
+}
+
+impl IdsMultipartResponse {
+    pub fn new(status: Status, header: Value, payload: Option<(ContentType, String)>) -> IdsMultipartResponse {
+        IdsMultipartResponse {
+            status,
+            header,
+            payload,
+        }
+    }
+
+    fn format_part(boundary: &str, name: &str, content_type: &ContentType, content: &str) -> String {
+        format!("--{}\r\nContent-Disposition: form-data; name=\"{}\"\r\nContent-Type: {}\r\n\r\n{}\r\n", boundary, name, content_type, content)
+    }
+}
+
+impl<'r> Responder<'r, 'static> for IdsMultipartResponse {
+    fn respond_to(self, _: &'r Request<'_>) -> response::Result<'static> {
+        let boundary = new_uuid();
+        let header_type = ContentType::new("application", "ld+json");
+        let mut body = IdsMultipartResponse::format_part(&boundary, "header", &header_type, &self.header.to_string());
+        if let Some((content_type, content)) = &self.payload {
+            body.push_str(&IdsMultipartResponse::format_part(&boundary, "payload", content_type, content));
+        }
+        body.push_str(&format!("--{}--\r\n", boundary));
+
+        Response::build()
+            .status(self.status)
+            .header(ContentType::with_params("multipart", "form-data", ("boundary", boundary)))
+            .sized_body(body.len(), Cursor::new(body))
+            .ok()
+    }
+}
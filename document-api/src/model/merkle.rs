@@ -0,0 +1,19 @@
+/// Leaf of the Merkle tree of a process. `hash` is the chain hash of the document with `id`.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct MerkleLeaf {
+    pub pid: String,
+    pub tc: i64,
+    pub id: String,
+    pub hash: String,
+}
+
+impl MerkleLeaf {
+    pub fn new(pid: String, tc: i64, id: String, hash: String) -> MerkleLeaf {
+        MerkleLeaf {
+            pid,
+            tc,
+            id,
+            hash,
+        }
+    }
+}
@@ -0,0 +1,5 @@
+pub mod anchor;
+pub mod idempotency;
+pub mod ids;
+pub mod merkle;
+pub mod transaction;
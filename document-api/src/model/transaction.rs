@@ -0,0 +1,24 @@
+/// Head of the hash chain of a single process.
+/// `tc` is the transaction counter that will be assigned to the next document and `hash` is the
+/// chain hash it has to carry, i.e. the hash of the latest document of the process.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct TransactionCounter {
+    pub pid: String,
+    pub tc: i64,
+    pub hash: String,
+}
+
+impl TransactionCounter {
+    pub fn new(pid: String, tc: i64, hash: String) -> TransactionCounter {
+        TransactionCounter {
+            pid,
+            tc,
+            hash,
+        }
+    }
+
+    /// counter of a process without any documents
+    pub fn new_chain(pid: String) -> TransactionCounter {
+        TransactionCounter::new(pid, 0, String::from("0"))
+    }
+}
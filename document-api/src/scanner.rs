@@ -0,0 +1,111 @@
+use std::time::Duration;
+use biscuit::Empty;
+use chrono::Utc;
+use core_lib::api::ApiResponse;
+use core_lib::api::auth::ApiKey;
+use core_lib::api::claims::IdsClaims;
+use core_lib::constants::{ADMINS, ROCKET_ADMIN_API, SCAN_INTERVAL};
+use core_lib::errors::*;
+use core_lib::model::alert::{scan_documents, unreadable_document_alerts};
+use rocket::fairing::AdHoc;
+use rocket::serde::json::json;
+use rocket::{State, tokio};
+use crate::db::DataStore;
+
+/// Subjects of the tokens that are allowed to use the admin endpoints
+pub struct Admins(Vec<String>);
+
+impl Admins{
+    pub fn new(subjects: Vec<String>) -> Admins{
+        Admins(subjects)
+    }
+
+    pub fn contains(&self, sub: &Option<String>) -> bool{
+        match sub{
+            Some(sub) => self.0.contains(sub),
+            None => false
+        }
+    }
+}
+
+/// Mounts the admin endpoint for the alerts of the tamper detection and, if `scan_interval` is
+/// configured, periodically verifies the chains and the structure of all stored documents.
+pub fn add_scanner() -> AdHoc {
+    AdHoc::try_on_ignite("Configuring Tamper Detection", |rocket| async {
+        let admins: Vec<String> = rocket.figment().extract_inner(ADMINS).unwrap_or_default();
+        if admins.is_empty(){
+            info!("No {} configured. Admin endpoints are not accessible.", ADMINS);
+        }
+        let rocket = rocket
+            .manage(Admins::new(admins))
+            .mount(ROCKET_ADMIN_API, routes![get_alerts]);
+        let interval: u64 = match rocket.figment().extract_inner(SCAN_INTERVAL){
+            Ok(interval) => interval,
+            Err(_) => {
+                info!("No {} configured. Tamper detection disabled.", SCAN_INTERVAL);
+                return Ok(rocket)
+            }
+        };
+        if interval == 0 {
+            error!("{} has to be at least one second", SCAN_INTERVAL);
+            return Err(rocket)
+        }
+        debug!("Scanning documents every {} seconds", interval);
+        Ok(rocket.attach(AdHoc::on_liftoff("Starting Tamper Detection", move |rocket| Box::pin(async move {
+            match rocket.state::<DataStore>(){
+                Some(db) => {
+                    tokio::spawn(scan_periodically(db.clone(), interval));
+                },
+                None => error!("Database not available. Tamper detection disabled.")
+            }
+        }))))
+    })
+}
+
+/// Retrieve the alerts recorded by the tamper detection, optionally only for the process with pid
+#[get("/alerts?<pid>", format = "json")]
+async fn get_alerts(api_key: ApiKey<IdsClaims, Empty>, admins: &State<Admins>, db: &State<DataStore>, pid: Option<String>) -> ApiResponse {
+    debug!("user '{:?}' with claims {:?}", api_key.sub(), api_key.claims());
+    if !admins.contains(&api_key.sub()){
+        warn!("user '{:?}' is not allowed to retrieve alerts", api_key.sub());
+        return ApiResponse::Unauthorized(String::from("Only admins are allowed to retrieve alerts"))
+    }
+    match db.get_alerts(pid).await{
+        Ok(alerts) => ApiResponse::SuccessOk(json!(alerts)),
+        Err(e) => {
+            error!("Error while retrieving alerts: {:?}", e);
+            ApiResponse::InternalError(String::from("Error while retrieving alerts"))
+        }
+    }
+}
+
+async fn scan_periodically(db: DataStore, interval: u64) {
+    let mut timer = tokio::time::interval(Duration::from_secs(interval));
+    loop {
+        timer.tick().await;
+        if let Err(e) = scan(&db).await{
+            error!("Error while scanning documents: {:?}", e);
+        }
+    }
+}
+
+/// verifies the stored documents process by process and records the violations found, including
+/// the documents that could not be read
+pub async fn scan(db: &DataStore) -> Result<usize> {
+    debug!("Scanning documents...");
+    let ts = Utc::now().timestamp();
+    let mut scanned = 0;
+    let mut found = 0;
+    for pid in db.get_pids().await?{
+        let (docs, unreadable) = db.get_documents_for_pid(&pid).await?;
+        let mut alerts = scan_documents(&docs, ts);
+        alerts.extend(unreadable_document_alerts(&pid, &unreadable, ts));
+        for alert in alerts.iter(){
+            db.add_alert(alert).await?;
+        }
+        scanned += docs.len() + unreadable.len();
+        found += alerts.len();
+    }
+    debug!("... scanned {} documents and found {} violations", scanned, found);
+    Ok(found)
+}
@@ -0,0 +1,60 @@
+use core_lib::api::ApiClient;
+use core_lib::api::client::tsa_api::TsaApiClient;
+use core_lib::constants::{TSA_CERTIFICATE, TSA_URL};
+use core_lib::errors::*;
+use core_lib::timestamp::{verify_timestamp_token, TimestampToken};
+use rocket::fairing::AdHoc;
+
+/// RFC 3161 time-stamp authority and its DER encoded certificate
+struct Tsa {
+    client: TsaApiClient,
+    certificate: Vec<u8>,
+}
+
+/// Obtains time-stamp tokens over new chain hashes. Timestamping is disabled if no TSA is configured.
+pub struct Timestamping {
+    tsa: Option<Tsa>,
+}
+
+impl Timestamping {
+    /// Obtains a time-stamp token over the chain hash and checks that the TSA issued it for this request.
+    /// Returns `None` if timestamping is disabled.
+    pub async fn timestamp(&self, chain_hash: &str) -> Result<Option<TimestampToken>> {
+        let tsa = match &self.tsa {
+            Some(tsa) => tsa,
+            None => return Ok(None)
+        };
+        let (token, nonce) = tsa.client.get_timestamp_token(chain_hash).await?;
+        let info = verify_timestamp_token(&token, chain_hash, &tsa.certificate)?;
+        if info.nonce.as_ref() != Some(&nonce) {
+            return Err(Error::from("Time-stamp token was not issued for this request"));
+        }
+        debug!("obtained time-stamp token with serial {} for chain hash '{}'", &info.serial, chain_hash);
+        Ok(Some(TimestampToken::new(chain_hash.to_string(), &token)))
+    }
+}
+
+pub fn add_timestamping() -> AdHoc {
+    AdHoc::try_on_ignite("Configuring Timestamping", |rocket| async {
+        let tsa_url: String = rocket.figment().extract_inner(TSA_URL).unwrap_or(String::new());
+        if tsa_url.is_empty() {
+            info!("No {} configured. Timestamping disabled.", TSA_URL);
+            return Ok(rocket.manage(Timestamping { tsa: None }))
+        }
+        let cert_file: String = rocket.figment().extract_inner(TSA_CERTIFICATE).unwrap_or(String::new());
+        debug!("Using TSA '{}' with certificate '{}'", &tsa_url, &cert_file);
+        match std::fs::read(&cert_file) {
+            Ok(certificate) => {
+                let tsa = Tsa {
+                    client: TsaApiClient::new(&tsa_url),
+                    certificate,
+                };
+                Ok(rocket.manage(Timestamping { tsa: Some(tsa) }))
+            },
+            Err(e) => {
+                error!("Error while loading TSA certificate: {:?}", e);
+                Err(rocket)
+            }
+        }
+    })
+}
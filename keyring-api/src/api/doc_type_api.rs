@@ -18,13 +18,13 @@ async fn create_doc_type(db: &State<KeyStore>, doc_type: Json<DocumentType>) ->
                 Ok(()) => ApiResponse::SuccessCreate(json!(doc_type)),
                 Err(e) => {
                     error!("Error while adding doctype: {:?}", e);
-                    return ApiResponse::InternalError(String::from("Error while adding document type!"))
+                    ApiResponse::InternalError(String::from("Error while adding document type!"))
                 }
             }
         },
         Err(e) => {
             error!("Error while adding document type: {:?}", e);
-            return ApiResponse::InternalError(String::from("Error while checking database!"))
+            ApiResponse::InternalError(String::from("Error while checking database!"))
         }
     }
 }
@@ -39,13 +39,13 @@ async fn update_doc_type(db: &State<KeyStore>, id: String, doc_type: Json<Docume
                 Ok(id) => ApiResponse::SuccessOk(json!(id)),
                 Err(e) => {
                     error!("Error while adding doctype: {:?}", e);
-                    return ApiResponse::InternalError(String::from("Error while storing document type!"))
+                    ApiResponse::InternalError(String::from("Error while storing document type!"))
                 }
             }
         },
         Err(e) => {
             error!("Error while adding document type: {:?}", e);
-            return ApiResponse::InternalError(String::from("Error while checking database!"))
+            ApiResponse::InternalError(String::from("Error while checking database!"))
         }
     }
 }
@@ -91,7 +91,7 @@ async fn get_doc_types(db: &State<KeyStore>) -> ApiResponse {
         Ok(dt) => ApiResponse::SuccessOk(json!(dt)),
         Err(e) => {
             error!("Error while retrieving default doctypes: {:?}", e);
-            ApiResponse::InternalError(format!("Error while retrieving all document types"))
+            ApiResponse::InternalError("Error while retrieving all document types".to_string())
         }
     }
 }
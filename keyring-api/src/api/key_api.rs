@@ -3,11 +3,15 @@ use core_lib::api::ApiResponse;
 use core_lib::api::auth::ApiKey;
 use core_lib::api::claims::IdsClaims;
 use core_lib::constants::ROCKET_KEYRING_API;
+use core_lib::util::split_list;
 use rocket::fairing::AdHoc;
 use rocket::State;
 use rocket::serde::json::{json, Json};
 
+use std::collections::HashMap;
+
 use crate::db::KeyStore;
+use crate::model::doc_type::DocumentType;
 use crate::crypto::{generate_key_map, restore_key_map};
 use core_lib::model::crypto::{KeyCtList, KeyMapListItem};
 
@@ -24,29 +28,73 @@ async fn generate_keys(api_key: ApiKey<IdsClaims, Empty>, db: &State<KeyStore>,
                     match generate_key_map(key, dt) {
                         Ok(key_map) => {
                             debug!("response: {:?}", &key_map);
-                            return ApiResponse::SuccessCreate(json!(key_map));
+                            ApiResponse::SuccessCreate(json!(key_map))
                         },
                         Err(e) => {
                             error!("Error while generating key map: {}", e);
-                            return ApiResponse::InternalError(String::from("Error while generating keys"));
+                            ApiResponse::InternalError(String::from("Error while generating keys"))
                         }
                     }
                 }
                 Ok(None) =>{
                     warn!("document type {} not found", &dt_id);
-                    return ApiResponse::BadRequest(String::from("Document type not found!"));
+                    ApiResponse::BadRequest(String::from("Document type not found!"))
                 }
                 Err(e) => {
                     warn!("Error while retrieving document type: {}", e);
-                    return ApiResponse::InternalError(String::from("Error while retrieving document type"));
+                    ApiResponse::InternalError(String::from("Error while retrieving document type"))
                 }
             }
         }
+        Err(e) => {
+            error!("Error while retrieving master key: {}", e);
+            ApiResponse::InternalError(String::from("Error while generating keys"))
+        }
+    }
+}
+
+#[post("/generate_keys/<_pid>", format = "json", data = "<dt_ids>")]
+async fn generate_multiple_keys(api_key: ApiKey<IdsClaims, Empty>, db: &State<KeyStore>, _pid: String, dt_ids: Json<Vec<String>>) -> ApiResponse {
+    debug!("user '{:?}' with claims {:?}", api_key.sub(), api_key.claims());
+    let dt_ids = dt_ids.into_inner();
+    debug!("number of key maps: {}", dt_ids.len());
+    let key = match db.get_msk().await{
+        Ok(key) => key,
         Err(e) => {
             error!("Error while retrieving master key: {}", e);
             return ApiResponse::InternalError(String::from("Error while generating keys"));
         }
+    };
+
+    // each document type is only retrieved once
+    let mut doc_types: HashMap<String, DocumentType> = HashMap::new();
+    let mut key_maps = vec!();
+    for dt_id in dt_ids.iter(){
+        if !doc_types.contains_key(dt_id){
+            match db.get_document_type(dt_id).await{
+                Ok(Some(dt)) => {
+                    doc_types.insert(dt_id.clone(), dt);
+                },
+                Ok(None) => {
+                    warn!("document type {} not found", dt_id);
+                    return ApiResponse::BadRequest(format!("Document type '{}' not found!", dt_id));
+                },
+                Err(e) => {
+                    warn!("Error while retrieving document type: {}", e);
+                    return ApiResponse::InternalError(String::from("Error while retrieving document type"));
+                }
+            }
+        }
+        // generate new random key map
+        match generate_key_map(key.clone(), doc_types[dt_id].clone()){
+            Ok(key_map) => key_maps.push(key_map),
+            Err(e) => {
+                error!("Error while generating key map: {}", e);
+                return ApiResponse::InternalError(String::from("Error while generating keys"));
+            }
+        }
     }
+    ApiResponse::SuccessCreate(json!(key_maps))
 }
 
 #[get("/decrypt_keys/<_pid>", format = "json", data = "<key_cts>")]
@@ -70,20 +118,20 @@ async fn decrypt_keys(api_key: ApiKey<IdsClaims, Empty>, db: &State<KeyStore>, _
                                 Ok(key) => Some((key_ct.id.clone(), key)),
                                 Err(e) => {
                                     error!("Error while decoding key ciphertext: {}", e);
-                                    dec_error_count = dec_error_count + 1;
+                                    dec_error_count += 1;
                                     None
                                 }
                             }
                         }
                     ).filter_map(
                             |(id, key)| {
-                                match restore_key_map(m_key.clone(), dt.clone(), key){
+                                match restore_key_map(m_key.clone(), dt.clone(), key, cts.parts.as_deref()){
                                     Ok(key_map) => {
                                         Some(KeyMapListItem::new(id, key_map))
                                     },
                                     Err(e) => {
                                         error!("Error while generating key map: {}", e);
-                                        map_error_count = map_error_count + 1;
+                                        map_error_count += 1;
                                         None
                                     }
                                 }
@@ -95,32 +143,33 @@ async fn decrypt_keys(api_key: ApiKey<IdsClaims, Empty>, db: &State<KeyStore>, _
 
                     // Currently, we don't tolerate errors while decrypting keys
                     if error_count > 0 {
-                        return ApiResponse::InternalError(String::from("Error while decrypting keys"));
+                        ApiResponse::InternalError(String::from("Error while decrypting keys"))
                     }
                     else{
-                        return ApiResponse::SuccessOk(json!(key_maps));
+                        ApiResponse::SuccessOk(json!(key_maps))
                     }
                 }
                 Ok(None) =>{
                     warn!("document type {} not found", &cts.dt);
-                    return ApiResponse::BadRequest(String::from("Document type not found!"));
+                    ApiResponse::BadRequest(String::from("Document type not found!"))
                 }
                 Err(e) => {
                     warn!("Error while retrieving document type: {}", e);
-                    return ApiResponse::NotFound(String::from("Document type not found!"));
+                    ApiResponse::NotFound(String::from("Document type not found!"))
                 }
             }
         }
         Err(e) => {
             error!("Error while retrieving master key: {}", e);
-            return ApiResponse::InternalError(String::from("Error while decrypting keys"));
+            ApiResponse::InternalError(String::from("Error while decrypting keys"))
         }
     }
 
 }
 
-#[get("/decrypt_keys/<_pid>/<keys_ct>?<dt_id>", format = "json")]
-async fn decrypt_key_map(api_key: ApiKey<IdsClaims, Empty>, db: &State<KeyStore>, keys_ct: String, _pid: Option<String>, dt_id: String) -> ApiResponse {
+/// Restores the keys of a document. `parts` is a comma separated list of the parts whose keys are restored, all parts if not given.
+#[get("/decrypt_keys/<_pid>/<keys_ct>?<dt_id>&<parts>", format = "json")]
+async fn decrypt_key_map(api_key: ApiKey<IdsClaims, Empty>, db: &State<KeyStore>, keys_ct: String, _pid: Option<String>, dt_id: String, parts: Option<String>) -> ApiResponse {
     debug!("user '{:?}' with claims {:?}", api_key.sub(), api_key.claims());
     debug!("ct: {}", &keys_ct);
     // get master key
@@ -138,29 +187,30 @@ async fn decrypt_key_map(api_key: ApiKey<IdsClaims, Empty>, db: &State<KeyStore>
                         }
                     };
 
-                    match restore_key_map(key, dt, keys_ct){
+                    let parts = parts.map(|parts| split_list(&parts));
+                    match restore_key_map(key, dt, keys_ct, parts.as_deref()){
                         Ok(key_map) => {
-                            return ApiResponse::SuccessOk(json!(key_map));
+                            ApiResponse::SuccessOk(json!(key_map))
                         },
                         Err(e) => {
                             error!("Error while generating key map: {}", e);
-                            return ApiResponse::InternalError(String::from("Error while restoring keys"));
+                            ApiResponse::InternalError(String::from("Error while restoring keys"))
                         }
                     }
                 }
                 Ok(None) =>{
                     warn!("document type {} not found", &dt_id);
-                    return ApiResponse::BadRequest(String::from("Document type not found!"));
+                    ApiResponse::BadRequest(String::from("Document type not found!"))
                 }
                 Err(e) => {
                     warn!("Error while retrieving document type: {}", e);
-                    return ApiResponse::NotFound(String::from("Document type not found!"));
+                    ApiResponse::NotFound(String::from("Document type not found!"))
                 }
             }
         }
         Err(e) => {
             error!("Error while retrieving master key: {}", e);
-            return ApiResponse::InternalError(String::from("Error while decrypting keys"));
+            ApiResponse::InternalError(String::from("Error while decrypting keys"))
         }
     }
 }
@@ -168,6 +218,6 @@ async fn decrypt_key_map(api_key: ApiKey<IdsClaims, Empty>, db: &State<KeyStore>
 pub fn mount_api() -> AdHoc {
     AdHoc::on_ignite("Mounting Keyring API", |rocket| async {
         rocket
-            .mount(ROCKET_KEYRING_API, routes![decrypt_key_map, decrypt_keys, generate_keys])
+            .mount(ROCKET_KEYRING_API, routes![decrypt_key_map, decrypt_keys, generate_keys, generate_multiple_keys])
     })
 }
\ No newline at end of file
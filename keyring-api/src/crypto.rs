@@ -1,8 +1,6 @@
-use aes_gcm_siv::Aes256GcmSiv;
-use aes_gcm_siv::aead::{Aead, NewAead};
+use aes_gcm_siv::aead::Aead;
 use core_lib::errors::*;
-use core_lib::model::crypto::{KeyEntry, KeyMap};
-use generic_array::GenericArray;
+use core_lib::model::crypto::{cipher_nonce, new_cipher, KeyEntry, KeyMap};
 use hkdf::Hkdf;
 use openssl::rand::rand_bytes;
 use sha2::Sha256;
@@ -27,20 +25,25 @@ pub fn generate_random_seed() -> Vec<u8>{
     buf.to_vec()
 }
 
-fn derive_key_map(kdf: Hkdf<Sha256>, dt: DocumentType, enc: bool) -> HashMap<String, KeyEntry>{
+/// Derives the keys of the parts of the document type. The id of a key is the position of its part in the document type.
+/// If `parts` is given, only the keys of these parts are derived.
+fn derive_key_map(kdf: Hkdf<Sha256>, dt: DocumentType, enc: bool, parts: Option<&[String]>) -> HashMap<String, KeyEntry>{
     let mut key_map = HashMap::new();
     let mut okm = [0u8; EXP_BUFF_SIZE];
     let mut i = 0;
     dt.parts.iter()
         .for_each( |p| {
-            if kdf.expand(p.name.clone().as_bytes(), &mut okm).is_ok() {
+            let selected = parts.is_none_or(|parts| parts.contains(&p.name));
+            if selected && kdf.expand(p.name.clone().as_bytes(), &mut okm).is_ok() {
                 let map_key = match enc{
                     true => p.name.clone(),
                     false => i.to_string()
                 };
-                key_map.insert(map_key, KeyEntry::new(i.to_string(), okm[..EXP_KEY_SIZE].to_vec(), okm[EXP_KEY_SIZE..].to_vec()));
+                let mut key_entry = KeyEntry::new(i.to_string(), okm[..EXP_KEY_SIZE].to_vec(), okm[EXP_KEY_SIZE..].to_vec());
+                key_entry.compression = dt.part_compression(p);
+                key_map.insert(map_key, key_entry);
             }
-            i = i +1;
+            i += 1;
         });
     key_map
 }
@@ -48,7 +51,7 @@ fn derive_key_map(kdf: Hkdf<Sha256>, dt: DocumentType, enc: bool) -> HashMap<Str
 pub fn generate_key_map(mkey: MasterKey, dt: DocumentType) -> Result<KeyMap>{
     debug!("generating encryption key_map for doc type: '{}'", &dt.id);
     let (secret, doc_kdf) = initialize_kdf();
-    let key_map = derive_key_map(doc_kdf, dt, true);
+    let key_map = derive_key_map(doc_kdf, dt, true, None);
 
     debug!("encrypting the key seed");
     let kdf = restore_kdf(&mkey.key)?;
@@ -65,7 +68,8 @@ pub fn generate_key_map(mkey: MasterKey, dt: DocumentType) -> Result<KeyMap>{
     }
 }
 
-pub fn restore_key_map(mkey: MasterKey, dt: DocumentType, keys_ct: Vec<u8>) -> Result<KeyMap>{
+/// Restores the keys of a document from the ct of its key seed. If `parts` is given, only the keys of these parts are restored.
+pub fn restore_key_map(mkey: MasterKey, dt: DocumentType, keys_ct: Vec<u8>, parts: Option<&[String]>) -> Result<KeyMap>{
     debug!("decrypting the key seed");
     let kdf = restore_kdf(&mkey.key)?;
     let mut okm = [0u8; EXP_BUFF_SIZE];
@@ -76,7 +80,7 @@ pub fn restore_key_map(mkey: MasterKey, dt: DocumentType, keys_ct: Vec<u8>) -> R
     match decrypt_secret(&okm[..EXP_KEY_SIZE], &okm[EXP_KEY_SIZE..], &keys_ct){
         Ok(key_seed) => {
             // generate new random key map
-            restore_keys(&key_seed, dt)
+            restore_keys(&key_seed, dt, parts)
         }
         Err(e) => {
             error!("Error while decrypting key ciphertext: {}", e);
@@ -85,10 +89,10 @@ pub fn restore_key_map(mkey: MasterKey, dt: DocumentType, keys_ct: Vec<u8>) -> R
     }
 }
 
-pub fn restore_keys(secret: &String, dt: DocumentType) -> Result<KeyMap>{
+pub fn restore_keys(secret: &String, dt: DocumentType, parts: Option<&[String]>) -> Result<KeyMap>{
     debug!("restoring decryption key_map for doc type: '{}'", &dt.id);
     let kdf = restore_kdf(secret)?;
-    let key_map = derive_key_map(kdf, dt, false);
+    let key_map = derive_key_map(kdf, dt, false, parts);
 
     Ok(KeyMap::new(false, key_map, None))
 }
@@ -124,9 +128,8 @@ pub fn encrypt_secret(key: &[u8], nonce: &[u8], secret: String) -> Result<Vec<u8
         bail!("Incorrect nonce size")
     }
     else{
-        let key = GenericArray::from_slice(key);
-        let nonce = GenericArray::from_slice(nonce);
-        let cipher = Aes256GcmSiv::new(key);
+        let nonce = cipher_nonce(nonce);
+        let cipher = new_cipher(key);
 
         match cipher.encrypt(nonce, secret.as_bytes()){
             Ok(ct) => {
@@ -140,9 +143,8 @@ pub fn encrypt_secret(key: &[u8], nonce: &[u8], secret: String) -> Result<Vec<u8
 pub fn decrypt_secret(key: &[u8], nonce: &[u8], ct: &[u8]) -> Result<String>{
     debug!("key len = {}", key.len());
     debug!("ct len = {}", ct.len());
-    let key = GenericArray::from_slice(key);
-    let nonce = GenericArray::from_slice(nonce);
-    let cipher = Aes256GcmSiv::new(key);
+    let nonce = cipher_nonce(nonce);
+    let cipher = new_cipher(key);
 
     debug!("key: {}", hex::encode_upper(key));
     debug!("nonce: {}", hex::encode_upper(nonce));
@@ -54,7 +54,7 @@ impl Fairing for KeyringDbConfigurator {
         };
         debug!("Using database url: '{:#?}'", &db_url);
 
-        match init_database_client::<KeyStore>(&db_url.as_str(), Some(KEYRING_DB_CLIENT.to_string())).await {
+        match init_database_client::<KeyStore>(db_url.as_str(), Some(KEYRING_DB_CLIENT.to_string())).await {
             Ok(keystore) => {
                 debug!("Check if database is empty...");
                 match keystore.client.database(KEYRING_DB)
@@ -62,7 +62,7 @@ impl Fairing for KeyringDbConfigurator {
                     .await {
                     Ok(colls) => {
                         debug!("... found collections: {:#?}", &colls);
-                        if colls.len() > 0 && clear_db {
+                        if !colls.is_empty() && clear_db {
                             debug!("Database not empty and clear_db == true. Dropping database...");
                             match keystore.client.database(KEYRING_DB).drop(None).await {
                                 Ok(_) => {
@@ -74,10 +74,10 @@ impl Fairing for KeyringDbConfigurator {
                                 }
                             };
                         }
-                        if colls.len() == 0 || clear_db {
+                        if colls.is_empty() || clear_db {
                             debug!("Database empty. Need to initialize...");
                             debug!("Adding initial document type...");
-                            match serde_json::from_str::<DocumentType>(&read_file(FILE_DEFAULT_DOC_TYPE).unwrap_or(String::new())) {
+                            match serde_json::from_str::<DocumentType>(&read_file(FILE_DEFAULT_DOC_TYPE).unwrap_or_default()) {
                                 Ok(dt) => {
                                     match keystore.add_document_type(dt).await {
                                         Ok(_) => {
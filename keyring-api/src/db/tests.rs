@@ -9,7 +9,7 @@ use mongodb::Client;
 use crate::db::{DataStoreApi, KeyStore};
 use crate::model::doc_type::DocumentType;
 
-const DATABASE_URL: &'static str = "mongodb://127.0.0.1:27018";
+const DATABASE_URL: &str = "mongodb://127.0.0.1:27018";
 
 async fn db_setup() -> KeyStore {
     let client = Client::with_uri_str(DATABASE_URL).await.unwrap();
@@ -33,7 +33,7 @@ async fn test_document_type_exists() -> Result<()>{
     db.add_document_type(dt.clone()).await?;
 
     // run the test: db should find document type
-    assert_eq!(db.exists_document_type(&dt.pid, &dt.id).await?, true);
+    assert!(db.exists_document_type(&dt.pid, &dt.id).await?);
 
     // clean up
     tear_down(db).await;
@@ -54,7 +54,7 @@ async fn test_document_type_exists_for_other_pid() -> Result<()>{
     db.add_document_type(dt.clone()).await?;
 
     // run the test: db should not find the document type
-    assert_eq!(db.exists_document_type(&wrong_pid, &dt.id).await?, false);
+    assert!(!db.exists_document_type(&wrong_pid, &dt.id).await?);
 
     // clean up
     tear_down(db).await;
@@ -78,7 +78,7 @@ async fn test_delete_document_type_correct_pid() -> Result<()>{
     db.delete_document_type(&dt.id, &dt.pid).await?;
 
     // db should not find document type
-    assert_eq!(db.exists_document_type(&dt.pid, &dt.id).await?, false);
+    assert!(!db.exists_document_type(&dt.pid, &dt.id).await?);
 
     // clean up
     tear_down(db).await;
@@ -101,7 +101,7 @@ async fn test_delete_document_type_wrong_pid() -> Result<()>{
     db.delete_document_type(&dt.id, &wrong_pid).await?;
 
     // db should still find document type
-    assert_eq!(db.exists_document_type(&dt.pid, &dt.id).await?, true);
+    assert!(db.exists_document_type(&dt.pid, &dt.id).await?);
 
     // clean up
     tear_down(db).await;
@@ -1,3 +1,6 @@
+// the errors of error_chain are large and the apis take `&String` throughout
+#![allow(clippy::result_large_err, clippy::ptr_arg)]
+
 #[macro_use] extern crate error_chain;
 #[macro_use] extern crate rocket;
 #[macro_use] extern crate serde_derive;
@@ -7,6 +10,8 @@ use rocket::{Build, Rocket};
 use crate::db::KeyringDbConfigurator;
 use core_lib::api::client::{ApiClientConfigurator, ApiClientEnum};
 
+// each route of rocket 0.5.0-rc.1 re-exports its uri macro, which is never used
+#[allow(unused_imports)]
 mod api;
 mod db;
 mod crypto;
@@ -11,6 +11,7 @@ pub struct MasterKey {
 }
 
 impl MasterKey{
+    #[cfg(test)]
     pub fn new(id: String, key: String, salt: String)-> MasterKey{
         MasterKey{
             id,
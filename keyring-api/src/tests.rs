@@ -1,13 +1,16 @@
 use core_lib::errors::*;
-use crate::model::doc_type::{DocumentType, DocumentTypePart};
+use core_lib::model::document::Compression;
+use crate::model::doc_type::DocumentType;
+use core_lib::model::doc_type::DocumentTypePart;
 use crate::crypto::{encrypt_secret, decrypt_secret, generate_key_map, restore_key_map};
 use crate::model::crypto::MasterKey;
 
 fn create_test_document_type() -> DocumentType{
-    let mut parts = vec!();
-    parts.push(DocumentTypePart::new(String::from("name")));
-    parts.push(DocumentTypePart::new(String::from("message")));
-    parts.push(DocumentTypePart::new(String::from("connector")));
+    let parts = vec!(
+        DocumentTypePart::new(String::from("name")),
+        DocumentTypePart::new(String::from("message")),
+        DocumentTypePart::new(String::from("connector"))
+    );
 
     DocumentType::new(String::from("test_dt_1"), String::from("test_pid_1"), parts)
 }
@@ -25,7 +28,7 @@ fn test_key_generation() -> Result<()>{
     let keys = generate_key_map(mkey, dt)?;
 
     // Keymap generated for encryption
-    assert_eq!(keys.enc, true);
+    assert!(keys.enc);
 
     // there should be 3 items in the hash map
     assert_eq!(keys.keys.len(), 3);
@@ -43,6 +46,32 @@ fn test_key_generation() -> Result<()>{
     Ok(())
 }
 
+#[test]
+fn test_key_generation_with_compression() -> Result<()>{
+    // prepare test data: compression of the document type, overridden for one part
+    let mut dt = create_test_document_type();
+    dt.compression = Some(Compression::Deflate);
+    dt.parts[1].compression = Some(Compression::Zstd);
+    let k = String::from("C36D50B35B5981C8F1FAD6738848BD5A4F77EF77B56A4E66F7961B9B7A642B2B");
+    let salt = String::from("A6E804FF70117E606686EDD8516C95734E239453AB52AC6E3F916D1D861412B5");
+    let id = String::from("86177e93-29aa-477a-b63f-03ccd9c5679d");
+    let mkey = MasterKey::new(id, k, salt);
+
+    // run the test
+    let keys = generate_key_map(mkey, dt)?;
+
+    // check
+    assert_eq!(keys.keys.get("name").unwrap().compression, Some(Compression::Deflate));
+    assert_eq!(keys.keys.get("message").unwrap().compression, Some(Compression::Zstd));
+    assert_eq!(keys.keys.get("connector").unwrap().compression, Some(Compression::Deflate));
+
+    // compression is not part of the stored document type, unless it is configured
+    let json = serde_json::to_string(&create_test_document_type()).unwrap();
+    assert!(!json.contains("compression"));
+
+    Ok(())
+}
+
 #[test]
 fn test_restoring_keymap() -> Result<()>{
     // prepare test data
@@ -53,21 +82,23 @@ fn test_restoring_keymap() -> Result<()>{
     let id = String::from("86177e93-29aa-477a-b63f-03ccd9c5679d");
     let mkey = MasterKey::new(id, k, salt);
 
-    let mut expected_keys = vec!();
-    expected_keys.push(hex::decode("0FCBA316FA47AC0E3EFF4D69B7780925ED22CFF46FC1A731B4E9942FED67BA04").unwrap());
-    expected_keys.push(hex::decode("DE888EF80B13390CA76387F18528F3B3948B8C446D70C09F7C2A1D2346CFE917").unwrap());
-    expected_keys.push(hex::decode("2E6953A92D081C5189DED6FB9644606257A2839CD2159F77166DF246E236B67C").unwrap());
+    let expected_keys = [
+        hex::decode("0FCBA316FA47AC0E3EFF4D69B7780925ED22CFF46FC1A731B4E9942FED67BA04").unwrap(),
+        hex::decode("DE888EF80B13390CA76387F18528F3B3948B8C446D70C09F7C2A1D2346CFE917").unwrap(),
+        hex::decode("2E6953A92D081C5189DED6FB9644606257A2839CD2159F77166DF246E236B67C").unwrap()
+    ];
 
-    let mut expected_nonces = vec!();
-    expected_nonces.push(hex::decode("6A63BE704DC9687FA3FDFF26").unwrap());
-    expected_nonces.push(hex::decode("D0E2744835BD2FFECFFA9AE6").unwrap());
-    expected_nonces.push(hex::decode("83587A962A24F94D907CF2B7").unwrap());
+    let expected_nonces = [
+        hex::decode("6A63BE704DC9687FA3FDFF26").unwrap(),
+        hex::decode("D0E2744835BD2FFECFFA9AE6").unwrap(),
+        hex::decode("83587A962A24F94D907CF2B7").unwrap()
+    ];
 
     // run the test
-    let result = restore_key_map(mkey, dt, keys_ct)?;
+    let result = restore_key_map(mkey, dt, keys_ct, None)?;
 
     // Keymap generated for decryption
-    assert_eq!(result.enc, false);
+    assert!(!result.enc);
 
     // there should be 3 items in the hash map
     assert_eq!(result.keys.len(), 3);
@@ -83,6 +114,31 @@ fn test_restoring_keymap() -> Result<()>{
 }
 
 
+#[test]
+fn test_restoring_keys_of_selected_parts() -> Result<()>{
+    // prepare test data
+    let dt = create_test_document_type();
+    let k = String::from("C36D50B35B5981C8F1FAD6738848BD5A4F77EF77B56A4E66F7961B9B7A642B2B");
+    let salt = String::from("5DEC9D8C1B09A5E2A35DD5A0E6B1B1EDEB32B4C3C4C1C8EB3D5EDA0EB3A3CB7F");
+    let mkey = MasterKey::new(String::from("86177e93-29aa-477a-b63f-03ccd9c5679d"), k, salt);
+    let key_map = generate_key_map(mkey.clone(), dt.clone())?;
+    let keys_ct = key_map.keys_enc.clone().unwrap();
+
+    // run the test
+    let all = restore_key_map(mkey.clone(), dt.clone(), keys_ct.clone(), None)?;
+    let parts = vec!(String::from("connector"), String::from("unknown"));
+    let selected = restore_key_map(mkey, dt, keys_ct, Some(&parts))?;
+
+    // only the key of the connector is restored, with the id of the connector in the document type
+    assert_eq!(selected.keys.len(), 1);
+    let key = &selected.keys["2"];
+    assert_eq!(key.id, String::from("2"));
+    assert_eq!(key.key, all.keys["2"].key);
+    assert_eq!(key.nonce, all.keys["2"].nonce);
+
+    Ok(())
+}
+
 #[test]
 fn test_encrypting_secret() -> Result<()>{
     // prepare test data